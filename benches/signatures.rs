@@ -0,0 +1,79 @@
+//! Verification throughput of ECDSA and Schnorr signed transactions.
+//!
+//! Run with `cargo bench --bench signatures`.
+
+mod transaction {
+    include!("../src/transaction.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use crypto::SignatureScheme;
+use transaction::{Transaction, TransactionKind};
+use types::Amount;
+use wallet::Wallet;
+
+const CHAIN_ID: &str = "chainrust-bench";
+const BLOCK_SIZES: [usize; 3] = [1, 100, 1000];
+
+fn signed(scheme: SignatureScheme, count: usize) -> Vec<Transaction> {
+    let receiver = Wallet::new().address;
+    (0..count)
+        .map(|_| Transaction::with_scheme(CHAIN_ID, scheme, TransactionKind::Transfer, &receiver, Amount::new(1), &Wallet::new().private_key))
+        .collect()
+}
+
+fn verify_single(c: &mut Criterion) {
+    let mut group = c.benchmark_group("verify");
+    for scheme in [SignatureScheme::Ecdsa, SignatureScheme::Schnorr] {
+        let tx = signed(scheme, 1).remove(0);
+        group.throughput(Throughput::Elements(1));
+        group.bench_function(format!("{:?}", scheme).to_lowercase(), |b| b.iter(|| assert!(tx.verify(CHAIN_ID))));
+    }
+    group.finish();
+}
+
+fn verify_block(c: &mut Criterion) {
+    let mut group = c.benchmark_group("verify_block");
+    group.sample_size(10);
+    for size in BLOCK_SIZES {
+        group.throughput(Throughput::Elements(size as u64));
+        let ecdsa = signed(SignatureScheme::Ecdsa, size);
+        let schnorr = signed(SignatureScheme::Schnorr, size);
+        for (scheme, txs) in [("ecdsa", &ecdsa), ("schnorr", &schnorr)] {
+            group.bench_with_input(BenchmarkId::new(scheme, size), txs, |b, txs| {
+                b.iter(|| assert!(txs.iter().all(|tx| tx.verify(CHAIN_ID))))
+            });
+            group.bench_with_input(BenchmarkId::new(format!("{}_parallel", scheme), size), txs, |b, txs| {
+                b.iter(|| assert!(Transaction::verify_batch(txs, CHAIN_ID)))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, verify_single, verify_block);
+criterion_main!(benches);
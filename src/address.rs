@@ -0,0 +1,96 @@
+use bech32::primitives::decode::CheckedHrpstring;
+use bech32::{Bech32m, Hrp};
+use serde::{Serialize, Deserialize, Deserializer, Serializer};
+use sha2::{Sha256, Digest};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::openapi::schema::{ObjectBuilder, Schema, SchemaFormat, SchemaType};
+use utoipa::openapi::RefOr;
+
+/// Human-readable prefix of every ChainRust address.
+pub const ADDRESS_HRP: &str = "crt";
+/// Length of the public key hash an address encodes.
+pub const ADDRESS_HASH_LEN: usize = 20;
+
+/// An account identifier: the first 20 bytes of SHA-256 over a compressed public key,
+/// bech32m encoded with the `crt` prefix so typos are caught by the checksum.
+///
+/// Deserializing parses the address, so malformed input is rejected before it reaches
+/// any handler or chain state.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Address(String);
+
+#[allow(dead_code)]
+impl Address {
+    /// Derives the address of a hex encoded compressed public key.
+    pub fn from_public_key(public_key: &str) -> Option<Address> {
+        let bytes = hex::decode(public_key).ok()?;
+        if bytes.len() != 33 {
+            return None;
+        }
+        Some(Self::from_digest(&Sha256::digest(&bytes)))
+    }
+
+    /// Encodes the first 20 bytes of a SHA-256 digest as an address.
+    pub fn from_digest(digest: &[u8]) -> Address {
+        let hrp = Hrp::parse(ADDRESS_HRP).expect("Valid address prefix");
+        let encoded = bech32::encode::<Bech32m>(hrp, &digest[..ADDRESS_HASH_LEN]).expect("Address fits in bech32");
+        Address(encoded)
+    }
+
+    /// Parses an address, checking the prefix, checksum and payload length.
+    pub fn parse(address: &str) -> Result<Address, String> {
+        let checked = CheckedHrpstring::new::<Bech32m>(address)
+            .map_err(|e| format!("Invalid address {}: {}", address, e))?;
+        if checked.hrp().to_lowercase() != ADDRESS_HRP {
+            return Err(format!("Invalid address {}: expected prefix {}", address, ADDRESS_HRP));
+        }
+        if checked.byte_iter().count() != ADDRESS_HASH_LEN {
+            return Err(format!("Invalid address {}: wrong length", address));
+        }
+        Ok(Address(address.to_lowercase()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Address {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Address::parse(s)
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let address = String::deserialize(deserializer)?;
+        Address::parse(&address).map_err(serde::de::Error::custom)
+    }
+}
+
+impl<'s> utoipa::ToSchema<'s> for Address {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        let schema = ObjectBuilder::new()
+            .schema_type(SchemaType::String)
+            .format(Some(SchemaFormat::Custom("bech32m".to_string())))
+            .pattern(Some(format!("^{}1[qpzry9x8gf2tvdw0s3jn54khce6mua7l]{{38}}$", ADDRESS_HRP)))
+            .description(Some("bech32m encoded hash of a secp256k1 public key"))
+            .example(Some(serde_json::json!("crt1e2a8x9jchwr2gcvs6uftae6kg2k3qh7x7u27sr")));
+        ("Address", schema.into())
+    }
+}
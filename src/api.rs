@@ -1,15 +1,37 @@
 use actix_web::{web, HttpResponse};
+use std::collections::HashSet;
+use chrono::Utc;
+use log::info;
 use utoipa::OpenApi;
-use crate::models::{AppState, FaucetRequest, TransactionRequest, BalanceRequest};
+use crate::address::Address;
+use crate::contract::{self, Contract, MAX_CONTRACT_GAS};
+use crate::crypto::{self, SignatureScheme};
+use crate::keystore::{EncryptedKey, KdfParams, Keystore, KeystoreEntry};
+use crate::multisig::{MultisigAuth, MultisigPolicy, MultisigSignature, PartiallySignedTransaction};
+use crate::nft::{Collection, Nft, NftTransfer};
+use crate::peers::Handshake;
+use crate::receipt::{Receipt, ReceiptStatus};
+use crate::script::{Script, ScriptAuth};
+use crate::models::{AppState, FaucetRequest, TransactionRequest, BatchTransactionRequest, BalanceRequest, StakeRequest, EvidenceRequest, HtlcLockRequest, HtlcClaimRequest, HtlcRefundRequest, HtlcQuery, RevealedPreimage, EscrowCreateRequest, EscrowApprovalRequest, TokenCreateRequest, TokenTransferRequest, TokenBurnRequest, NftCollectionRequest, NftMintRequest, NftTransferRequest, ContractDeployRequest, DeployedContract, ContractCallRequest, ContractQuery, ContractOutput, NodeInfo, WalletRequest, WalletInfo, ImportKeyRequest, ExportKeyRequest, MultisigAddressRequest, MultisigAccount, ScriptAddressRequest, ScriptAccount, TransactionDetails, MemoSearchQuery};
 use crate::blockchain::Blockchain;
-use crate::block::Block;
-use crate::transaction::Transaction;
+use crate::escrow::{Escrow, EscrowDecision, EscrowStatus};
+use crate::genesis::LedgerModel;
+use crate::htlc::{Htlc, HtlcStatus};
+use crate::block::{Block, SignedHeader};
+use crate::staking::{Slash, Staking, Unbonding};
+use crate::token::Token;
+use crate::transaction::{BatchOutput, Gas, TimeLock, Transaction, TransactionKind, TransactionStatus, TxInput};
+use crate::types::{Amount, Hash};
+use crate::utxo::{select_coins, OutPoint, UnspentOutput, Utxo};
 use crate::wallet::Wallet;
 
+/// Blocks a faucet grant stays valid for; every Mint must expire, on either ledger.
+const FAUCET_GRANT_TTL: u64 = 1_000;
+
 #[derive(OpenApi)]
 #[openapi(
-    paths(get_chain, add_block, faucet, add_transaction, create_wallet, check_balance),
-    components(schemas(Block, Transaction, Wallet, Blockchain, FaucetRequest, TransactionRequest, BalanceRequest))
+    paths(get_chain, add_block, faucet, add_transaction, add_batch_transaction, submit_signed_transaction, list_utxos, transaction_status, search_transactions, multisig_address, script_address, create_wallet, list_keys, import_key, export_key, check_balance, next_nonce, list_tokens, token_balance, bond, unbond, get_validators, submit_evidence, lock_htlc, claim_htlc, refund_htlc, list_htlcs, htlc_preimage, create_escrow, release_escrow, refund_escrow, get_escrow, create_token, transfer_token, mint_token, burn_token, create_collection, mint_nft, transfer_nft, list_nfts, get_nft, nft_owner, nft_history, deploy_contract, execute_contract, call_contract, get_contract, get_receipt, node_info, add_peer, list_peers),
+    components(schemas(Address, Amount, Hash, Block, Transaction, TransactionKind, BatchOutput, TxInput, OutPoint, Utxo, UnspentOutput, LedgerModel, SignatureScheme, TimeLock, TransactionStatus, TransactionDetails, Wallet, WalletInfo, Blockchain, Staking, Unbonding, Slash, Htlc, HtlcStatus, RevealedPreimage, Escrow, EscrowDecision, EscrowStatus, Token, Collection, Nft, NftTransfer, Contract, Gas, Receipt, ReceiptStatus, SignedHeader, FaucetRequest, TransactionRequest, BatchTransactionRequest, BalanceRequest, StakeRequest, EvidenceRequest, HtlcLockRequest, HtlcClaimRequest, HtlcRefundRequest, EscrowCreateRequest, EscrowApprovalRequest, TokenCreateRequest, TokenTransferRequest, TokenBurnRequest, NftCollectionRequest, NftMintRequest, NftTransferRequest, ContractDeployRequest, DeployedContract, ContractCallRequest, ContractQuery, ContractOutput, NodeInfo, Handshake, WalletRequest, ImportKeyRequest, ExportKeyRequest, KeystoreEntry, EncryptedKey, KdfParams, MultisigPolicy, MultisigSignature, MultisigAuth, PartiallySignedTransaction, MultisigAddressRequest, MultisigAccount, Script, ScriptAuth, ScriptAddressRequest, ScriptAccount))
 )]
 pub struct ApiDoc;
 
@@ -17,13 +39,87 @@ pub struct ApiDoc;
 #[utoipa::path(
     post,
     path = "/wallet",
+    request_body = WalletRequest,
     responses(
-        (status = 200, description = "Wallet created successfully", body = Wallet)
+        (status = 200, description = "Key created and stored encrypted in the keystore", body = WalletInfo),
+        (status = 400, description = "No password given outside dev mode")
     )
 )]
-pub async fn create_wallet(_: web::Data<AppState>) -> impl actix_web::Responder {
+pub async fn create_wallet(state: web::Data<AppState>, req: Option<web::Json<WalletRequest>>) -> impl actix_web::Responder {
     let wallet = Wallet::new();
-    HttpResponse::Ok().json(wallet)
+    match req {
+        Some(req) => {
+            let stored = wallet.clone();
+            if let Err(e) = with_keystore(&state, move |keystore| keystore.insert(&stored, &req.password)).await {
+                return HttpResponse::BadRequest().json(e);
+            }
+        }
+        None if !state.dev_mode => return HttpResponse::BadRequest().json("A password is required to store the new key"),
+        None => {}
+    }
+    // Plaintext keys only ever leave the node in dev mode
+    if state.dev_mode {
+        return HttpResponse::Ok().json(wallet);
+    }
+    HttpResponse::Ok().json(WalletInfo { address: wallet.address, public_key: wallet.public_key })
+}
+
+/// Runs a keystore operation on the blocking thread pool: Argon2 takes a noticeable part
+/// of a second and would otherwise hold up every request on the worker.
+async fn with_keystore<T: Send + 'static>(
+    state: &AppState,
+    operation: impl FnOnce(&Keystore) -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    let keystore = state.keystore.clone();
+    web::block(move || operation(&keystore)).await.map_err(|e| e.to_string())?
+}
+
+#[utoipa::path(
+    get,
+    path = "/keys",
+    responses(
+        (status = 200, description = "Addresses of the keys in the keystore", body = Vec<Address>)
+    )
+)]
+pub async fn list_keys(state: web::Data<AppState>) -> impl actix_web::Responder {
+    match state.keystore.list() {
+        Ok(addresses) => HttpResponse::Ok().json(addresses),
+        Err(e) => HttpResponse::InternalServerError().json(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/keys/import",
+    request_body = ImportKeyRequest,
+    responses(
+        (status = 200, description = "Key encrypted and stored; returns its address", body = Address),
+        (status = 400, description = "Invalid private key or the key is already stored")
+    )
+)]
+pub async fn import_key(state: web::Data<AppState>, req: web::Json<ImportKeyRequest>) -> impl actix_web::Responder {
+    let req = req.into_inner();
+    match with_keystore(&state, move |keystore| keystore.import(&req.private_key, &req.password)).await {
+        Ok(address) => HttpResponse::Ok().json(address),
+        Err(e) => HttpResponse::BadRequest().json(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/keys/export",
+    request_body = ExportKeyRequest,
+    responses(
+        (status = 200, description = "Encrypted keystore entry; the key itself is never returned in plaintext", body = KeystoreEntry),
+        (status = 400, description = "Unknown address or wrong password")
+    )
+)]
+pub async fn export_key(state: web::Data<AppState>, req: web::Json<ExportKeyRequest>) -> impl actix_web::Responder {
+    let req = req.into_inner();
+    match with_keystore(&state, move |keystore| keystore.export(&req.address, &req.password)).await {
+        Ok(entry) => HttpResponse::Ok().json(entry),
+        Err(e) => HttpResponse::BadRequest().json(e),
+    }
 }
 
 #[utoipa::path(
@@ -31,24 +127,42 @@ pub async fn create_wallet(_: web::Data<AppState>) -> impl actix_web::Responder
     path = "/faucet",
     request_body = FaucetRequest,
     responses(
-        (status = 200, description = "Funds added to address", body = String)
+        (status = 200, description = "Mint transaction added to mempool", body = String),
+        (status = 400, description = "Malformed address or the faucet grant is not valid"),
+        (status = 403, description = "Faucet is disabled on this network or node"),
+        (status = 429, description = "Address is in cooldown or the daily cap is reached")
     )
 )]
 pub async fn faucet(state: web::Data<AppState>, req: web::Json<FaucetRequest>) -> impl actix_web::Responder {
-    let mut blockchain = state.blockchain.lock().unwrap();
-    const FAUCET_AMOUNT: u64 = 100;
-    blockchain.add_funds(&req.address, FAUCET_AMOUNT);
-    HttpResponse::Ok().json(format!("Added {} funds to {}", FAUCET_AMOUNT, req.address))
+    let blockchain = state.blockchain.lock().unwrap();
+    let (config, key) = match (&blockchain.genesis.faucet, &state.faucet_key) {
+        (Some(config), Some(key)) if blockchain.genesis.is_dev() => (config.clone(), key),
+        _ => return HttpResponse::Forbidden().json("Faucet is disabled on this network"),
+    };
+    let mut transaction = Transaction::mint(blockchain.chain_id(), &req.address, config.amount, key).expiring_at(blockchain.next_height() + FAUCET_GRANT_TTL, key);
+    let mut mempool = state.mempool.lock().unwrap();
+    let nonce = blockchain.next_nonce(&transaction.sender, &mempool);
+    transaction = transaction.with_nonce(nonce, key);
+    if let Err(e) = blockchain.validate_pending(&transaction, &mempool) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let mut limiter = state.faucet_limiter.lock().unwrap();
+    if let Err(e) = limiter.check_and_record(&config, &req.address, Utc::now().timestamp()) {
+        return HttpResponse::TooManyRequests().json(e);
+    }
+    mempool.push(transaction);
+    HttpResponse::Ok().json(format!("Minting {} funds to {} in the next block", config.amount, req.address))
 }
 
 #[utoipa::path(
     get,
     path = "/check_balance",
     params(
-        ("address" = String, Query, description = "The blockchain address to check balance for")
+        ("address" = Address, Query, description = "The blockchain address to check balance for")
     ),
     responses(
-        (status = 200, description = "Balance retrieved successfully", body = u64)
+        (status = 200, description = "Balance retrieved successfully", body = Amount),
+        (status = 400, description = "Malformed address")
     )
 )]
 pub async fn check_balance(
@@ -60,6 +174,72 @@ pub async fn check_balance(
     HttpResponse::Ok().json(balance)
 }
 
+#[utoipa::path(
+    get,
+    path = "/nonce",
+    params(
+        ("address" = Address, Query, description = "Sender to number the next transaction of")
+    ),
+    responses(
+        (status = 200, description = "Nonce the next transaction from the address must carry, counting those in the mempool", body = u64),
+        (status = 400, description = "Malformed address")
+    )
+)]
+pub async fn next_nonce(state: web::Data<AppState>, query: web::Query<BalanceRequest>) -> impl actix_web::Responder {
+    let blockchain = state.blockchain.lock().unwrap();
+    let mempool = state.mempool.lock().unwrap();
+    HttpResponse::Ok().json(blockchain.next_nonce(&query.address, &mempool))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tokens",
+    responses(
+        (status = 200, description = "Every token issued on chain with its current supply, ordered by symbol", body = Vec<Token>)
+    )
+)]
+pub async fn list_tokens(state: web::Data<AppState>) -> impl actix_web::Responder {
+    let blockchain = state.blockchain.lock().unwrap();
+    HttpResponse::Ok().json(blockchain.tokens.list())
+}
+
+#[utoipa::path(
+    get,
+    path = "/tokens/{id}/balance",
+    params(
+        ("id" = Hash, Path, description = "Id of the transaction that created the token"),
+        ("address" = Address, Query, description = "The blockchain address to check balance for")
+    ),
+    responses(
+        (status = 200, description = "Units of the token the address holds", body = Amount),
+        (status = 400, description = "Malformed id or address"),
+        (status = 404, description = "No token with this id")
+    )
+)]
+pub async fn token_balance(state: web::Data<AppState>, id: web::Path<Hash>, query: web::Query<BalanceRequest>) -> impl actix_web::Responder {
+    let blockchain = state.blockchain.lock().unwrap();
+    if blockchain.tokens.get(&id).is_none() {
+        return HttpResponse::NotFound().json(format!("No token {}", id));
+    }
+    HttpResponse::Ok().json(blockchain.tokens.balance(&id, &query.address))
+}
+
+
+#[utoipa::path(
+    get,
+    path = "/utxos",
+    params(
+        ("address" = Address, Query, description = "Owner of the outputs")
+    ),
+    responses(
+        (status = 200, description = "Unspent outputs the address can spend; always empty on an account ledger", body = Vec<UnspentOutput>),
+        (status = 400, description = "Malformed address")
+    )
+)]
+pub async fn list_utxos(state: web::Data<AppState>, query: web::Query<BalanceRequest>) -> impl actix_web::Responder {
+    let blockchain = state.blockchain.lock().unwrap();
+    HttpResponse::Ok().json(blockchain.utxos.owned_by(&query.address))
+}
 
 #[utoipa::path(
     post,
@@ -67,53 +247,828 @@ pub async fn check_balance(
     request_body = TransactionRequest,
     responses(
         (status = 200, description = "Transaction added to mempool", body = String),
-        (status = 400, description = "Invalid transaction or insufficient funds")
+        (status = 400, description = "Malformed request, invalid transaction or insufficient funds")
     )
 )]
 pub async fn add_transaction(state: web::Data<AppState>, req: web::Json<TransactionRequest>) -> impl actix_web::Responder {
-    let transaction = Transaction::new(&req.sender, &req.receiver, req.amount, &req.private_key);
-    if !transaction.verify() {
-        return HttpResponse::BadRequest().json("Invalid transaction signature");
+    if let Err(e) = check_signer(&req.sender, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let transaction = match ledger(&state) {
+        LedgerModel::Account => Ok(Transaction::with_scheme(&chain_id(&state), req.scheme, TransactionKind::Transfer, &req.receiver, req.amount, &req.private_key)),
+        LedgerModel::Utxo => {
+            let outputs = vec![BatchOutput { receiver: req.receiver.clone(), amount: req.amount }];
+            spend_from(&state, &req.sender, req.scheme, outputs, req.memo.as_deref(), &req.private_key)
+        }
+    };
+    match transaction {
+        Ok(transaction) => submit_transaction(&state, with_conditions(transaction, req.valid_after, req.expires_at_height, req.memo.as_deref(), &req.private_key), &req.private_key),
+        Err(e) => HttpResponse::BadRequest().json(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/transaction/batch",
+    request_body = BatchTransactionRequest,
+    responses(
+        (status = 200, description = "Batch transfer added to mempool; returns its id", body = Hash),
+        (status = 400, description = "Malformed request, invalid outputs or insufficient funds for the total")
+    )
+)]
+pub async fn add_batch_transaction(state: web::Data<AppState>, req: web::Json<BatchTransactionRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.sender, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let transaction = match ledger(&state) {
+        LedgerModel::Account => Transaction::batch_with_scheme(&chain_id(&state), req.scheme, req.outputs.clone(), &req.private_key),
+        LedgerModel::Utxo => spend_from(&state, &req.sender, req.scheme, req.outputs.clone(), req.memo.as_deref(), &req.private_key),
+    };
+    let transaction = match transaction {
+        Ok(transaction) => with_conditions(transaction, req.valid_after, req.expires_at_height, req.memo.as_deref(), &req.private_key),
+        Err(e) => return HttpResponse::BadRequest().json(e),
+    };
+    submit_for_id(&state, transaction, &req.private_key)
+}
+
+#[utoipa::path(
+    post,
+    path = "/transaction/signed",
+    request_body = Transaction,
+    responses(
+        (status = 200, description = "Transaction added to mempool; returns its id", body = Hash),
+        (status = 400, description = "Malformed transaction, invalid signature or insufficient funds")
+    )
+)]
+pub async fn submit_signed_transaction(state: web::Data<AppState>, req: web::Json<Transaction>) -> impl actix_web::Responder {
+    let transaction = req.into_inner();
+    let id = transaction.id();
+    let blockchain = state.blockchain.lock().unwrap();
+    let mut mempool = state.mempool.lock().unwrap();
+    if mempool.iter().any(|tx| tx.id() == id) {
+        return HttpResponse::Ok().json(id);
+    }
+    if let Err(e) = blockchain.validate_pending(&transaction, &mempool) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    mempool.push(transaction);
+    HttpResponse::Ok().json(id)
+}
+
+#[utoipa::path(
+    get,
+    path = "/transaction/{id}",
+    params(
+        ("id" = Hash, Path, description = "Transaction id returned on submission")
+    ),
+    responses(
+        (status = 200, description = "Whether the transaction is pending, confirmed or unknown, with the transaction itself when known", body = TransactionDetails),
+        (status = 400, description = "Malformed id")
+    )
+)]
+pub async fn transaction_status(state: web::Data<AppState>, id: web::Path<Hash>) -> impl actix_web::Responder {
+    let id = id.into_inner();
+    let blockchain = state.blockchain.lock().unwrap();
+    if let Some((block, tx)) = blockchain.get_transaction(&id) {
+        return HttpResponse::Ok().json(confirmed(&blockchain, block, tx));
+    }
+    let mempool = state.mempool.lock().unwrap();
+    if let Some(tx) = mempool.iter().find(|tx| tx.id() == id) {
+        return HttpResponse::Ok().json(TransactionDetails { id, status: TransactionStatus::Pending, transaction: Some(tx.clone()) });
+    }
+    HttpResponse::Ok().json(TransactionDetails { id, status: TransactionStatus::Unknown, transaction: None })
+}
+
+fn confirmed(blockchain: &Blockchain, block: u64, tx: &Transaction) -> TransactionDetails {
+    let confirmations = blockchain.next_height() - block;
+    TransactionDetails {
+        id: tx.id(),
+        status: TransactionStatus::Confirmed { block, confirmations },
+        transaction: Some(tx.clone()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/transactions/search",
+    params(MemoSearchQuery),
+    responses(
+        (status = 200, description = "Pending, then confirmed transactions whose memo contains the text, newest first", body = Vec<TransactionDetails>),
+        (status = 400, description = "Empty search text")
+    )
+)]
+pub async fn search_transactions(state: web::Data<AppState>, query: web::Query<MemoSearchQuery>) -> impl actix_web::Responder {
+    if query.memo.is_empty() {
+        return HttpResponse::BadRequest().json("Search text must not be empty");
+    }
+    let limit = query.limit.unwrap_or(100);
+    let blockchain = state.blockchain.lock().unwrap();
+    let mempool = state.mempool.lock().unwrap();
+    let pending = mempool
+        .iter()
+        .rev()
+        .filter(|tx| tx.memo.as_ref().is_some_and(|memo| memo.contains(&query.memo)))
+        .map(|tx| TransactionDetails { id: tx.id(), status: TransactionStatus::Pending, transaction: Some(tx.clone()) });
+    let confirmed = blockchain.search_memos(&query.memo).into_iter().map(|(block, tx)| confirmed(&blockchain, block, tx));
+    HttpResponse::Ok().json(pending.chain(confirmed).take(limit).collect::<Vec<_>>())
+}
+
+#[utoipa::path(
+    post,
+    path = "/multisig/address",
+    request_body = MultisigAddressRequest,
+    responses(
+        (status = 200, description = "Address controlled by the m-of-n policy", body = MultisigAccount),
+        (status = 400, description = "Invalid threshold or public keys")
+    )
+)]
+pub async fn multisig_address(req: web::Json<MultisigAddressRequest>) -> impl actix_web::Responder {
+    match MultisigPolicy::new(req.threshold, &req.public_keys) {
+        Ok(policy) => HttpResponse::Ok().json(MultisigAccount { address: policy.address(), policy }),
+        Err(e) => HttpResponse::BadRequest().json(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/script/address",
+    request_body = ScriptAddressRequest,
+    responses(
+        (status = 200, description = "Address controlled by the locking script", body = ScriptAccount),
+        (status = 400, description = "Malformed or empty script, or one over the size limits")
+    )
+)]
+pub async fn script_address(req: web::Json<ScriptAddressRequest>) -> impl actix_web::Responder {
+    let script = req.into_inner().script;
+    // An empty locking script is satisfied by any unlocking script that pushes a true value
+    if script.ops.is_empty() {
+        return HttpResponse::BadRequest().json("Script is empty");
+    }
+    HttpResponse::Ok().json(ScriptAccount { address: script.address(), script })
+}
+
+/// Rejects private keys that do not belong to `address`.
+fn check_signer(address: &Address, private_key: &str) -> Result<(), String> {
+    let public_key = crypto::public_key_from_private(private_key).ok_or("Invalid private key")?;
+    if Address::from_public_key(&public_key).as_ref() != Some(address) {
+        return Err("Private key does not belong to the address".to_string());
+    }
+    Ok(())
+}
+
+fn chain_id(state: &AppState) -> String {
+    state.blockchain.lock().unwrap().chain_id().to_string()
+}
+
+fn ledger(state: &AppState) -> LedgerModel {
+    state.blockchain.lock().unwrap().ledger()
+}
+
+/// Gas up to `limit` at `price`, or the chain's minimum gas price if not set.
+fn gas(state: &AppState, limit: u64, price: Option<Amount>) -> Gas {
+    let price = price.unwrap_or_else(|| state.blockchain.lock().unwrap().genesis.consensus.min_gas_price);
+    Gas { limit, price }
+}
+
+/// Re-signs `transaction` with the optional conditions of a request.
+fn with_conditions(mut transaction: Transaction, valid_after: Option<TimeLock>, expires_at_height: Option<u64>, memo: Option<&str>, private_key: &str) -> Transaction {
+    if let Some(lock) = valid_after {
+        transaction = transaction.locked_until(lock, private_key);
+    }
+    if let Some(expiry) = expires_at_height {
+        transaction = transaction.expiring_at(expiry, private_key);
+    }
+    if let Some(memo) = memo {
+        transaction = transaction.with_memo(memo, private_key);
     }
+    transaction
+}
+
+/// Pays `outputs` on a UTXO ledger from coins of `sender`, selected to cover them plus the
+/// fee for `memo`. Coins spent by pending transactions are skipped, and change goes back
+/// to the sender.
+fn spend_from(state: &AppState, sender: &Address, scheme: SignatureScheme, mut outputs: Vec<BatchOutput>, memo: Option<&str>, private_key: &str) -> Result<Transaction, String> {
     let blockchain = state.blockchain.lock().unwrap();
-    let balance = blockchain.balances.get(&req.sender).unwrap_or(&0);
-    if *balance < req.amount {
-        return HttpResponse::BadRequest().json("Insufficient funds");
+    let mempool = state.mempool.lock().unwrap();
+    let pending: HashSet<&OutPoint> = mempool
+        .iter()
+        .filter_map(|tx| match &tx.kind {
+            TransactionKind::Spend { inputs, .. } => Some(inputs.iter().map(|input| &input.outpoint)),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    let coins: Vec<UnspentOutput> = blockchain.utxos.owned_by(sender).into_iter().filter(|coin| !pending.contains(&coin.outpoint)).collect();
+    let fee = blockchain.genesis.consensus.memo_fee_per_byte.checked_mul(memo.map_or(0, str::len) as u64).ok_or("Memo fee overflows")?;
+    let target = BatchOutput::total(&outputs).and_then(|total| total.checked_add(fee)).ok_or("Amount plus fee overflows")?;
+    let (inputs, change) = select_coins(&coins, target)?;
+    if !change.is_zero() {
+        outputs.push(BatchOutput { receiver: sender.clone(), amount: change });
     }
+    Transaction::spend(blockchain.chain_id(), scheme, inputs, outputs, private_key)
+}
+
+/// Re-signs `transaction` with the sender's next nonce after the mempool, validates it and
+/// queues it, returning its final id. Spends keep a zero nonce.
+fn enqueue(state: &AppState, mut transaction: Transaction, private_key: &str) -> Result<Hash, String> {
+    let blockchain = state.blockchain.lock().unwrap();
     let mut mempool = state.mempool.lock().unwrap();
+    if !matches!(transaction.kind, TransactionKind::Spend { .. }) {
+        let nonce = blockchain.next_nonce(&transaction.sender, &mempool);
+        transaction = transaction.with_nonce(nonce, private_key);
+    }
+    blockchain.validate_pending(&transaction, &mempool)?;
+    let id = transaction.id();
     mempool.push(transaction);
-    HttpResponse::Ok().json("Transaction added to mempool")
+    Ok(id)
+}
+
+fn submit_transaction(state: &AppState, transaction: Transaction, private_key: &str) -> HttpResponse {
+    match enqueue(state, transaction, private_key) {
+        Ok(_) => HttpResponse::Ok().json("Transaction added to mempool"),
+        Err(e) => HttpResponse::BadRequest().json(e),
+    }
+}
+
+/// Like `submit_transaction`, answering with the transaction id, which for some kinds
+/// names what the transaction creates.
+fn submit_for_id(state: &AppState, transaction: Transaction, private_key: &str) -> HttpResponse {
+    match enqueue(state, transaction, private_key) {
+        Ok(id) => HttpResponse::Ok().json(id),
+        Err(e) => HttpResponse::BadRequest().json(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/stake/bond",
+    request_body = StakeRequest,
+    responses(
+        (status = 200, description = "Bond transaction added to mempool", body = String),
+        (status = 400, description = "Invalid transaction or insufficient funds")
+    )
+)]
+pub async fn bond(state: web::Data<AppState>, req: web::Json<StakeRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.address, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let transaction = Transaction::bond(&chain_id(&state), req.amount, &req.private_key);
+    submit_transaction(&state, transaction, &req.private_key)
+}
+
+#[utoipa::path(
+    post,
+    path = "/stake/unbond",
+    request_body = StakeRequest,
+    responses(
+        (status = 200, description = "Unbond transaction added to mempool", body = String),
+        (status = 400, description = "Invalid transaction or insufficient stake")
+    )
+)]
+pub async fn unbond(state: web::Data<AppState>, req: web::Json<StakeRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.address, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let transaction = Transaction::unbond(&chain_id(&state), req.amount, &req.private_key);
+    submit_transaction(&state, transaction, &req.private_key)
+}
+
+#[utoipa::path(
+    post,
+    path = "/evidence",
+    request_body = EvidenceRequest,
+    responses(
+        (status = 200, description = "Evidence transaction added to mempool", body = String),
+        (status = 400, description = "Headers do not prove equivocation or the offender has no stake")
+    )
+)]
+pub async fn submit_evidence(state: web::Data<AppState>, req: web::Json<EvidenceRequest>) -> impl actix_web::Responder {
+    let req = req.into_inner();
+    if let Err(e) = check_signer(&req.reporter, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    match Transaction::evidence(&chain_id(&state), req.first, req.second, &req.private_key) {
+        Ok(transaction) => submit_transaction(&state, transaction, &req.private_key),
+        Err(e) => HttpResponse::BadRequest().json(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/htlc/lock",
+    request_body = HtlcLockRequest,
+    responses(
+        (status = 200, description = "HTLC lock added to mempool; returns its id, which names the HTLC", body = Hash),
+        (status = 400, description = "Invalid transaction, timeout already reached or insufficient funds")
+    )
+)]
+pub async fn lock_htlc(state: web::Data<AppState>, req: web::Json<HtlcLockRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.sender, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let transaction = Transaction::htlc_lock(&chain_id(&state), &req.receiver, req.amount, req.hashlock.clone(), req.timeout_height, &req.private_key);
+    submit_for_id(&state, transaction, &req.private_key)
+}
+
+/// Amount locked in the HTLC `id`, which a claim or refund has to sign for.
+fn htlc_amount(state: &AppState, id: &Hash) -> Result<Amount, String> {
+    let blockchain = state.blockchain.lock().unwrap();
+    blockchain.htlcs.get(id).map(|htlc| htlc.amount).ok_or_else(|| format!("No HTLC {}", id))
+}
+
+#[utoipa::path(
+    post,
+    path = "/htlc/claim",
+    request_body = HtlcClaimRequest,
+    responses(
+        (status = 200, description = "HTLC claim added to mempool; returns its id", body = Hash),
+        (status = 400, description = "Unknown or settled HTLC, wrong preimage or timeout reached")
+    )
+)]
+pub async fn claim_htlc(state: web::Data<AppState>, req: web::Json<HtlcClaimRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.receiver, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    match htlc_amount(&state, &req.htlc) {
+        Ok(amount) => submit_for_id(&state, Transaction::htlc_claim(&chain_id(&state), req.htlc.clone(), amount, &req.preimage, &req.private_key), &req.private_key),
+        Err(e) => HttpResponse::BadRequest().json(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/htlc/refund",
+    request_body = HtlcRefundRequest,
+    responses(
+        (status = 200, description = "HTLC refund added to mempool; returns its id", body = Hash),
+        (status = 400, description = "Unknown or settled HTLC, or its timeout has not been reached")
+    )
+)]
+pub async fn refund_htlc(state: web::Data<AppState>, req: web::Json<HtlcRefundRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.sender, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    match htlc_amount(&state, &req.htlc) {
+        Ok(amount) => submit_for_id(&state, Transaction::htlc_refund(&chain_id(&state), req.htlc.clone(), amount, &req.private_key), &req.private_key),
+        Err(e) => HttpResponse::BadRequest().json(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/htlcs",
+    params(HtlcQuery),
+    responses(
+        (status = 200, description = "Open HTLCs, soonest timeout first", body = Vec<Htlc>),
+        (status = 400, description = "Malformed address")
+    )
+)]
+pub async fn list_htlcs(state: web::Data<AppState>, query: web::Query<HtlcQuery>) -> impl actix_web::Responder {
+    let blockchain = state.blockchain.lock().unwrap();
+    HttpResponse::Ok().json(blockchain.htlcs.open(query.address.as_ref()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/htlc/preimage/{hashlock}",
+    params(
+        ("hashlock" = Hash, Path, description = "SHA-256 hashlock of an HTLC")
+    ),
+    responses(
+        (status = 200, description = "Preimage revealed by the first claim of an HTLC with this hashlock", body = RevealedPreimage),
+        (status = 400, description = "Malformed hash"),
+        (status = 404, description = "No HTLC with this hashlock has been claimed")
+    )
+)]
+pub async fn htlc_preimage(state: web::Data<AppState>, hashlock: web::Path<Hash>) -> impl actix_web::Responder {
+    let hashlock = hashlock.into_inner();
+    let blockchain = state.blockchain.lock().unwrap();
+    match blockchain.htlcs.revealed(&hashlock) {
+        Some(Htlc { id, status: HtlcStatus::Claimed { preimage, height }, .. }) => HttpResponse::Ok().json(RevealedPreimage {
+            hashlock,
+            preimage: preimage.clone(),
+            htlc: id.clone(),
+            height: *height,
+        }),
+        _ => HttpResponse::NotFound().json("No preimage has been revealed for this hashlock"),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/escrow/create",
+    request_body = EscrowCreateRequest,
+    responses(
+        (status = 200, description = "Escrow added to mempool; returns its id, which names the escrow", body = Hash),
+        (status = 400, description = "Invalid transaction, repeated party or insufficient funds")
+    )
+)]
+pub async fn create_escrow(state: web::Data<AppState>, req: web::Json<EscrowCreateRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.payer, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let transaction = Transaction::escrow_create(&chain_id(&state), &req.payee, &req.arbiter, req.amount, &req.private_key);
+    submit_for_id(&state, transaction, &req.private_key)
+}
+
+/// Signs and submits the approval of `req.party` for `decision` on an escrow.
+fn approve_escrow(state: &AppState, req: &EscrowApprovalRequest, decision: EscrowDecision) -> HttpResponse {
+    if let Err(e) = check_signer(&req.party, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let escrow = match state.blockchain.lock().unwrap().escrows.get(&req.escrow) {
+        Some(escrow) => escrow.clone(),
+        None => return HttpResponse::BadRequest().json(format!("No escrow {}", req.escrow)),
+    };
+    let chain_id = chain_id(state);
+    let transaction = match decision {
+        EscrowDecision::Release => Transaction::escrow_release(&chain_id, escrow.id, &escrow.payee, escrow.amount, &req.private_key),
+        EscrowDecision::Refund => Transaction::escrow_refund(&chain_id, escrow.id, &escrow.payer, escrow.amount, &req.private_key),
+    };
+    submit_for_id(state, transaction, &req.private_key)
+}
+
+#[utoipa::path(
+    post,
+    path = "/escrow/release",
+    request_body = EscrowApprovalRequest,
+    responses(
+        (status = 200, description = "Approval to pay the payee added to mempool; returns its id", body = Hash),
+        (status = 400, description = "Unknown or settled escrow, not a party, or already approved")
+    )
+)]
+pub async fn release_escrow(state: web::Data<AppState>, req: web::Json<EscrowApprovalRequest>) -> impl actix_web::Responder {
+    approve_escrow(&state, &req, EscrowDecision::Release)
+}
+
+#[utoipa::path(
+    post,
+    path = "/escrow/refund",
+    request_body = EscrowApprovalRequest,
+    responses(
+        (status = 200, description = "Approval to refund the payer added to mempool; returns its id", body = Hash),
+        (status = 400, description = "Unknown or settled escrow, not a party, or already approved")
+    )
+)]
+pub async fn refund_escrow(state: web::Data<AppState>, req: web::Json<EscrowApprovalRequest>) -> impl actix_web::Responder {
+    approve_escrow(&state, &req, EscrowDecision::Refund)
+}
+
+#[utoipa::path(
+    get,
+    path = "/escrow/{id}",
+    params(
+        ("id" = Hash, Path, description = "Id of the transaction that created the escrow")
+    ),
+    responses(
+        (status = 200, description = "Parties, amount, approvals so far and status of the escrow", body = Escrow),
+        (status = 400, description = "Malformed id"),
+        (status = 404, description = "No escrow with this id")
+    )
+)]
+pub async fn get_escrow(state: web::Data<AppState>, id: web::Path<Hash>) -> impl actix_web::Responder {
+    let blockchain = state.blockchain.lock().unwrap();
+    match blockchain.escrows.get(&id) {
+        Some(escrow) => HttpResponse::Ok().json(escrow),
+        None => HttpResponse::NotFound().json(format!("No escrow {}", id)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/tokens/create",
+    request_body = TokenCreateRequest,
+    responses(
+        (status = 200, description = "Token creation added to mempool; returns its id, which names the token", body = Hash),
+        (status = 400, description = "Invalid transaction, malformed or taken symbol, or too many decimals")
+    )
+)]
+pub async fn create_token(state: web::Data<AppState>, req: web::Json<TokenCreateRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.issuer, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let transaction = Transaction::token_create(&chain_id(&state), &req.symbol, req.decimals, req.supply, &req.private_key);
+    submit_for_id(&state, transaction, &req.private_key)
+}
+
+#[utoipa::path(
+    post,
+    path = "/tokens/transfer",
+    request_body = TokenTransferRequest,
+    responses(
+        (status = 200, description = "Token transfer added to mempool", body = String),
+        (status = 400, description = "Invalid transaction, unknown token or insufficient token balance")
+    )
+)]
+pub async fn transfer_token(state: web::Data<AppState>, req: web::Json<TokenTransferRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.sender, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let transaction = Transaction::token_transfer(&chain_id(&state), req.token.clone(), &req.receiver, req.amount, &req.private_key);
+    submit_transaction(&state, transaction, &req.private_key)
+}
+
+#[utoipa::path(
+    post,
+    path = "/tokens/mint",
+    request_body = TokenTransferRequest,
+    responses(
+        (status = 200, description = "Mint added to mempool", body = String),
+        (status = 400, description = "Invalid transaction, unknown token, sender is not the issuer or supply overflow")
+    )
+)]
+pub async fn mint_token(state: web::Data<AppState>, req: web::Json<TokenTransferRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.sender, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let transaction = Transaction::token_mint(&chain_id(&state), req.token.clone(), &req.receiver, req.amount, &req.private_key);
+    submit_transaction(&state, transaction, &req.private_key)
+}
+
+#[utoipa::path(
+    post,
+    path = "/tokens/burn",
+    request_body = TokenBurnRequest,
+    responses(
+        (status = 200, description = "Burn added to mempool", body = String),
+        (status = 400, description = "Invalid transaction, unknown token or insufficient token balance")
+    )
+)]
+pub async fn burn_token(state: web::Data<AppState>, req: web::Json<TokenBurnRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.holder, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let transaction = Transaction::token_burn(&chain_id(&state), req.token.clone(), req.amount, &req.private_key);
+    submit_transaction(&state, transaction, &req.private_key)
+}
+
+#[utoipa::path(
+    post,
+    path = "/nft/collection",
+    request_body = NftCollectionRequest,
+    responses(
+        (status = 200, description = "Collection creation added to mempool; returns its id, which names the collection", body = Hash),
+        (status = 400, description = "Invalid transaction or collection name")
+    )
+)]
+pub async fn create_collection(state: web::Data<AppState>, req: web::Json<NftCollectionRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.owner, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let transaction = Transaction::nft_collection_create(&chain_id(&state), &req.name, &req.private_key);
+    submit_for_id(&state, transaction, &req.private_key)
+}
+
+#[utoipa::path(
+    post,
+    path = "/nft/mint",
+    request_body = NftMintRequest,
+    responses(
+        (status = 200, description = "Mint added to mempool; returns its id, which names the NFT", body = Hash),
+        (status = 400, description = "Invalid transaction, unknown collection, minter is not its owner, invalid metadata URI or content already minted")
+    )
+)]
+pub async fn mint_nft(state: web::Data<AppState>, req: web::Json<NftMintRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.minter, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let transaction = Transaction::nft_mint(&chain_id(&state), req.collection.clone(), &req.receiver, req.content_hash.clone(), &req.metadata_uri, &req.private_key);
+    submit_for_id(&state, transaction, &req.private_key)
+}
+
+#[utoipa::path(
+    post,
+    path = "/nft/transfer",
+    request_body = NftTransferRequest,
+    responses(
+        (status = 200, description = "Transfer added to mempool", body = String),
+        (status = 400, description = "Invalid transaction, unknown NFT or sender is not its owner")
+    )
+)]
+pub async fn transfer_nft(state: web::Data<AppState>, req: web::Json<NftTransferRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.owner, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let transaction = Transaction::nft_transfer(&chain_id(&state), req.nft.clone(), &req.receiver, &req.private_key);
+    submit_transaction(&state, transaction, &req.private_key)
+}
+
+#[utoipa::path(
+    get,
+    path = "/nfts",
+    params(
+        ("address" = Address, Query, description = "Owner of the NFTs")
+    ),
+    responses(
+        (status = 200, description = "NFTs the address owns, ordered by collection", body = Vec<Nft>),
+        (status = 400, description = "Malformed address")
+    )
+)]
+pub async fn list_nfts(state: web::Data<AppState>, query: web::Query<BalanceRequest>) -> impl actix_web::Responder {
+    let blockchain = state.blockchain.lock().unwrap();
+    HttpResponse::Ok().json(blockchain.nfts.owned_by(&query.address))
+}
+
+#[utoipa::path(
+    get,
+    path = "/nft/{id}",
+    params(
+        ("id" = Hash, Path, description = "Id of the transaction that minted the NFT")
+    ),
+    responses(
+        (status = 200, description = "Collection, content hash, metadata URI and owner of the NFT", body = Nft),
+        (status = 400, description = "Malformed id"),
+        (status = 404, description = "No NFT with this id")
+    )
+)]
+pub async fn get_nft(state: web::Data<AppState>, id: web::Path<Hash>) -> impl actix_web::Responder {
+    let blockchain = state.blockchain.lock().unwrap();
+    match blockchain.nfts.get(&id) {
+        Some(nft) => HttpResponse::Ok().json(nft),
+        None => HttpResponse::NotFound().json(format!("No NFT {}", id)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/nft/{id}/owner",
+    params(
+        ("id" = Hash, Path, description = "Id of the transaction that minted the NFT")
+    ),
+    responses(
+        (status = 200, description = "Current owner of the NFT", body = Address),
+        (status = 400, description = "Malformed id"),
+        (status = 404, description = "No NFT with this id")
+    )
+)]
+pub async fn nft_owner(state: web::Data<AppState>, id: web::Path<Hash>) -> impl actix_web::Responder {
+    let blockchain = state.blockchain.lock().unwrap();
+    match blockchain.nfts.get(&id) {
+        Some(nft) => HttpResponse::Ok().json(&nft.owner),
+        None => HttpResponse::NotFound().json(format!("No NFT {}", id)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/nft/{id}/history",
+    params(
+        ("id" = Hash, Path, description = "Id of the transaction that minted the NFT")
+    ),
+    responses(
+        (status = 200, description = "The mint and every transfer of the NFT, oldest first, read from the blocks", body = Vec<NftTransfer>),
+        (status = 400, description = "Malformed id"),
+        (status = 404, description = "No NFT with this id")
+    )
+)]
+pub async fn nft_history(state: web::Data<AppState>, id: web::Path<Hash>) -> impl actix_web::Responder {
+    let blockchain = state.blockchain.lock().unwrap();
+    if blockchain.nfts.get(&id).is_none() {
+        return HttpResponse::NotFound().json(format!("No NFT {}", id));
+    }
+    HttpResponse::Ok().json(blockchain.nft_history(&id))
+}
+
+#[utoipa::path(
+    post,
+    path = "/contract/deploy",
+    request_body = ContractDeployRequest,
+    responses(
+        (status = 200, description = "Deploy added to mempool; returns its id and the address of the contract", body = DeployedContract),
+        (status = 400, description = "Invalid transaction, invalid code or input, or the `init` export fails")
+    )
+)]
+pub async fn deploy_contract(state: web::Data<AppState>, req: web::Json<ContractDeployRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.creator, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let transaction = Transaction::contract_deploy(&chain_id(&state), &req.code, &req.input, req.amount, gas(&state, req.gas_limit, req.gas_price), &req.private_key);
+    match enqueue(&state, transaction, &req.private_key) {
+        Ok(id) => HttpResponse::Ok().json(DeployedContract { address: contract::contract_address(&id), id }),
+        Err(e) => HttpResponse::BadRequest().json(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/contract/execute",
+    request_body = ContractCallRequest,
+    responses(
+        (status = 200, description = "Contract call added to mempool", body = String),
+        (status = 400, description = "Invalid transaction, unknown contract or method, or the call fails against current state")
+    )
+)]
+pub async fn execute_contract(state: web::Data<AppState>, req: web::Json<ContractCallRequest>) -> impl actix_web::Responder {
+    if let Err(e) = check_signer(&req.caller, &req.private_key) {
+        return HttpResponse::BadRequest().json(e);
+    }
+    let gas = gas(&state, req.gas_limit, req.gas_price);
+    let transaction = Transaction::contract_call(&chain_id(&state), &req.contract, &req.method, &req.input, req.amount, gas, &req.private_key);
+    submit_transaction(&state, transaction, &req.private_key)
+}
+
+#[utoipa::path(
+    post,
+    path = "/contract/call",
+    request_body = ContractQuery,
+    responses(
+        (status = 200, description = "Output and gas used of the call, run against current state without changing it", body = ContractOutput),
+        (status = 400, description = "Unknown contract or method, invalid input, or the call fails")
+    )
+)]
+pub async fn call_contract(state: web::Data<AppState>, req: web::Json<ContractQuery>) -> impl actix_web::Responder {
+    let blockchain = state.blockchain.lock().unwrap();
+    let caller = req.caller.as_ref().unwrap_or(&req.contract);
+    let gas_limit = req.gas_limit.unwrap_or(MAX_CONTRACT_GAS);
+    match blockchain.query_contract(&req.contract, &req.method, &req.input, caller, gas_limit) {
+        Ok(outcome) => HttpResponse::Ok().json(ContractOutput { output: hex::encode(outcome.output), gas_used: outcome.gas_used }),
+        Err(e) => HttpResponse::BadRequest().json(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/contract/{address}",
+    params(
+        ("address" = Address, Path, description = "Address of the contract")
+    ),
+    responses(
+        (status = 200, description = "Creator, code and storage of the contract", body = Contract),
+        (status = 400, description = "Malformed address"),
+        (status = 404, description = "No contract at this address")
+    )
+)]
+pub async fn get_contract(state: web::Data<AppState>, address: web::Path<Address>) -> impl actix_web::Responder {
+    let blockchain = state.blockchain.lock().unwrap();
+    match blockchain.contracts.get(&address) {
+        Some(contract) => HttpResponse::Ok().json(contract),
+        None => HttpResponse::NotFound().json(format!("No contract {}", address)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/receipt/{id}",
+    params(
+        ("id" = Hash, Path, description = "Id of a confirmed contract or scripted transaction")
+    ),
+    responses(
+        (status = 200, description = "Gas limit, gas used, fee and outcome of the transaction", body = Receipt),
+        (status = 400, description = "Malformed transaction id"),
+        (status = 404, description = "No receipt for this transaction")
+    )
+)]
+pub async fn get_receipt(state: web::Data<AppState>, id: web::Path<Hash>) -> impl actix_web::Responder {
+    let blockchain = state.blockchain.lock().unwrap();
+    match blockchain.receipts.get(&id) {
+        Some(receipt) => HttpResponse::Ok().json(receipt),
+        None => HttpResponse::NotFound().json(format!("No receipt for {}", id)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/validators",
+    responses(
+        (status = 200, description = "Bonded stakes, pending unbonds and staking parameters", body = Staking)
+    )
+)]
+pub async fn get_validators(state: web::Data<AppState>) -> impl actix_web::Responder {
+    let blockchain = state.blockchain.lock().unwrap();
+    HttpResponse::Ok().json(&blockchain.staking)
 }
 
 #[utoipa::path(
     post,
     path = "/add_block",
     responses(
-        (status = 200, description = "Block added successfully", body = String)
+        (status = 200, description = "Block added successfully", body = String),
+        (status = 400, description = "This node is not the selected proposer")
     )
 )]
 pub async fn add_block(state: web::Data<AppState>) -> impl actix_web::Responder {
     let mut blockchain = state.blockchain.lock().unwrap();
     let mut mempool = state.mempool.lock().unwrap();
-    let transactions = mempool.drain(..).collect::<Vec<_>>();
-    
-    for tx in &transactions {
-        if tx.verify() {
-            let sender_balance = blockchain.balances.get(&tx.sender).unwrap_or(&0);
-            if *sender_balance >= tx.amount {
-                *blockchain.balances.entry(tx.sender.clone()).or_insert(0) -= tx.amount;
-                *blockchain.balances.entry(tx.receiver.clone()).or_insert(0) += tx.amount;
-            }
-        }
+    // Time-locked transactions stay in the mempool until a block can include them
+    let (height, now) = (blockchain.next_height(), Utc::now().timestamp());
+    let (transactions, mut locked) = Blockchain::ready_transactions(mempool.drain(..).collect(), height, now);
+    // So do transactions the block has no gas left for
+    let (transactions, deferred) = blockchain.fill_block(transactions);
+    // Kept in their original order, so each sender's nonces stay ascending
+    *mempool = deferred;
+    mempool.append(&mut locked);
+    if let Err(e) = blockchain.mine_block(transactions.clone(), state.validator_key.as_deref()) {
+        // Nothing was applied, so keep the transactions for the selected proposer
+        mempool.splice(0..0, transactions);
+        return HttpResponse::BadRequest().json(e);
+    }
+    // Held transactions the chain has now passed can never be included
+    let next_height = blockchain.next_height();
+    let before = mempool.len();
+    mempool.retain(|tx| !tx.is_expired(next_height));
+    if mempool.len() < before {
+        info!("Purged {} expired transactions from the mempool", before - mempool.len());
     }
-
-    let previous_block = blockchain.chain.last().unwrap();
-    let new_block = Block::new(
-        previous_block.index + 1,
-        transactions,
-        previous_block.hash.clone(),
-    );
-    blockchain.chain.push(new_block);
     HttpResponse::Ok().json("Block added")
 }
 
@@ -127,4 +1082,62 @@ pub async fn add_block(state: web::Data<AppState>) -> impl actix_web::Responder
 pub async fn get_chain(state: web::Data<AppState>) -> impl actix_web::Responder {
     let blockchain = state.blockchain.lock().unwrap();
     HttpResponse::Ok().json(&*blockchain)
-}
\ No newline at end of file
+}
+
+#[utoipa::path(
+    get,
+    path = "/node_info",
+    responses(
+        (status = 200, description = "Chain id, genesis hash and height of this node", body = NodeInfo)
+    )
+)]
+pub async fn node_info(state: web::Data<AppState>) -> impl actix_web::Responder {
+    HttpResponse::Ok().json(info_of(&state.blockchain.lock().unwrap()))
+}
+
+fn info_of(blockchain: &Blockchain) -> NodeInfo {
+    NodeInfo {
+        chain_id: blockchain.chain_id().to_string(),
+        genesis_hash: blockchain.genesis_hash().clone(),
+        height: blockchain.next_height() - 1,
+        ledger: blockchain.ledger(),
+        memo_fee_per_byte: blockchain.genesis.consensus.memo_fee_per_byte,
+        block_gas_limit: blockchain.genesis.consensus.block_gas_limit,
+        min_gas_price: blockchain.genesis.consensus.min_gas_price,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/peers",
+    request_body = Handshake,
+    responses(
+        (status = 200, description = "Peer accepted; returns this node's info so the peer can check it in turn", body = NodeInfo),
+        (status = 400, description = "Peer is on another chain id or genesis, or the peer limit is reached")
+    )
+)]
+pub async fn add_peer(state: web::Data<AppState>, handshake: web::Json<Handshake>) -> impl actix_web::Responder {
+    let blockchain = state.blockchain.lock().unwrap();
+    let url = handshake.url.clone();
+    match state.peers.lock().unwrap().accept(handshake.into_inner(), blockchain.chain_id(), blockchain.genesis_hash()) {
+        Ok(()) => {
+            info!("Accepted peer {}", url);
+            HttpResponse::Ok().json(info_of(&blockchain))
+        }
+        Err(e) => {
+            info!("Refused peer {}: {}", url, e);
+            HttpResponse::BadRequest().json(e)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/peers",
+    responses(
+        (status = 200, description = "Peers whose handshake matched this network", body = [Handshake])
+    )
+)]
+pub async fn list_peers(state: web::Data<AppState>) -> impl actix_web::Responder {
+    HttpResponse::Ok().json(state.peers.lock().unwrap().list())
+}
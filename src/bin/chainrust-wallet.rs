@@ -0,0 +1,1166 @@
+use clap::{Parser, Subcommand};
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
+use address::Address;
+use contract::Contract;
+use crypto::SignatureScheme;
+use escrow::{Escrow, EscrowDecision, EscrowStatus};
+use htlc::Htlc;
+use keystore::Keystore;
+use multisig::{MultisigPolicy, PartiallySignedTransaction};
+use nft::{Nft, NftTransfer};
+use receipt::{Receipt, ReceiptStatus};
+use script::Script;
+use token::Token;
+use transaction::{BatchOutput, Gas, TimeLock, Transaction, TransactionKind, TransactionStatus};
+use types::{Amount, Hash};
+use utxo::{select_coins, UnspentOutput};
+use wallet::{HdWallet, Wallet};
+use zeroize::Zeroizing;
+
+// The wallet shares the node's transaction and key code, but only uses part of it
+#[allow(dead_code)]
+#[path = "../address.rs"]
+mod address;
+#[allow(dead_code)]
+#[path = "../block.rs"]
+mod block;
+#[allow(dead_code)]
+#[path = "../contract.rs"]
+mod contract;
+#[allow(dead_code)]
+#[path = "../crypto.rs"]
+mod crypto;
+#[allow(dead_code)]
+#[path = "../escrow.rs"]
+mod escrow;
+#[allow(dead_code)]
+#[path = "../htlc.rs"]
+mod htlc;
+#[allow(dead_code)]
+#[path = "../keystore.rs"]
+mod keystore;
+#[allow(dead_code)]
+#[path = "../multisig.rs"]
+mod multisig;
+#[allow(dead_code)]
+#[path = "../nft.rs"]
+mod nft;
+#[allow(dead_code)]
+#[path = "../receipt.rs"]
+mod receipt;
+#[allow(dead_code)]
+#[path = "../script.rs"]
+mod script;
+#[allow(dead_code)]
+#[path = "../token.rs"]
+mod token;
+#[allow(dead_code)]
+#[path = "../transaction.rs"]
+mod transaction;
+#[allow(dead_code)]
+#[path = "../types.rs"]
+mod types;
+#[allow(dead_code)]
+#[path = "../utxo.rs"]
+mod utxo;
+#[allow(dead_code)]
+#[path = "../wallet.rs"]
+mod wallet;
+
+/// Create wallets, sign transactions locally and submit them to a ChainRust node.
+#[derive(Parser)]
+#[command(name = "chainrust-wallet", version)]
+struct Cli {
+    /// Node API to query and submit transactions to.
+    #[arg(long, global = true, env = "CHAINRUST_NODE", default_value = "http://127.0.0.1:8080")]
+    node: String,
+    /// Directory of the local encrypted keystore.
+    #[arg(long, global = true, env = "CHAINRUST_WALLET_KEYSTORE")]
+    keystore: Option<PathBuf>,
+    /// Optional BIP39 passphrase, sometimes called the 25th word.
+    #[arg(long, global = true, default_value = "")]
+    passphrase: String,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate a new mnemonic and store its first receiving key in the keystore.
+    New {
+        /// Number of mnemonic words, 12 or 24.
+        #[arg(long, default_value_t = 24)]
+        words: usize,
+    },
+    /// Read a mnemonic from stdin and store its first receiving keys in the keystore.
+    Restore {
+        #[arg(long, default_value_t = 0)]
+        account: u32,
+        /// Number of receiving keys to store.
+        #[arg(long, default_value_t = 1)]
+        count: u32,
+    },
+    /// Read a mnemonic from stdin and list receiving addresses of an account.
+    Addresses {
+        #[arg(long, default_value_t = 0)]
+        account: u32,
+        #[arg(long, default_value_t = 10)]
+        count: u32,
+    },
+    /// Read a mnemonic from stdin and print the address and public key at a derivation path.
+    Derive {
+        /// BIP32 path, e.g. m/44'/1'/0'/0/0.
+        #[arg(long)]
+        path: String,
+        /// Also print the private key, which then ends up in the terminal scrollback.
+        #[arg(long)]
+        show_private_key: bool,
+    },
+    /// Read a hex private key from stdin and store it in the keystore.
+    Import,
+    /// List the addresses in the keystore.
+    List,
+    /// Show the balance of an address.
+    Balance { address: Address },
+    /// Sign a transfer with a keystore key and submit it to the node.
+    Send {
+        #[arg(long)]
+        from: Address,
+        #[arg(long)]
+        to: Address,
+        #[arg(long)]
+        amount: Amount,
+        /// Sign with BIP340 Schnorr instead of ECDSA.
+        #[arg(long)]
+        schnorr: bool,
+        /// Keep the transaction out of blocks up to and including this height.
+        #[arg(long, conflicts_with = "valid_after_time")]
+        valid_after_height: Option<u64>,
+        /// Keep the transaction out of blocks up to and including this Unix timestamp.
+        #[arg(long)]
+        valid_after_time: Option<i64>,
+        /// Drop the transaction if it is not in a block below this height.
+        #[arg(long)]
+        expires_at_height: Option<u64>,
+        /// Note such as an invoice reference; the node charges a fee per byte.
+        #[arg(long)]
+        memo: Option<String>,
+        /// Wait until the transaction is included in a block.
+        #[arg(long)]
+        wait: bool,
+        /// Seconds to wait for confirmation.
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// Pay every `address,amount` line of a CSV file in one transaction, all or none.
+    SendBatch {
+        #[arg(long)]
+        from: Address,
+        /// CSV file with one `address,amount` payment per line; blank and `#` lines are skipped.
+        file: PathBuf,
+        #[arg(long)]
+        schnorr: bool,
+        /// Note such as a payroll reference; the node charges a fee per byte.
+        #[arg(long)]
+        memo: Option<String>,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// Show whether a submitted transaction is pending or confirmed.
+    Status { id: Hash },
+    /// Print the address controlled by a locking script.
+    ScriptAddress {
+        /// Operation names and hex pushes, e.g. "dup hash <hex> equal verify checksig".
+        script: Script,
+    },
+    /// Build, cosign and submit transactions from m-of-n multisig addresses.
+    Multisig {
+        #[command(subcommand)]
+        command: MultisigCommand,
+    },
+    /// Lock, claim and refund hash time-locked contracts for atomic swaps.
+    Htlc {
+        #[command(subcommand)]
+        command: HtlcCommand,
+    },
+    /// Create three-party escrows and approve releasing or refunding them.
+    Escrow {
+        #[command(subcommand)]
+        command: EscrowCommand,
+    },
+    /// Issue, send, mint and burn fungible tokens. Amounts are in the token's smallest unit.
+    Token {
+        #[command(subcommand)]
+        command: TokenCommand,
+    },
+    /// Create NFT collections, mint NFTs and hand them to new owners.
+    Nft {
+        #[command(subcommand)]
+        command: NftCommand,
+    },
+    /// Deploy WebAssembly contracts, call them in transactions and query them for free.
+    Contract {
+        #[command(subcommand)]
+        command: ContractCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ContractCommand {
+    /// Deploy a compiled .wasm module, running its `init` export with the input.
+    Deploy {
+        code: PathBuf,
+        #[arg(long)]
+        from: Address,
+        /// Hex input for `init`.
+        #[arg(long, default_value = "")]
+        input: String,
+        /// Funds to endow the contract with.
+        #[arg(long, default_value_t = Amount::ZERO)]
+        amount: Amount,
+        #[arg(long, default_value_t = 1_000_000)]
+        gas_limit: u64,
+        /// Price per unit of gas used; the node's minimum if not set.
+        #[arg(long)]
+        gas_price: Option<Amount>,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// Call a contract method in a transaction, which can change its storage and move funds.
+    Call {
+        contract: Address,
+        method: String,
+        #[arg(long)]
+        from: Address,
+        #[arg(long, default_value = "")]
+        input: String,
+        /// Funds to pay the contract.
+        #[arg(long, default_value_t = Amount::ZERO)]
+        amount: Amount,
+        #[arg(long, default_value_t = 1_000_000)]
+        gas_limit: u64,
+        /// Price per unit of gas used; the node's minimum if not set.
+        #[arg(long)]
+        gas_price: Option<Amount>,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// Run a contract method against current state without a transaction and print its hex output.
+    Query {
+        contract: Address,
+        method: String,
+        #[arg(long, default_value = "")]
+        input: String,
+        /// Address the contract sees as its caller.
+        #[arg(long)]
+        caller: Option<Address>,
+    },
+    /// Show a contract's creator, code hash and storage.
+    Show { contract: Address },
+    /// Show the gas a confirmed deploy or call used, the fee it paid and whether it succeeded.
+    Receipt { id: Hash },
+}
+
+#[derive(Subcommand)]
+enum NftCommand {
+    /// Create a collection owned by a keystore key, which alone can mint into it.
+    Collection {
+        #[arg(long)]
+        from: Address,
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// Mint an NFT for a receiver, committing to the SHA-256 of a content file or a given hash.
+    Mint {
+        collection: Hash,
+        #[arg(long)]
+        from: Address,
+        #[arg(long)]
+        to: Address,
+        #[arg(long, required_unless_present = "content_hash")]
+        content: Option<PathBuf>,
+        #[arg(long, conflicts_with = "content")]
+        content_hash: Option<Hash>,
+        #[arg(long)]
+        metadata_uri: String,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// Hand an NFT you own to a new owner.
+    Send {
+        nft: Hash,
+        #[arg(long)]
+        from: Address,
+        #[arg(long)]
+        to: Address,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// List the NFTs an address owns.
+    List { address: Address },
+    /// Show an NFT and every change of its ownership.
+    Show { nft: Hash },
+}
+
+#[derive(Subcommand)]
+enum TokenCommand {
+    /// Issue a new token whose whole initial supply goes to the issuer.
+    Create {
+        #[arg(long)]
+        from: Address,
+        #[arg(long)]
+        symbol: String,
+        #[arg(long, default_value_t = 0)]
+        decimals: u8,
+        #[arg(long)]
+        supply: Amount,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// Send units of a token.
+    Send {
+        token: Hash,
+        #[arg(long)]
+        from: Address,
+        #[arg(long)]
+        to: Address,
+        #[arg(long)]
+        amount: Amount,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// Create new units of a token with the issuer's keystore key.
+    Mint {
+        token: Hash,
+        #[arg(long)]
+        from: Address,
+        #[arg(long)]
+        to: Address,
+        #[arg(long)]
+        amount: Amount,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// Destroy units of a token you hold.
+    Burn {
+        token: Hash,
+        #[arg(long)]
+        from: Address,
+        #[arg(long)]
+        amount: Amount,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// List every token issued on chain.
+    List,
+    /// Show the units of a token an address holds.
+    Balance { token: Hash, address: Address },
+}
+
+#[derive(Subcommand)]
+enum EscrowCommand {
+    /// Lock funds for a payee until two of the payer, payee and arbiter agree how to settle them.
+    Create {
+        #[arg(long)]
+        from: Address,
+        #[arg(long)]
+        to: Address,
+        #[arg(long)]
+        arbiter: Address,
+        #[arg(long)]
+        amount: Amount,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// Approve paying an escrow to its payee with a keystore key of one of its parties.
+    Release {
+        id: Hash,
+        #[arg(long)]
+        from: Address,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// Approve returning an escrow to its payer with a keystore key of one of its parties.
+    Refund {
+        id: Hash,
+        #[arg(long)]
+        from: Address,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// Show the parties, approvals and status of an escrow.
+    Status { id: Hash },
+}
+
+#[derive(Subcommand)]
+enum HtlcCommand {
+    /// Generate a random preimage and print it with its hashlock.
+    Secret,
+    /// Lock funds for a receiver until a timeout height, claimable with the hashlock's preimage.
+    Lock {
+        #[arg(long)]
+        from: Address,
+        #[arg(long)]
+        to: Address,
+        #[arg(long)]
+        amount: Amount,
+        /// SHA-256 of the 32 byte preimage, as printed by `htlc secret`.
+        #[arg(long)]
+        hashlock: Hash,
+        /// First height at which the receiver can no longer claim and the funds can be refunded.
+        #[arg(long)]
+        timeout_height: u64,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// Read the preimage from stdin and claim an HTLC locked for a keystore address.
+    Claim {
+        id: Hash,
+        #[arg(long)]
+        from: Address,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// Take back the funds of a timed out HTLC locked by a keystore address.
+    Refund {
+        id: Hash,
+        #[arg(long)]
+        from: Address,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+    /// List open HTLCs, optionally only those an address locked or can claim.
+    List {
+        #[arg(long)]
+        address: Option<Address>,
+    },
+    /// Print the preimage revealed by a claimed HTLC with this hashlock.
+    Preimage { hashlock: Hash },
+}
+
+#[derive(Subcommand)]
+enum MultisigCommand {
+    /// Print the address controlled by a set of public keys.
+    Address {
+        /// Number of signatures required.
+        #[arg(long)]
+        threshold: usize,
+        /// Hex public key of a cosigner; repeat for each.
+        #[arg(long = "key", required = true)]
+        keys: Vec<String>,
+    },
+    /// Write an unsigned transfer from a multisig address to a file for cosigners to sign.
+    Create {
+        #[arg(long)]
+        threshold: usize,
+        #[arg(long = "key", required = true)]
+        keys: Vec<String>,
+        #[arg(long)]
+        to: Address,
+        #[arg(long)]
+        amount: Amount,
+        #[arg(long)]
+        out: PathBuf,
+        /// Cosigners sign with BIP340 Schnorr instead of ECDSA.
+        #[arg(long)]
+        schnorr: bool,
+    },
+    /// Add the signature of a keystore key to a partially signed transaction file.
+    Sign {
+        file: PathBuf,
+        #[arg(long)]
+        from: Address,
+    },
+    /// Merge the signatures of several copies of a partially signed transaction.
+    Combine {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Finalize a fully signed transaction and submit it to the node.
+    Submit {
+        file: PathBuf,
+        #[arg(long)]
+        wait: bool,
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let node = Node { url: cli.node.trim_end_matches('/').to_string() };
+    match cli.command {
+        Command::New { words } => {
+            let hd = HdWallet::generate(words, &cli.passphrase)?;
+            let wallet = hd.derive(0, 0)?;
+            store(&open_keystore(cli.keystore)?, &wallet)?;
+            println!("mnemonic: {}", hd.mnemonic());
+            println!("address:  {}", wallet.address);
+            eprintln!("Write the mnemonic down and keep it offline; anyone holding it controls the funds.");
+        }
+        Command::Restore { account, count } => {
+            let hd = HdWallet::restore(&read_secret("Enter mnemonic:")?, &cli.passphrase)?;
+            let keystore = open_keystore(cli.keystore)?;
+            let password = new_password()?;
+            for index in 0..count {
+                let wallet = hd.derive(account, index)?;
+                if keystore.entry(&wallet.address).is_err() {
+                    keystore.insert(&wallet, &password)?;
+                }
+                println!("{}  {}", HdWallet::path(account, index), wallet.address);
+            }
+        }
+        Command::Addresses { account, count } => {
+            let hd = HdWallet::restore(&read_secret("Enter mnemonic:")?, &cli.passphrase)?;
+            for (index, address) in hd.addresses(account, count)?.iter().enumerate() {
+                println!("{}  {}", HdWallet::path(account, index as u32), address);
+            }
+        }
+        Command::Derive { path, show_private_key } => {
+            let wallet = HdWallet::restore(&read_secret("Enter mnemonic:")?, &cli.passphrase)?.derive_path(&path)?;
+            println!("address:     {}", wallet.address);
+            println!("public_key:  {}", wallet.public_key);
+            if show_private_key {
+                println!("private_key: {}", wallet.private_key.as_str());
+            }
+        }
+        Command::Import => {
+            let wallet = Wallet::from_private_key(&read_secret("Enter private key:")?)?;
+            store(&open_keystore(cli.keystore)?, &wallet)?;
+            println!("{}", wallet.address);
+        }
+        Command::List => {
+            for address in open_keystore(cli.keystore)?.list()? {
+                println!("{}", address);
+            }
+        }
+        Command::Balance { address } => {
+            println!("{}", node.balance(&address)?);
+        }
+        Command::Send { from, to, amount, schnorr, valid_after_height, valid_after_time, expires_at_height, memo, wait, timeout } => {
+            let wallet = open_keystore(cli.keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            let info = node.info()?;
+            let mut transaction = match info.ledger.as_str() {
+                "utxo" => spend(&node, &info, &wallet, scheme(schnorr), vec![BatchOutput { receiver: to, amount }], memo.as_deref())?,
+                _ => Transaction::with_scheme(&info.chain_id, scheme(schnorr), TransactionKind::Transfer, &to, amount, &wallet.private_key),
+            };
+            let lock = valid_after_height.map(TimeLock::Height).or(valid_after_time.map(TimeLock::Timestamp));
+            if let Some(lock) = lock {
+                transaction = transaction.locked_until(lock, &wallet.private_key);
+            }
+            if let Some(expiry) = expires_at_height {
+                transaction = transaction.expiring_at(expiry, &wallet.private_key);
+            }
+            if let Some(memo) = memo {
+                transaction = transaction.with_memo(&memo, &wallet.private_key);
+            }
+            let id = node.submit(&numbered(&node, &wallet, transaction)?)?;
+            println!("{}", id);
+            if wait {
+                wait_for_confirmation(&node, &id, Duration::from_secs(timeout))?;
+            }
+        }
+        Command::SendBatch { from, file, schnorr, memo, wait, timeout } => {
+            let outputs = read_outputs(&file)?;
+            let receivers = outputs.len();
+            let total = BatchOutput::total(&outputs).ok_or("Batch total overflows")?;
+            let wallet = open_keystore(cli.keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            let info = node.info()?;
+            let mut transaction = match info.ledger.as_str() {
+                "utxo" => spend(&node, &info, &wallet, scheme(schnorr), outputs, memo.as_deref())?,
+                _ => Transaction::batch_with_scheme(&info.chain_id, scheme(schnorr), outputs, &wallet.private_key)?,
+            };
+            if let Some(memo) = memo {
+                transaction = transaction.with_memo(&memo, &wallet.private_key);
+            }
+            eprintln!("Paying {} to {} receivers", total, receivers);
+            let id = node.submit(&numbered(&node, &wallet, transaction)?)?;
+            println!("{}", id);
+            if wait {
+                wait_for_confirmation(&node, &id, Duration::from_secs(timeout))?;
+            }
+        }
+        Command::Status { id } => {
+            print_status(&node.status(&id)?);
+        }
+        Command::ScriptAddress { script } => {
+            println!("{}", script.address());
+        }
+        Command::Multisig { command } => run_multisig(command, &node, cli.keystore)?,
+        Command::Htlc { command } => run_htlc(command, &node, cli.keystore)?,
+        Command::Escrow { command } => run_escrow(command, &node, cli.keystore)?,
+        Command::Token { command } => run_token(command, &node, cli.keystore)?,
+        Command::Nft { command } => run_nft(command, &node, cli.keystore)?,
+        Command::Contract { command } => run_contract(command, &node, cli.keystore)?,
+    }
+    Ok(())
+}
+
+fn run_contract(command: ContractCommand, node: &Node, keystore: Option<PathBuf>) -> Result<(), String> {
+    let (transaction, wait, timeout) = match command {
+        ContractCommand::Query { contract, method, input, caller } => {
+            let (output, gas_used) = node.query_contract(&contract, &method, &input, caller.as_ref())?;
+            eprintln!("Used {} gas", gas_used);
+            println!("{}", output);
+            return Ok(());
+        }
+        ContractCommand::Show { contract } => {
+            let details = node.contract(&contract)?;
+            println!("creator:   {}", details.creator);
+            println!("deployed:  {}", details.deployed_by);
+            println!("code hash: {}", details.code_hash);
+            for (key, value) in &details.storage {
+                println!("{} = {}", key, value);
+            }
+            return Ok(());
+        }
+        ContractCommand::Receipt { id } => {
+            let receipt = node.receipt(&id)?;
+            match &receipt.status {
+                ReceiptStatus::Succeeded => println!("succeeded in block {}", receipt.height),
+                ReceiptStatus::Failed { error } => println!("failed in block {}: {}", receipt.height, error),
+            }
+            println!("gas used: {} of {} at {}", receipt.gas_used, receipt.gas_limit, receipt.gas_price);
+            println!("fee:      {} ({} refunded)", receipt.fee, receipt.refund);
+            if !receipt.output.is_empty() {
+                println!("output:   {}", receipt.output);
+            }
+            return Ok(());
+        }
+        ContractCommand::Deploy { code, from, input, amount, gas_limit, gas_price, wait, timeout } => {
+            let code = hex::encode(std::fs::read(&code).map_err(|e| format!("Cannot read {}: {}", code.display(), e))?);
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            let gas = gas(node, gas_limit, gas_price)?;
+            let transaction = numbered(node, &wallet, Transaction::contract_deploy(&node.chain_id()?, &code, &input, amount, gas, &wallet.private_key))?;
+            eprintln!("Deploying contract {}", contract::contract_address(&transaction.id()));
+            (transaction, wait, timeout)
+        }
+        ContractCommand::Call { contract, method, from, input, amount, gas_limit, gas_price, wait, timeout } => {
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            let gas = gas(node, gas_limit, gas_price)?;
+            (numbered(node, &wallet, Transaction::contract_call(&node.chain_id()?, &contract, &method, &input, amount, gas, &wallet.private_key))?, wait, timeout)
+        }
+    };
+    let id = node.submit(&transaction)?;
+    println!("{}", id);
+    if wait {
+        wait_for_confirmation(node, &id, Duration::from_secs(timeout))?;
+    }
+    Ok(())
+}
+
+/// Gas up to `limit` at `price`, or at the node's minimum gas price if not set.
+fn gas(node: &Node, limit: u64, price: Option<Amount>) -> Result<Gas, String> {
+    let price = match price {
+        Some(price) => price,
+        None => node.info()?.min_gas_price,
+    };
+    Ok(Gas { limit, price })
+}
+
+fn run_nft(command: NftCommand, node: &Node, keystore: Option<PathBuf>) -> Result<(), String> {
+    let (transaction, wait, timeout) = match command {
+        NftCommand::List { address } => {
+            for nft in node.nfts(&address)? {
+                println!("{} collection={} content={} {}", nft.id, nft.collection, nft.content_hash, nft.metadata_uri);
+            }
+            return Ok(());
+        }
+        NftCommand::Show { nft } => {
+            let details = node.nft(&nft)?;
+            println!("collection:   {}", details.collection);
+            println!("content hash: {}", details.content_hash);
+            println!("metadata:     {}", details.metadata_uri);
+            println!("owner:        {}", details.owner);
+            for transfer in node.nft_history(&nft)? {
+                match transfer.from {
+                    Some(from) => println!("height {}: {} -> {} ({})", transfer.height, from, transfer.to, transfer.tx),
+                    None => println!("height {}: minted to {} ({})", transfer.height, transfer.to, transfer.tx),
+                }
+            }
+            return Ok(());
+        }
+        NftCommand::Collection { from, name, wait, timeout } => {
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            (numbered(node, &wallet, Transaction::nft_collection_create(&node.chain_id()?, &name, &wallet.private_key))?, wait, timeout)
+        }
+        NftCommand::Mint { collection, from, to, content, content_hash, metadata_uri, wait, timeout } => {
+            let content_hash = match (content, content_hash) {
+                (_, Some(hash)) => hash,
+                (Some(path), None) => Hash::digest(std::fs::read(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?),
+                (None, None) => return Err("Pass --content or --content-hash".to_string()),
+            };
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            (numbered(node, &wallet, Transaction::nft_mint(&node.chain_id()?, collection, &to, content_hash, &metadata_uri, &wallet.private_key))?, wait, timeout)
+        }
+        NftCommand::Send { nft, from, to, wait, timeout } => {
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            (numbered(node, &wallet, Transaction::nft_transfer(&node.chain_id()?, nft, &to, &wallet.private_key))?, wait, timeout)
+        }
+    };
+    let id = node.submit(&transaction)?;
+    println!("{}", id);
+    if wait {
+        wait_for_confirmation(node, &id, Duration::from_secs(timeout))?;
+    }
+    Ok(())
+}
+
+fn run_token(command: TokenCommand, node: &Node, keystore: Option<PathBuf>) -> Result<(), String> {
+    let (transaction, wait, timeout) = match command {
+        TokenCommand::List => {
+            for token in node.tokens()? {
+                println!("{} {:<12} decimals={} supply={} issuer={}", token.id, token.symbol, token.decimals, token.supply, token.issuer);
+            }
+            return Ok(());
+        }
+        TokenCommand::Balance { token, address } => {
+            println!("{}", node.token_balance(&token, &address)?);
+            return Ok(());
+        }
+        TokenCommand::Create { from, symbol, decimals, supply, wait, timeout } => {
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            (numbered(node, &wallet, Transaction::token_create(&node.chain_id()?, &symbol, decimals, supply, &wallet.private_key))?, wait, timeout)
+        }
+        TokenCommand::Send { token, from, to, amount, wait, timeout } => {
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            (numbered(node, &wallet, Transaction::token_transfer(&node.chain_id()?, token, &to, amount, &wallet.private_key))?, wait, timeout)
+        }
+        TokenCommand::Mint { token, from, to, amount, wait, timeout } => {
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            (numbered(node, &wallet, Transaction::token_mint(&node.chain_id()?, token, &to, amount, &wallet.private_key))?, wait, timeout)
+        }
+        TokenCommand::Burn { token, from, amount, wait, timeout } => {
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            (numbered(node, &wallet, Transaction::token_burn(&node.chain_id()?, token, amount, &wallet.private_key))?, wait, timeout)
+        }
+    };
+    let id = node.submit(&transaction)?;
+    println!("{}", id);
+    if wait {
+        wait_for_confirmation(node, &id, Duration::from_secs(timeout))?;
+    }
+    Ok(())
+}
+
+fn run_escrow(command: EscrowCommand, node: &Node, keystore: Option<PathBuf>) -> Result<(), String> {
+    let (transaction, wait, timeout) = match command {
+        EscrowCommand::Status { id } => {
+            let escrow = node.escrow(&id)?;
+            println!("payer:   {}", escrow.payer);
+            println!("payee:   {}", escrow.payee);
+            println!("arbiter: {}", escrow.arbiter);
+            println!("amount:  {}", escrow.amount);
+            for (party, decision) in &escrow.approvals {
+                match decision {
+                    EscrowDecision::Release => println!("release approved by {}", party),
+                    EscrowDecision::Refund => println!("refund approved by {}", party),
+                }
+            }
+            match escrow.status {
+                EscrowStatus::Open => println!("status:  open"),
+                EscrowStatus::Released { height } => println!("status:  released at height {}", height),
+                EscrowStatus::Refunded { height } => println!("status:  refunded at height {}", height),
+            }
+            return Ok(());
+        }
+        EscrowCommand::Create { from, to, arbiter, amount, wait, timeout } => {
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            (numbered(node, &wallet, Transaction::escrow_create(&node.chain_id()?, &to, &arbiter, amount, &wallet.private_key))?, wait, timeout)
+        }
+        EscrowCommand::Release { id, from, wait, timeout } => {
+            let escrow = node.escrow(&id)?;
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            (numbered(node, &wallet, Transaction::escrow_release(&node.chain_id()?, id, &escrow.payee, escrow.amount, &wallet.private_key))?, wait, timeout)
+        }
+        EscrowCommand::Refund { id, from, wait, timeout } => {
+            let escrow = node.escrow(&id)?;
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            (numbered(node, &wallet, Transaction::escrow_refund(&node.chain_id()?, id, &escrow.payer, escrow.amount, &wallet.private_key))?, wait, timeout)
+        }
+    };
+    let id = node.submit(&transaction)?;
+    println!("{}", id);
+    if wait {
+        wait_for_confirmation(node, &id, Duration::from_secs(timeout))?;
+    }
+    Ok(())
+}
+
+fn run_htlc(command: HtlcCommand, node: &Node, keystore: Option<PathBuf>) -> Result<(), String> {
+    let (transaction, wait, timeout) = match command {
+        HtlcCommand::Secret => {
+            let preimage = htlc::generate_preimage();
+            println!("preimage: {}", preimage);
+            println!("hashlock: {}", htlc::hashlock_of(&preimage)?);
+            eprintln!("Keep the preimage secret until you claim; claiming reveals it to the other party.");
+            return Ok(());
+        }
+        HtlcCommand::List { address } => {
+            for htlc in node.htlcs(address.as_ref())? {
+                println!("{}  {} -> {}  {}  hashlock {}  timeout {}", htlc.id, htlc.sender, htlc.receiver, htlc.amount, htlc.hashlock, htlc.timeout_height);
+            }
+            return Ok(());
+        }
+        HtlcCommand::Preimage { hashlock } => {
+            println!("{}", node.preimage(&hashlock)?);
+            return Ok(());
+        }
+        HtlcCommand::Lock { from, to, amount, hashlock, timeout_height, wait, timeout } => {
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            (numbered(node, &wallet, Transaction::htlc_lock(&node.chain_id()?, &to, amount, hashlock, timeout_height, &wallet.private_key))?, wait, timeout)
+        }
+        HtlcCommand::Claim { id, from, wait, timeout } => {
+            let htlc = node.open_htlc(&from, &id)?;
+            let preimage = read_secret("Enter preimage:")?;
+            if htlc::hashlock_of(&preimage)? != htlc.hashlock {
+                return Err("Preimage does not match the hashlock".to_string());
+            }
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            (numbered(node, &wallet, Transaction::htlc_claim(&node.chain_id()?, id, htlc.amount, &preimage, &wallet.private_key))?, wait, timeout)
+        }
+        HtlcCommand::Refund { id, from, wait, timeout } => {
+            let htlc = node.open_htlc(&from, &id)?;
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            (numbered(node, &wallet, Transaction::htlc_refund(&node.chain_id()?, id, htlc.amount, &wallet.private_key))?, wait, timeout)
+        }
+    };
+    let id = node.submit(&transaction)?;
+    println!("{}", id);
+    if wait {
+        wait_for_confirmation(node, &id, Duration::from_secs(timeout))?;
+    }
+    Ok(())
+}
+
+fn run_multisig(command: MultisigCommand, node: &Node, keystore: Option<PathBuf>) -> Result<(), String> {
+    match command {
+        MultisigCommand::Address { threshold, keys } => {
+            println!("{}", MultisigPolicy::new(threshold, &keys)?.address());
+        }
+        MultisigCommand::Create { threshold, keys, to, amount, out, schnorr } => {
+            let policy = MultisigPolicy::new(threshold, &keys)?;
+            let mut psbt = PartiallySignedTransaction::new(&node.chain_id()?, policy, TransactionKind::Transfer, &to, amount)?;
+            psbt.transaction.scheme = scheme(schnorr);
+            psbt.transaction.nonce = node.nonce(&psbt.transaction.sender)?;
+            write_psbt(&out, &psbt)?;
+            println!("{}", psbt.transaction.sender);
+        }
+        MultisigCommand::Sign { file, from } => {
+            let mut psbt = read_psbt(&file)?;
+            let wallet = open_keystore(keystore)?.unlock(&from, &password("Keystore password: ")?)?;
+            psbt.sign(&wallet.private_key)?;
+            write_psbt(&file, &psbt)?;
+            println!("{} more signature(s) required", psbt.signatures_missing());
+        }
+        MultisigCommand::Combine { files, out } => {
+            let mut combined = read_psbt(&files[0])?;
+            for file in &files[1..] {
+                combined.combine(&read_psbt(file)?)?;
+            }
+            write_psbt(&out, &combined)?;
+            println!("{} more signature(s) required", combined.signatures_missing());
+        }
+        MultisigCommand::Submit { file, wait, timeout } => {
+            let transaction = read_psbt(&file)?.finalize()?;
+            let id = node.submit(&transaction)?;
+            println!("{}", id);
+            if wait {
+                wait_for_confirmation(node, &id, Duration::from_secs(timeout))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Re-signs `transaction` from `wallet` with the next nonce the node expects from it.
+/// Spends are left alone, as they carry no nonce.
+fn numbered(node: &Node, wallet: &Wallet, transaction: Transaction) -> Result<Transaction, String> {
+    if matches!(transaction.kind, TransactionKind::Spend { .. }) {
+        return Ok(transaction);
+    }
+    Ok(transaction.with_nonce(node.nonce(&wallet.address)?, &wallet.private_key))
+}
+
+fn scheme(schnorr: bool) -> SignatureScheme {
+    if schnorr {
+        SignatureScheme::Schnorr
+    } else {
+        SignatureScheme::Ecdsa
+    }
+}
+
+/// Parses `address,amount` lines, naming the line of the first malformed one.
+fn read_outputs(path: &Path) -> Result<Vec<BatchOutput>, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut outputs = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (receiver, amount) = line.split_once(',').ok_or_else(|| format!("Line {}: expected address,amount", number + 1))?;
+        let receiver = receiver.trim().parse().map_err(|e| format!("Line {}: {}", number + 1, e))?;
+        let amount = amount.trim().parse().map_err(|e| format!("Line {}: {}", number + 1, e))?;
+        outputs.push(BatchOutput { receiver, amount });
+    }
+    Ok(outputs)
+}
+
+/// On a UTXO ledger, pays `outputs` from coins of `wallet` selected to cover them plus the
+/// memo fee, with change back to the wallet.
+fn spend(node: &Node, info: &NodeInfo, wallet: &Wallet, scheme: SignatureScheme, mut outputs: Vec<BatchOutput>, memo: Option<&str>) -> Result<Transaction, String> {
+    let fee = info.memo_fee_per_byte.checked_mul(memo.map_or(0, str::len) as u64).ok_or("Memo fee overflows")?;
+    let target = BatchOutput::total(&outputs).and_then(|total| total.checked_add(fee)).ok_or("Amount plus fee overflows")?;
+    let (inputs, change) = select_coins(&node.utxos(&wallet.address)?, target)?;
+    if !change.is_zero() {
+        outputs.push(BatchOutput { receiver: wallet.address.clone(), amount: change });
+    }
+    Transaction::spend(&info.chain_id, scheme, inputs, outputs, &wallet.private_key)
+}
+
+fn read_psbt(path: &Path) -> Result<PartiallySignedTransaction, String> {
+    let json = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid partially signed transaction {}: {}", path.display(), e))
+}
+
+fn write_psbt(path: &Path, psbt: &PartiallySignedTransaction) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(psbt).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+fn wait_for_confirmation(node: &Node, id: &Hash, timeout: Duration) -> Result<(), String> {
+    let started = Instant::now();
+    loop {
+        match node.status(id)? {
+            status @ TransactionStatus::Confirmed { .. } => {
+                print_status(&status);
+                return Ok(());
+            }
+            TransactionStatus::Unknown => return Err("Transaction was dropped by the node".to_string()),
+            TransactionStatus::Pending if started.elapsed() >= timeout => {
+                return Err("Timed out waiting for confirmation".to_string());
+            }
+            TransactionStatus::Pending => thread::sleep(Duration::from_secs(2)),
+        }
+    }
+}
+
+fn print_status(status: &TransactionStatus) {
+    match status {
+        TransactionStatus::Pending => println!("pending"),
+        TransactionStatus::Confirmed { block, confirmations } => {
+            println!("confirmed in block {} ({} confirmations)", block, confirmations)
+        }
+        TransactionStatus::Unknown => println!("unknown"),
+    }
+}
+
+fn open_keystore(dir: Option<PathBuf>) -> Result<Keystore, String> {
+    let dir = dir.unwrap_or_else(|| {
+        let home = std::env::var_os("HOME").map_or_else(|| PathBuf::from("."), PathBuf::from);
+        home.join(".chainrust").join("keystore")
+    });
+    Keystore::open(&dir).map_err(|e| format!("Cannot open keystore {}: {}", dir.display(), e))
+}
+
+fn store(keystore: &Keystore, wallet: &Wallet) -> Result<(), String> {
+    keystore.insert(wallet, &new_password()?).map(|_| ())
+}
+
+/// Secrets are read from stdin rather than arguments so they stay out of shell history.
+fn read_secret(prompt: &str) -> Result<Zeroizing<String>, String> {
+    eprintln!("{}", prompt);
+    let mut secret = Zeroizing::new(String::new());
+    io::stdin().lock().read_line(&mut secret).map_err(|e| e.to_string())?;
+    Ok(Zeroizing::new(secret.trim().to_string()))
+}
+
+/// Keystore password from `CHAINRUST_WALLET_PASSWORD` for scripts, otherwise prompted for.
+fn password(prompt: &str) -> Result<String, String> {
+    if let Ok(password) = std::env::var("CHAINRUST_WALLET_PASSWORD") {
+        return Ok(password);
+    }
+    rpassword::prompt_password(prompt).map_err(|e| e.to_string())
+}
+
+fn new_password() -> Result<String, String> {
+    let password = password("New keystore password: ")?;
+    if std::env::var("CHAINRUST_WALLET_PASSWORD").is_err() && rpassword::prompt_password("Repeat password: ").map_err(|e| e.to_string())? != password {
+        return Err("Passwords do not match".to_string());
+    }
+    Ok(password)
+}
+
+/// The parts of `GET /node_info` the wallet needs.
+#[derive(serde::Deserialize)]
+struct NodeInfo {
+    chain_id: String,
+    #[serde(default)]
+    ledger: String,
+    #[serde(default)]
+    memo_fee_per_byte: Amount,
+    #[serde(default)]
+    min_gas_price: Amount,
+}
+
+/// Blocking client for the node's REST API.
+struct Node {
+    url: String,
+}
+
+impl Node {
+    fn chain_id(&self) -> Result<String, String> {
+        Ok(self.info()?.chain_id)
+    }
+
+    fn info(&self) -> Result<NodeInfo, String> {
+        self.get("/node_info")
+    }
+
+    fn utxos(&self, address: &Address) -> Result<Vec<UnspentOutput>, String> {
+        self.get(&format!("/utxos?address={}", address))
+    }
+
+    fn htlcs(&self, address: Option<&Address>) -> Result<Vec<Htlc>, String> {
+        match address {
+            Some(address) => self.get(&format!("/htlcs?address={}", address)),
+            None => self.get("/htlcs"),
+        }
+    }
+
+    /// The open HTLC `id` that `address` locked or can claim.
+    fn open_htlc(&self, address: &Address, id: &Hash) -> Result<Htlc, String> {
+        self.htlcs(Some(address))?.into_iter().find(|htlc| htlc.id == *id).ok_or_else(|| format!("No open HTLC {} involving {}", id, address))
+    }
+
+    fn preimage(&self, hashlock: &Hash) -> Result<String, String> {
+        let revealed: serde_json::Value = self.get(&format!("/htlc/preimage/{}", hashlock))?;
+        revealed["preimage"].as_str().map(str::to_string).ok_or_else(|| "Unexpected response from node".to_string())
+    }
+
+    fn escrow(&self, id: &Hash) -> Result<Escrow, String> {
+        self.get(&format!("/escrow/{}", id))
+    }
+
+    fn tokens(&self) -> Result<Vec<Token>, String> {
+        self.get("/tokens")
+    }
+
+    fn token_balance(&self, token: &Hash, address: &Address) -> Result<Amount, String> {
+        self.get(&format!("/tokens/{}/balance?address={}", token, address))
+    }
+
+    fn nfts(&self, owner: &Address) -> Result<Vec<Nft>, String> {
+        self.get(&format!("/nfts?address={}", owner))
+    }
+
+    fn nft(&self, id: &Hash) -> Result<Nft, String> {
+        self.get(&format!("/nft/{}", id))
+    }
+
+    fn nft_history(&self, id: &Hash) -> Result<Vec<NftTransfer>, String> {
+        self.get(&format!("/nft/{}/history", id))
+    }
+
+    fn contract(&self, address: &Address) -> Result<Contract, String> {
+        self.get(&format!("/contract/{}", address))
+    }
+
+    fn receipt(&self, id: &Hash) -> Result<Receipt, String> {
+        self.get(&format!("/receipt/{}", id))
+    }
+
+    /// Hex output and gas used of a read-only call.
+    fn query_contract(&self, contract: &Address, method: &str, input: &str, caller: Option<&Address>) -> Result<(String, u64), String> {
+        let query = serde_json::json!({ "contract": contract, "method": method, "input": input, "caller": caller });
+        let response: serde_json::Value = Self::parse(ureq::post(&format!("{}/contract/call", self.url)).send_json(query))?;
+        match (response["output"].as_str(), response["gas_used"].as_u64()) {
+            (Some(output), Some(gas_used)) => Ok((output.to_string(), gas_used)),
+            _ => Err("Unexpected response from node".to_string()),
+        }
+    }
+
+    fn nonce(&self, address: &Address) -> Result<u64, String> {
+        self.get(&format!("/nonce?address={}", address))
+    }
+
+    fn balance(&self, address: &Address) -> Result<Amount, String> {
+        self.get(&format!("/check_balance?address={}", address))
+    }
+
+    fn submit(&self, transaction: &Transaction) -> Result<Hash, String> {
+        let response = ureq::post(&format!("{}/transaction/signed", self.url)).send_json(transaction);
+        Self::parse(response)
+    }
+
+    fn status(&self, id: &Hash) -> Result<TransactionStatus, String> {
+        self.get(&format!("/transaction/{}", id))
+    }
+
+    fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, String> {
+        Self::parse(ureq::get(&format!("{}{}", self.url, path)).call())
+    }
+
+    /// Decodes a JSON response, surfacing the node's error message on 4xx and 5xx.
+    fn parse<T: serde::de::DeserializeOwned>(response: Result<ureq::Response, ureq::Error>) -> Result<T, String> {
+        match response {
+            Ok(response) => response.into_json().map_err(|e| format!("Unexpected response from node: {}", e)),
+            Err(ureq::Error::Status(code, response)) => {
+                let body = response.into_string().unwrap_or_default();
+                let message = serde_json::from_str::<String>(&body).unwrap_or(body);
+                Err(format!("Node rejected the request ({}): {}", code, message))
+            }
+            Err(e) => Err(format!("Cannot reach node: {}", e)),
+        }
+    }
+}
@@ -3,26 +3,40 @@ use sha2::{Sha256, Digest};
 use chrono::Utc;
 use log::{debug, trace};
 use crate::transaction::Transaction;
+use crate::crypto;
+use crate::types::Hash;
 
 #[derive(Serialize, Deserialize, Clone, utoipa::ToSchema)]
 pub struct Block {
+    /// Network the block belongs to, taken from the genesis config.
+    #[serde(default)]
+    pub chain_id: String,
     pub index: u64,
     pub timestamp: i64,
     pub transactions: Vec<Transaction>,
-    pub previous_hash: String,
-    pub hash: String,
+    pub previous_hash: Hash,
+    pub hash: Hash,
     pub nonce: u64,
+    /// Public key of the validator that produced the block; empty for unsigned blocks.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub proposer: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub proposer_signature: String,
 }
 
+#[allow(dead_code)]
 impl Block {
-    pub fn new(index: u64, transactions: Vec<Transaction>, previous_hash: String) -> Self {
+    pub fn new(chain_id: &str, index: u64, transactions: Vec<Transaction>, previous_hash: Hash) -> Self {
         let mut block = Block {
+            chain_id: chain_id.to_string(),
             index,
             timestamp: Utc::now().timestamp(),
             transactions,
             previous_hash,
-            hash: String::new(),
+            hash: Hash::zero(),
             nonce: 0,
+            proposer: String::new(),
+            proposer_signature: String::new(),
         };
         block.hash = block.calculate_hash();
         debug!(
@@ -36,25 +50,59 @@ impl Block {
         block
     }
 
-    pub fn genesis() -> Self {
-        Block::new(0, vec![], "0".to_string())
+    /// The genesis block has no parent, so its `previous_hash` commits to the genesis
+    /// config instead; any difference in allocations or parameters changes its hash.
+    pub fn genesis(chain_id: &str, timestamp: i64, config_hash: Hash) -> Self {
+        let mut block = Block {
+            chain_id: chain_id.to_string(),
+            index: 0,
+            timestamp,
+            transactions: vec![],
+            previous_hash: config_hash,
+            hash: Hash::zero(),
+            nonce: 0,
+            proposer: String::new(),
+            proposer_signature: String::new(),
+        };
+        block.hash = block.calculate_hash();
+        block
+    }
+
+    /// Proof of work: the hash must start with `difficulty` zero hex digits.
+    pub fn meets_difficulty(&self, difficulty: u32) -> bool {
+        self.hash.as_str().chars().take(difficulty as usize).filter(|c| *c == '0').count() == difficulty as usize
     }
 
-    pub fn calculate_hash(&self) -> String {
+    /// Increments the nonce until the hash satisfies `difficulty`.
+    pub fn mine(&mut self, difficulty: u32) {
+        self.hash = self.calculate_hash();
+        while !self.meets_difficulty(difficulty) {
+            self.nonce += 1;
+            self.hash = self.calculate_hash();
+        }
+        debug!("Mined block {} with nonce {}: {}", self.index, self.nonce, self.hash);
+    }
+
+    pub fn calculate_hash(&self) -> Hash {
         // Serialize fields individually to ensure consistency
         let mut hasher = Sha256::new();
         
         // Update hasher with each field as bytes
+        hasher.update(self.chain_id.as_bytes());
         hasher.update(self.index.to_be_bytes());
         hasher.update(self.timestamp.to_be_bytes());
         hasher.update(
             serde_json::to_vec(&self.transactions)
                 .expect("Failed to serialize transactions")
         );
-        hasher.update(self.previous_hash.as_bytes());
+        hasher.update(self.previous_hash.as_str().as_bytes());
         hasher.update(self.nonce.to_be_bytes());
+        // Unsigned blocks hash exactly as they did before proposers existed
+        if !self.proposer.is_empty() {
+            hasher.update(self.proposer.as_bytes());
+        }
 
-        let hash = format!("{:x}", hasher.finalize());
+        let hash = Hash::from_digest(hasher.finalize());
         trace!(
             "Hash input for block {}: index={}, timestamp={}, transactions={:?}, previous_hash={}, nonce={}",
             self.index,
@@ -67,4 +115,71 @@ impl Block {
         debug!("Calculated hash for block {}: {}", self.index, hash);
         hash
     }
-}
\ No newline at end of file
+
+    /// Marks the block as produced by the holder of `private_key` and signs its hash.
+    pub fn sign(&mut self, private_key: &str) {
+        self.proposer = crypto::public_key_from_private(private_key).expect("Invalid private key");
+        self.hash = self.calculate_hash();
+        self.proposer_signature = crypto::sign_message(
+            &SignedHeader::signing_message(&self.chain_id, self.index, &self.hash),
+            private_key,
+        );
+    }
+
+    /// Unsigned blocks carry no proposer and trivially pass.
+    pub fn verify_proposer_signature(&self) -> bool {
+        if self.proposer.is_empty() {
+            return self.proposer_signature.is_empty();
+        }
+        self.header().verify()
+    }
+
+    /// The part of the block a proposer attests to, enough to prove equivocation.
+    pub fn header(&self) -> SignedHeader {
+        SignedHeader {
+            chain_id: self.chain_id.clone(),
+            index: self.index,
+            hash: self.hash.clone(),
+            proposer: self.proposer.clone(),
+            proposer_signature: self.proposer_signature.clone(),
+        }
+    }
+}
+
+/// A proposer's signed claim that the block at `index` on `chain_id` has `hash`.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct SignedHeader {
+    pub chain_id: String,
+    pub index: u64,
+    pub hash: Hash,
+    pub proposer: String,
+    pub proposer_signature: String,
+}
+
+#[allow(dead_code)]
+impl SignedHeader {
+    /// Binds the chain and height into the signature so a header cannot be replayed on
+    /// another network or at another index.
+    pub fn signing_message(chain_id: &str, index: u64, hash: &Hash) -> String {
+        format!("{}:{}:{}", chain_id, index, hash)
+    }
+
+    pub fn verify(&self) -> bool {
+        crypto::verify_message(
+            &Self::signing_message(&self.chain_id, self.index, &self.hash),
+            &self.proposer_signature,
+            &self.proposer,
+        )
+    }
+
+    /// True when both headers are validly signed by the same proposer for different
+    /// blocks at the same height.
+    pub fn is_equivocation(first: &SignedHeader, second: &SignedHeader) -> bool {
+        first.chain_id == second.chain_id
+            && first.index == second.index
+            && first.proposer == second.proposer
+            && first.hash != second.hash
+            && first.verify()
+            && second.verify()
+    }
+}
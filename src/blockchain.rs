@@ -1,32 +1,1233 @@
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io;
-use crate::block::Block;
+use std::sync::Arc;
+use log::warn;
+use chrono::Utc;
+use crate::address::Address;
+use crate::block::{Block, SignedHeader};
+use crate::contract::{self, Contract, Contracts, Execution, Failure, Outcome, INIT_METHOD, MAX_CONTRACT_GAS};
+use crate::crypto;
+use crate::escrow::{Escrow, EscrowDecision, EscrowStatus, Escrows};
+use crate::genesis::{GenesisConfig, LedgerModel};
+use crate::htlc::{self, Htlc, HtlcStatus, Htlcs};
+use crate::nft::{self, Collection, Nft, NftTransfer, Nfts};
+use crate::receipt::{Receipt, ReceiptStatus, Receipts};
+use crate::sigcache::SignatureCache;
+use crate::staking::Staking;
+use crate::transaction::{BatchOutput, Gas, TimeLock, Transaction, TransactionKind, TxInput, MAX_BATCH_OUTPUTS, MAX_MEMO_BYTES};
+use crate::token::{self, Token, Tokens, MAX_TOKEN_DECIMALS};
+use crate::types::{Amount, Hash};
+use crate::utxo::{OutPoint, Utxo, UtxoSet, MAX_SPEND_INPUTS};
+
+/// How far ahead of the local clock a block timestamp may be, as in Bitcoin. Without a
+/// bound, a proposer could include timestamp-locked transactions early.
+pub const MAX_FUTURE_BLOCK_TIME: i64 = 2 * 60 * 60;
+/// Seconds after its parent before a block passes to the proposer of the next round.
+pub const PROPOSER_TIMEOUT: i64 = 30;
 
 #[derive(Serialize, Deserialize, Clone, utoipa::ToSchema)]
 pub struct Blockchain {
     pub chain: Vec<Block>,
-    pub balances: HashMap<String, u64>,
+    /// Account balances; empty on a chain using the UTXO ledger.
+    pub balances: HashMap<Address, Amount>,
+    /// Unspent outputs; empty on a chain using the account ledger.
+    #[serde(default, skip_serializing_if = "UtxoSet::is_empty")]
+    pub utxos: UtxoSet,
+    #[serde(default)]
+    pub staking: Staking,
+    /// Hash time-locked contracts, open and settled.
+    #[serde(default, skip_serializing_if = "Htlcs::is_empty")]
+    pub htlcs: Htlcs,
+    /// Three-party escrows, open and settled.
+    #[serde(default, skip_serializing_if = "Escrows::is_empty")]
+    pub escrows: Escrows,
+    /// Fungible tokens and the balances held in them.
+    #[serde(default, skip_serializing_if = "Tokens::is_empty")]
+    pub tokens: Tokens,
+    /// NFT collections and the tokens minted into them.
+    #[serde(default, skip_serializing_if = "Nfts::is_empty")]
+    pub nfts: Nfts,
+    /// Deployed WebAssembly contracts and their storage.
+    #[serde(default, skip_serializing_if = "Contracts::is_empty")]
+    pub contracts: Contracts,
+    /// Gas used and paid by every programmable transaction, by transaction id.
+    #[serde(default, skip_serializing_if = "Receipts::is_empty")]
+    pub receipts: Receipts,
+    /// Transactions confirmed from each sender, which is the nonce its next one must carry.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub nonces: HashMap<Address, u64>,
+    #[serde(default)]
+    pub genesis: GenesisConfig,
+    /// Transactions already known to be correctly signed; shared by clones of the chain.
+    #[serde(skip)]
+    signature_cache: Arc<SignatureCache>,
 }
 
+#[allow(dead_code)]
 impl Blockchain {
     pub fn new() -> Self {
+        Self::from_genesis(GenesisConfig::default())
+    }
+
+    /// Builds the genesis block and initial balances and stakes from `genesis`. On a UTXO
+    /// ledger each allocation becomes an output of the genesis block, in address order.
+    pub fn from_genesis(genesis: GenesisConfig) -> Self {
+        let staking = Staking {
+            stakes: genesis.validators.clone(),
+            unbonding_delay: genesis.consensus.unbonding_delay,
+            min_stake: genesis.consensus.min_stake,
+            slash_percent: genesis.consensus.slash_percent,
+            ..Staking::default()
+        };
+        let block = genesis.block();
+        let mut balances = HashMap::new();
+        let mut utxos = UtxoSet::default();
+        match genesis.ledger {
+            LedgerModel::Account => balances = genesis.balances.clone().into_iter().collect(),
+            LedgerModel::Utxo => {
+                for (index, (owner, amount)) in genesis.balances.iter().enumerate() {
+                    let outpoint = OutPoint { tx: block.hash.clone(), index: index as u32 };
+                    utxos.insert(outpoint, Utxo { owner: owner.clone(), amount: *amount });
+                }
+            }
+        }
         Blockchain {
-            chain: vec![Block::genesis()],
-            balances: HashMap::new(),
+            chain: vec![block],
+            balances,
+            utxos,
+            staking,
+            htlcs: Htlcs::default(),
+            escrows: Escrows::default(),
+            tokens: Tokens::default(),
+            nfts: Nfts::default(),
+            contracts: Contracts::default(),
+            receipts: Receipts::default(),
+            nonces: HashMap::new(),
+            genesis,
+            signature_cache: Arc::default(),
+        }
+    }
+
+    /// Saves the state a block can change, so that a block failing halfway can be undone.
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            balances: self.balances.clone(),
+            utxos: self.utxos.clone(),
+            staking: self.staking.clone(),
+            htlcs: self.htlcs.clone(),
+            escrows: self.escrows.clone(),
+            tokens: self.tokens.clone(),
+            nfts: self.nfts.clone(),
+            contracts: self.contracts.clone(),
+            receipts: self.receipts.clone(),
+            nonces: self.nonces.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.balances = snapshot.balances;
+        self.utxos = snapshot.utxos;
+        self.staking = snapshot.staking;
+        self.htlcs = snapshot.htlcs;
+        self.escrows = snapshot.escrows;
+        self.tokens = snapshot.tokens;
+        self.nfts = snapshot.nfts;
+        self.contracts = snapshot.contracts;
+        self.receipts = snapshot.receipts;
+        self.nonces = snapshot.nonces;
+    }
+
+    pub fn signature_cache(&self) -> &SignatureCache {
+        &self.signature_cache
+    }
+
+    pub fn chain_id(&self) -> &str {
+        &self.genesis.chain_id
+    }
+
+    pub fn ledger(&self) -> LedgerModel {
+        self.genesis.ledger
+    }
+
+    pub fn genesis_hash(&self) -> &Hash {
+        &self.chain[0].hash
+    }
+
+    /// Credits `amount` to `address`, refusing to overflow its balance.
+    pub fn add_funds(&mut self, address: &Address, amount: Amount) -> Result<(), String> {
+        let balance = self.balances.entry(address.clone()).or_default();
+        *balance = balance.checked_add(amount).ok_or_else(|| format!("Balance of {} would overflow", address))?;
+        Ok(())
+    }
+
+    /// Debits `amount` from `address`, refusing to go below zero.
+    fn deduct_funds(&mut self, address: &Address, amount: Amount) -> Result<(), String> {
+        let balance = self.balances.entry(address.clone()).or_default();
+        *balance = balance.checked_sub(amount).ok_or("Insufficient funds")?;
+        Ok(())
+    }
+
+    /// Balance of `address`, or on a UTXO ledger the sum of the outputs it can spend.
+    pub fn get_balance(&self, address: &Address) -> Amount {
+        match self.genesis.ledger {
+            LedgerModel::Account => self.balances.get(address).copied().unwrap_or_default(),
+            LedgerModel::Utxo => self.utxos.balance(address),
+        }
+    }
+
+    fn check_credit(&self, address: &Address, amount: Amount) -> Result<(), String> {
+        match self.get_balance(address).checked_add(amount) {
+            Some(_) => Ok(()),
+            None => Err(format!("Balance of {} would overflow", address)),
+        }
+    }
+
+    /// Index of the block that includes the transaction with `id`.
+    pub fn find_transaction(&self, id: &Hash) -> Option<u64> {
+        self.get_transaction(id).map(|(block, _)| block)
+    }
+
+    /// The confirmed transaction with `id` and the index of its block.
+    pub fn get_transaction(&self, id: &Hash) -> Option<(u64, &Transaction)> {
+        self.chain
+            .iter()
+            .flat_map(|block| block.transactions.iter().map(move |tx| (block.index, tx)))
+            .find(|(_, tx)| tx.id() == *id)
+    }
+
+    /// Confirmed transactions whose memo contains `query`, newest first.
+    pub fn search_memos(&self, query: &str) -> Vec<(u64, &Transaction)> {
+        self.chain
+            .iter()
+            .rev()
+            .flat_map(|block| block.transactions.iter().map(move |tx| (block.index, tx)))
+            .filter(|(_, tx)| tx.memo.as_ref().is_some_and(|memo| memo.contains(query)))
+            .collect()
+    }
+
+    /// Every change of ownership of NFT `id` in confirmed blocks, from its mint on.
+    pub fn nft_history(&self, id: &Hash) -> Vec<NftTransfer> {
+        let mut owner = None;
+        let mut history = Vec::new();
+        for block in &self.chain {
+            for tx in &block.transactions {
+                let minted = matches!(tx.kind, TransactionKind::NftMint { .. }) && tx.id() == *id;
+                let transferred = matches!(&tx.kind, TransactionKind::NftTransfer { nft } if nft == id);
+                if minted || transferred {
+                    history.push(NftTransfer {
+                        tx: tx.id(),
+                        height: block.index,
+                        timestamp: block.timestamp,
+                        from: owner.replace(tx.receiver.clone()),
+                        to: tx.receiver.clone(),
+                    });
+                }
+            }
+        }
+        history
+    }
+
+    /// Height the next block will be produced at.
+    pub fn next_height(&self) -> u64 {
+        self.chain.last().map_or(0, |block| block.index + 1)
+    }
+
+    /// Nonce the next transaction from `address` has to carry, after those in `pending`.
+    pub fn next_nonce(&self, address: &Address, pending: &[Transaction]) -> u64 {
+        pending
+            .iter()
+            .filter(|tx| tx.sender == *address && !matches!(tx.kind, TransactionKind::Spend { .. }))
+            .map(|tx| tx.nonce.saturating_add(1))
+            .fold(self.nonces.get(address).copied().unwrap_or_default(), u64::max)
+    }
+
+    /// Checks `tx` against the current state without applying it.
+    pub fn validate_transaction(&self, tx: &Transaction) -> Result<(), String> {
+        self.validate_pending(tx, &[])
+    }
+
+    /// Like `validate_transaction`, for a transaction that will be mined after `pending`.
+    pub fn validate_pending(&self, tx: &Transaction, pending: &[Transaction]) -> Result<(), String> {
+        self.check_transaction(tx, pending)?;
+        // Contracts only fail when run, so they are run against the current state and the
+        // effects thrown away
+        if tx.kind.runs_contract() {
+            self.run_contract(tx, self.next_height())?;
+        }
+        Ok(())
+    }
+
+    /// Runs `method` of the contract at `address` with the hex `input` as `caller` would in
+    /// the next block, without changing anything. This is how contract state is read.
+    pub fn query_contract(&self, address: &Address, method: &str, input: &str, caller: &Address, gas_limit: u64) -> Result<Outcome, String> {
+        let deployed = self.contracts.get(address).ok_or_else(|| format!("No contract {}", address))?;
+        Self::check_method(method)?;
+        let execution = Execution {
+            caller: caller.clone(),
+            contract: address.clone(),
+            height: self.next_height(),
+            value: Amount::ZERO,
+            balance: self.get_balance(address),
+            input: contract::decode_input(input)?,
+            gas_limit,
+        };
+        Ok(contract::call(&contract::decode_code(&deployed.code)?, deployed.storage.clone(), method, execution)?)
+    }
+
+    /// Everything validation checks short of running contracts.
+    fn check_transaction(&self, tx: &Transaction, pending: &[Transaction]) -> Result<(), String> {
+        if tx.chain_id != self.genesis.chain_id {
+            return Err(format!("Transaction was signed for chain {}", tx.chain_id));
+        }
+        if !self.signature_cache.verify(tx, &self.genesis.chain_id) {
+            return Err("Invalid transaction signature".to_string());
+        }
+        Self::check_expiry(tx, self.next_height())?;
+        self.check_state(tx, pending)?;
+        Ok(())
+    }
+
+    /// Fee the memo of `tx` costs. Memos are size limited and only allowed on transfers,
+    /// batched or not.
+    pub fn memo_fee(&self, tx: &Transaction) -> Result<Amount, String> {
+        let Some(memo) = &tx.memo else {
+            return Ok(Amount::ZERO);
+        };
+        if !matches!(tx.kind, TransactionKind::Transfer | TransactionKind::Batch { .. } | TransactionKind::Spend { .. }) {
+            return Err("Memos are only allowed on transfers".to_string());
+        }
+        if memo.len() > MAX_MEMO_BYTES {
+            return Err(format!("Memo exceeds {} bytes", MAX_MEMO_BYTES));
+        }
+        tx.memo_fee(self.genesis.consensus.memo_fee_per_byte).ok_or("Memo fee overflows".to_string())
+    }
+
+    /// The state dependent part of validation, for transactions whose signature is known
+    /// good, to be applied after `pending`. Returns the gas the transaction pays for, if any.
+    fn check_state(&self, tx: &Transaction, pending: &[Transaction]) -> Result<Option<Gas>, String> {
+        self.check_ledger(tx)?;
+        let fee = self.memo_fee(tx)?;
+        let gas = self.check_gas(tx, fee)?;
+        match &tx.kind {
+            TransactionKind::Transfer => {
+                let total = tx.amount.checked_add(fee).ok_or("Amount plus fee overflows")?;
+                if self.get_balance(&tx.sender) < total {
+                    return Err("Insufficient funds".to_string());
+                }
+                if tx.receiver != tx.sender {
+                    self.check_credit(&tx.receiver, tx.amount)?;
+                }
+            }
+            TransactionKind::Bond => {
+                if tx.receiver != tx.sender {
+                    return Err("Staking transactions must name the sender as receiver".to_string());
+                }
+                // Validators sign blocks with a single key, which a multisig account does not have
+                if tx.multisig.is_some() {
+                    return Err("Multisig accounts cannot bond stake".to_string());
+                }
+                if tx.script.is_some() {
+                    return Err("Script accounts cannot bond stake".to_string());
+                }
+                if self.get_balance(&tx.sender) < tx.amount {
+                    return Err("Insufficient funds".to_string());
+                }
+                if self.staking.stake_of(&tx.sender).checked_add(tx.amount).is_none() {
+                    return Err("Stake overflow".to_string());
+                }
+            }
+            TransactionKind::Unbond => {
+                if tx.receiver != tx.sender {
+                    return Err("Staking transactions must name the sender as receiver".to_string());
+                }
+                if self.staking.stake_of(&tx.sender) < tx.amount {
+                    return Err("Insufficient stake".to_string());
+                }
+            }
+            TransactionKind::Mint => {
+                let faucet = self.genesis.faucet.as_ref().ok_or("Faucet is disabled on this network")?;
+                if !self.genesis.is_dev() {
+                    return Err("Faucet is disabled outside dev networks".to_string());
+                }
+                if tx.sender != faucet.address {
+                    return Err("Only the faucet account can mint".to_string());
+                }
+                // Together with the nonce this keeps a grant from being held back and replayed later
+                if tx.expires_at_height.is_none() {
+                    return Err("Faucet grants must set an expiry height".to_string());
+                }
+                if tx.amount > faucet.amount {
+                    return Err(format!("Mint exceeds the faucet grant of {}", faucet.amount));
+                }
+                match self.genesis.ledger {
+                    LedgerModel::Account => self.check_credit(&tx.receiver, tx.amount)?,
+                    // Identical grants would create the same outpoint
+                    LedgerModel::Utxo if self.utxos.contains(&Self::mint_outpoint(tx)) => {
+                        return Err("This grant was already minted".to_string());
+                    }
+                    LedgerModel::Utxo => {}
+                }
+            }
+            TransactionKind::Evidence { first, second } => {
+                if first.chain_id != self.genesis.chain_id {
+                    return Err("Evidence is for headers on another chain".to_string());
+                }
+                if !SignedHeader::is_equivocation(first, second) {
+                    return Err("Headers are not conflicting signatures from one proposer".to_string());
+                }
+                let offender = Address::from_public_key(&first.proposer).ok_or("Invalid proposer public key")?;
+                if tx.receiver != offender {
+                    return Err("Evidence does not name the offending proposer".to_string());
+                }
+                if self.staking.is_slashed(&tx.receiver, first.index) {
+                    return Err("Equivocation at this height was already punished".to_string());
+                }
+                // Checked as `slash` rounds, so evidence passing here can always be applied
+                if self.staking.slash_amount(&tx.receiver).is_zero() {
+                    return Err("Offender has no stake to slash".to_string());
+                }
+            }
+            TransactionKind::Batch { outputs } => self.check_batch(tx, outputs, fee)?,
+            TransactionKind::Spend { inputs, outputs } => {
+                // A spend runs nothing but its scripts, so it pays for exactly their gas
+                let gas_fee = gas.map_or(Some(Amount::ZERO), |gas| gas.fee(tx.script_gas())).ok_or("Gas fee overflows")?;
+                self.check_spend(tx, inputs, outputs, fee.checked_add(gas_fee).ok_or("Fee overflows")?)?;
+            }
+            TransactionKind::HtlcLock { timeout_height, .. } => {
+                if tx.amount.is_zero() {
+                    return Err("An HTLC must lock a positive amount".to_string());
+                }
+                if *timeout_height <= self.next_height() {
+                    return Err(format!("HTLC timeout height {} has already been reached", timeout_height));
+                }
+                if self.htlcs.contains(&tx.id()) {
+                    return Err("This HTLC was already created".to_string());
+                }
+                if self.get_balance(&tx.sender) < tx.amount {
+                    return Err("Insufficient funds".to_string());
+                }
+            }
+            TransactionKind::HtlcClaim { htlc, preimage } => {
+                let htlc = self.settleable_htlc(tx, htlc)?;
+                if tx.sender != htlc.receiver {
+                    return Err("Only the receiver of an HTLC can claim it".to_string());
+                }
+                if htlc.has_timed_out(self.next_height()) {
+                    return Err(format!("HTLC timed out at height {}", htlc.timeout_height));
+                }
+                if htlc::hashlock_of(preimage)? != htlc.hashlock {
+                    return Err("Preimage does not match the hashlock".to_string());
+                }
+                self.check_credit(&tx.sender, tx.amount)?;
+            }
+            TransactionKind::HtlcRefund { htlc } => {
+                let htlc = self.settleable_htlc(tx, htlc)?;
+                if tx.sender != htlc.sender {
+                    return Err("Only the sender of an HTLC can refund it".to_string());
+                }
+                if !htlc.has_timed_out(self.next_height()) {
+                    return Err(format!("HTLC cannot be refunded before height {}", htlc.timeout_height));
+                }
+                self.check_credit(&tx.sender, tx.amount)?;
+            }
+            TransactionKind::EscrowCreate { arbiter } => {
+                if tx.amount.is_zero() {
+                    return Err("An escrow must hold a positive amount".to_string());
+                }
+                if tx.sender == tx.receiver || *arbiter == tx.sender || *arbiter == tx.receiver {
+                    return Err("Payer, payee and arbiter of an escrow must be different".to_string());
+                }
+                if self.escrows.contains(&tx.id()) {
+                    return Err("This escrow was already created".to_string());
+                }
+                if self.get_balance(&tx.sender) < tx.amount {
+                    return Err("Insufficient funds".to_string());
+                }
+            }
+            TransactionKind::EscrowRelease { escrow } => self.check_escrow_approval(tx, escrow, EscrowDecision::Release)?,
+            TransactionKind::EscrowRefund { escrow } => self.check_escrow_approval(tx, escrow, EscrowDecision::Refund)?,
+            TransactionKind::TokenCreate { symbol, decimals } => {
+                if tx.receiver != tx.sender {
+                    return Err("Token creation must name the issuer as receiver".to_string());
+                }
+                token::check_symbol(symbol)?;
+                if *decimals > MAX_TOKEN_DECIMALS {
+                    return Err(format!("A token can have at most {} decimals", MAX_TOKEN_DECIMALS));
+                }
+                if self.tokens.get(&tx.id()).is_some() {
+                    return Err("This token was already created".to_string());
+                }
+                if self.tokens.by_symbol(symbol).is_some() {
+                    return Err(format!("Token symbol {} is already taken", symbol));
+                }
+            }
+            TransactionKind::TokenTransfer { token } => {
+                self.token_to_move(tx, token)?;
+                if self.tokens.balance(token, &tx.sender) < tx.amount {
+                    return Err("Insufficient token balance".to_string());
+                }
+            }
+            TransactionKind::TokenMint { token } => {
+                let token = self.token_to_move(tx, token)?;
+                if tx.sender != token.issuer {
+                    return Err(format!("Only the issuer of {} can mint it", token.symbol));
+                }
+                if token.supply.checked_add(tx.amount).is_none() {
+                    return Err("Token supply would overflow".to_string());
+                }
+            }
+            TransactionKind::TokenBurn { token } => {
+                self.token_to_move(tx, token)?;
+                if tx.receiver != tx.sender {
+                    return Err("Token burns must name the sender as receiver".to_string());
+                }
+                if self.tokens.balance(token, &tx.sender) < tx.amount {
+                    return Err("Insufficient token balance".to_string());
+                }
+            }
+            TransactionKind::NftCollectionCreate { name } => {
+                Self::check_no_amount(tx)?;
+                if tx.receiver != tx.sender {
+                    return Err("Collection creation must name the owner as receiver".to_string());
+                }
+                nft::check_collection_name(name)?;
+                if self.nfts.collection(&tx.id()).is_some() {
+                    return Err("This collection was already created".to_string());
+                }
+            }
+            TransactionKind::NftMint { collection, content_hash, metadata_uri } => {
+                Self::check_no_amount(tx)?;
+                let collection = self.nfts.collection(collection).ok_or_else(|| format!("No collection {}", collection))?;
+                if tx.sender != collection.owner {
+                    return Err(format!("Only the owner of {} can mint into it", collection.name));
+                }
+                nft::check_metadata_uri(metadata_uri)?;
+                if self.nfts.get(&tx.id()).is_some() {
+                    return Err("This NFT was already minted".to_string());
+                }
+                if self.nfts.has_content(&collection.id, content_hash) {
+                    return Err(format!("{} already has an NFT for this content", collection.name));
+                }
+            }
+            TransactionKind::NftTransfer { nft } => {
+                Self::check_no_amount(tx)?;
+                let nft = self.nfts.get(nft).ok_or_else(|| format!("No NFT {}", nft))?;
+                if tx.sender != nft.owner {
+                    return Err("Only the owner of an NFT can transfer it".to_string());
+                }
+            }
+            TransactionKind::ContractDeploy { code, input } => {
+                if tx.receiver != tx.sender {
+                    return Err("Contract deploys must name the sender as receiver".to_string());
+                }
+                contract::check_code(code)?;
+                contract::decode_input(input)?;
+                let address = contract::contract_address(&tx.id());
+                if self.contracts.contains(&address) {
+                    return Err("This contract was already deployed".to_string());
+                }
+                self.check_credit(&address, tx.amount)?;
+            }
+            TransactionKind::ContractCall { method, input } => {
+                if !self.contracts.contains(&tx.receiver) {
+                    return Err(format!("No contract {}", tx.receiver));
+                }
+                Self::check_method(method)?;
+                contract::decode_input(input)?;
+                self.check_credit(&tx.receiver, tx.amount)?;
+            }
+        }
+        // Last, so that a transaction invalid for another reason is rejected for that
+        self.check_nonce(tx, pending)?;
+        Ok(gas)
+    }
+
+    /// Checks the gas `tx` offers and, on the account ledger, that the sender can pay its
+    /// native debit plus the fee for all of it; `fee` is the memo fee. Contract transactions
+    /// always pay for gas. Scripted ones may leave it out while the minimum gas price is
+    /// zero, and then use what their scripts cost for free.
+    fn check_gas(&self, tx: &Transaction, fee: Amount) -> Result<Option<Gas>, String> {
+        let consensus = &self.genesis.consensus;
+        let script_gas = tx.script_gas();
+        let gas = match tx.gas {
+            None if tx.kind.runs_contract() => return Err("Contract transactions must set a gas limit and price".to_string()),
+            None if script_gas == 0 => return Ok(None),
+            None if consensus.min_gas_price.is_zero() => Gas { limit: script_gas, price: Amount::ZERO },
+            None => return Err(format!("Scripted transactions must pay at least {} per gas on this chain", consensus.min_gas_price)),
+            Some(_) if script_gas == 0 && !tx.kind.runs_contract() => {
+                return Err("Only contract and scripted transactions pay for gas".to_string());
+            }
+            Some(gas) => gas,
+        };
+        if tx.kind.runs_contract() && (gas.limit == 0 || gas.limit > MAX_CONTRACT_GAS) {
+            return Err(format!("Gas limit must be between 1 and {}", MAX_CONTRACT_GAS));
         }
+        if gas.limit > consensus.block_gas_limit {
+            return Err(format!("Gas limit {} exceeds the block gas limit of {}", gas.limit, consensus.block_gas_limit));
+        }
+        if gas.limit < script_gas {
+            return Err(format!("Gas limit {} does not cover the {} gas its scripts use", gas.limit, script_gas));
+        }
+        if gas.price < consensus.min_gas_price {
+            return Err(format!("Gas price {} is below the minimum of {}", gas.price, consensus.min_gas_price));
+        }
+        let max_fee = gas.max_fee().ok_or("Gas fee overflows")?;
+        match (self.genesis.ledger, &tx.kind) {
+            (LedgerModel::Account, _) => {
+                let total = Self::debit(tx, fee).checked_add(max_fee).ok_or("Amount plus fee overflows")?;
+                if self.get_balance(&tx.sender) < total {
+                    return Err("Insufficient funds".to_string());
+                }
+            }
+            // A spend's inputs pay for its gas, which `check_spend` covers
+            (LedgerModel::Utxo, TransactionKind::Spend { .. }) => {}
+            (LedgerModel::Utxo, _) if !max_fee.is_zero() => return Err("Only spends can pay for gas on a UTXO ledger".to_string()),
+            (LedgerModel::Utxo, _) => {}
+        }
+        Ok(Some(gas))
     }
 
-    pub fn add_funds(&mut self, address: &str, amount: u64) {
-        *self.balances.entry(address.to_string()).or_insert(0) += amount;
+    /// Native funds `tx` takes from the sender's account besides gas, given its memo `fee`.
+    /// Saturates, since an overflowing amount is rejected anyway.
+    fn debit(tx: &Transaction, fee: Amount) -> Amount {
+        match tx.kind {
+            TransactionKind::Transfer | TransactionKind::Batch { .. } => tx.amount.saturating_add(fee),
+            TransactionKind::Bond
+            | TransactionKind::HtlcLock { .. }
+            | TransactionKind::EscrowCreate { .. }
+            | TransactionKind::ContractDeploy { .. }
+            | TransactionKind::ContractCall { .. } => tx.amount,
+            _ => Amount::ZERO,
+        }
     }
 
-    pub fn get_balance(&self, address: &str) -> u64 {
-        *self.balances.get(address).unwrap_or(&0)
+    /// Rejects method names a call cannot run, including `init`, which only runs on deploy.
+    fn check_method(method: &str) -> Result<(), String> {
+        contract::check_method(method)?;
+        if method == INIT_METHOD {
+            return Err(format!("{} only runs when a contract is deployed", INIT_METHOD));
+        }
+        Ok(())
+    }
+
+    /// Runs the contract deploy or call `tx` as part of the block at `height`, without
+    /// changing state, and works out the balances it leaves every account it touches with.
+    /// The contract gets whatever gas the sender's scripts leave of the gas limit.
+    fn run_contract(&self, tx: &Transaction, height: u64) -> Result<ContractRun, Failure> {
+        let gas_limit = tx.gas.map_or(0, |gas| gas.limit.saturating_sub(tx.script_gas()));
+        let execution = |address: &Address, balance: Amount, input: &str| -> Result<Execution, Failure> {
+            Ok(Execution {
+                caller: tx.sender.clone(),
+                contract: address.clone(),
+                height,
+                value: tx.amount,
+                balance,
+                input: contract::decode_input(input)?,
+                gas_limit,
+            })
+        };
+        let (address, balance, outcome) = match &tx.kind {
+            TransactionKind::ContractDeploy { code, input } => {
+                let address = contract::contract_address(&tx.id());
+                let balance = self.get_balance(&address).checked_add(tx.amount).ok_or("Contract balance would overflow")?;
+                let outcome = contract::deploy(&contract::decode_code(code)?, execution(&address, balance, input)?)?;
+                (address, balance, outcome)
+            }
+            TransactionKind::ContractCall { method, input } => {
+                let deployed = self.contracts.get(&tx.receiver).ok_or_else(|| format!("No contract {}", tx.receiver))?;
+                let balance = self.get_balance(&deployed.address).checked_add(tx.amount).ok_or("Contract balance would overflow")?;
+                let execution = execution(&deployed.address, balance, input)?;
+                let outcome = contract::call(&contract::decode_code(&deployed.code)?, deployed.storage.clone(), method, execution)?;
+                (deployed.address.clone(), balance, outcome)
+            }
+            _ => return Err(format!("{} transactions do not run a contract", tx.kind.tag()).into()),
+        };
+        let balances = self
+            .contract_balances(tx, &address, balance, &outcome)
+            .map_err(|error| Failure { error, gas_used: outcome.gas_used })?;
+        Ok(ContractRun { address, outcome, balances })
+    }
+
+    /// Final balances after the sender pays `tx.amount` to the contract at `address`, which
+    /// then holds `balance`, and the contract makes the transfers of `outcome`.
+    fn contract_balances(&self, tx: &Transaction, address: &Address, balance: Amount, outcome: &Outcome) -> Result<HashMap<Address, Amount>, String> {
+        let mut balances = HashMap::new();
+        balances.insert(tx.sender.clone(), self.get_balance(&tx.sender).checked_sub(tx.amount).ok_or("Insufficient funds")?);
+        balances.insert(address.clone(), balance);
+        for (receiver, amount) in &outcome.transfers {
+            let paid = balances[address].checked_sub(*amount).ok_or("Contract balance is too low for its transfers")?;
+            balances.insert(address.clone(), paid);
+            let credited = balances.get(receiver).copied().unwrap_or_else(|| self.get_balance(receiver));
+            let credited = credited.checked_add(*amount).ok_or_else(|| format!("Balance of {} would overflow", receiver))?;
+            balances.insert(receiver.clone(), credited);
+        }
+        Ok(balances)
+    }
+
+    /// NFTs are moved whole, so their transactions carry no amount.
+    fn check_no_amount(tx: &Transaction) -> Result<(), String> {
+        if !tx.amount.is_zero() {
+            return Err(format!("{} transactions must have a zero amount", tx.kind.tag()));
+        }
+        Ok(())
+    }
+
+    /// The token a transfer, mint or burn moves a positive amount of.
+    fn token_to_move(&self, tx: &Transaction, id: &Hash) -> Result<&Token, String> {
+        let token = self.tokens.get(id).ok_or_else(|| format!("No token {}", id))?;
+        if tx.amount.is_zero() {
+            return Err("Token transactions must move a positive amount".to_string());
+        }
+        Ok(token)
+    }
+
+    /// Checks that the sender is a party to the open escrow `id` that has not approved
+    /// `decision` yet, and that the transaction pays the escrowed amount to whom the
+    /// decision names.
+    fn check_escrow_approval(&self, tx: &Transaction, id: &Hash, decision: EscrowDecision) -> Result<(), String> {
+        let escrow = self.escrows.get(id).ok_or_else(|| format!("No escrow {}", id))?;
+        if !escrow.is_open() {
+            return Err(format!("Escrow {} is already settled", id));
+        }
+        if !escrow.is_party(&tx.sender) {
+            return Err("Only the payer, payee or arbiter can settle an escrow".to_string());
+        }
+        if escrow.approvals.get(&tx.sender) == Some(&decision) {
+            return Err(format!("{} has already approved this", tx.sender));
+        }
+        if tx.receiver != *escrow.beneficiary(decision) {
+            return Err(format!("Escrow {} would pay {}, not {}", id, escrow.beneficiary(decision), tx.receiver));
+        }
+        if tx.amount != escrow.amount {
+            return Err(format!("Escrow {} holds {}, not {}", id, escrow.amount, tx.amount));
+        }
+        if escrow.settles(&tx.sender, decision) {
+            self.check_credit(&tx.receiver, tx.amount)?;
+        }
+        Ok(())
+    }
+
+    /// The open HTLC a claim or refund settles, which has to pay out to the sender exactly
+    /// the locked amount.
+    fn settleable_htlc(&self, tx: &Transaction, id: &Hash) -> Result<&Htlc, String> {
+        let htlc = self.htlcs.get(id).ok_or_else(|| format!("No HTLC {}", id))?;
+        if !htlc.is_open() {
+            return Err(format!("HTLC {} is already settled", id));
+        }
+        if tx.receiver != tx.sender {
+            return Err("HTLC settlements must name the sender as receiver".to_string());
+        }
+        if tx.amount != htlc.amount {
+            return Err(format!("HTLC {} locks {}, not {}", id, htlc.amount, tx.amount));
+        }
+        Ok(htlc)
+    }
+
+    /// Rejects kinds that move funds the way the other ledger model does. Staking, HTLCs,
+    /// escrows, tokens, NFTs and contracts are only supported on the account ledger.
+    fn check_ledger(&self, tx: &Transaction) -> Result<(), String> {
+        match (self.genesis.ledger, &tx.kind) {
+            (LedgerModel::Account, TransactionKind::Spend { .. }) => Err("Spend transactions are only valid on a UTXO ledger".to_string()),
+            (LedgerModel::Utxo, TransactionKind::Spend { .. } | TransactionKind::Mint | TransactionKind::Evidence { .. }) => Ok(()),
+            (LedgerModel::Utxo, _) => Err(format!("{} transactions are not valid on a UTXO ledger", tx.kind.tag())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Rejects transactions that are not the sender's next, such as one already confirmed.
+    /// Spends carry no nonce: each input can only be spent once anyway.
+    fn check_nonce(&self, tx: &Transaction, pending: &[Transaction]) -> Result<(), String> {
+        let expected = match tx.kind {
+            TransactionKind::Spend { .. } => 0,
+            _ => self.next_nonce(&tx.sender, pending),
+        };
+        if tx.nonce != expected {
+            return Err(format!("Expected nonce {}, got {}", expected, tx.nonce));
+        }
+        Ok(())
+    }
+
+    /// Output created by a faucet grant on a UTXO ledger.
+    fn mint_outpoint(tx: &Transaction) -> OutPoint {
+        OutPoint { tx: tx.id(), index: 0 }
+    }
+
+    /// Checks that every input is a distinct unspent output owned by the key that signed
+    /// it, and that the inputs cover the outputs plus the memo and gas fees.
+    fn check_spend(&self, tx: &Transaction, inputs: &[TxInput], outputs: &[BatchOutput], fee: Amount) -> Result<(), String> {
+        if tx.receiver != tx.sender {
+            return Err("Spend transactions must name the sender as receiver".to_string());
+        }
+        if inputs.is_empty() || inputs.len() > MAX_SPEND_INPUTS {
+            return Err(format!("A spend needs between 1 and {} inputs", MAX_SPEND_INPUTS));
+        }
+        if outputs.is_empty() || outputs.len() > MAX_BATCH_OUTPUTS {
+            return Err(format!("A spend needs between 1 and {} outputs", MAX_BATCH_OUTPUTS));
+        }
+        if outputs.iter().any(|output| output.amount.is_zero()) {
+            return Err("Spend outputs must pay a positive amount".to_string());
+        }
+        let mut spent = HashSet::new();
+        let mut available = Amount::ZERO;
+        for input in inputs {
+            if !spent.insert(&input.outpoint) {
+                return Err(format!("Output {} is spent twice", input.outpoint));
+            }
+            let utxo = self.utxos.get(&input.outpoint).ok_or_else(|| format!("Output {} is not unspent", input.outpoint))?;
+            if input.owner().as_ref() != Some(&utxo.owner) {
+                return Err(format!("Output {} is not owned by the input's key or script", input.outpoint));
+            }
+            available = available.checked_add(utxo.amount).ok_or("Input total overflows")?;
+        }
+        let total = BatchOutput::total(outputs).ok_or("Output total overflows")?;
+        if total != tx.amount {
+            return Err(format!("Spend amount {} does not match the output total {}", tx.amount, total));
+        }
+        if available < total.checked_add(fee).ok_or("Amount plus fee overflows")? {
+            return Err("Inputs do not cover the outputs plus fee".to_string());
+        }
+        Ok(())
+    }
+
+    /// Checks a batch transfer far enough that applying it cannot fail halfway: the signed
+    /// total must match the outputs, cover them plus the fee, and no receiver's balance may
+    /// overflow, counting every output it gets.
+    fn check_batch(&self, tx: &Transaction, outputs: &[BatchOutput], fee: Amount) -> Result<(), String> {
+        if tx.receiver != tx.sender {
+            return Err("Batch transfers must name the sender as receiver".to_string());
+        }
+        if outputs.is_empty() || outputs.len() > MAX_BATCH_OUTPUTS {
+            return Err(format!("A batch transfer needs between 1 and {} outputs", MAX_BATCH_OUTPUTS));
+        }
+        if outputs.iter().any(|output| output.amount.is_zero()) {
+            return Err("Batch outputs must pay a positive amount".to_string());
+        }
+        let total = BatchOutput::total(outputs).ok_or("Batch total overflows")?;
+        if total != tx.amount {
+            return Err(format!("Batch amount {} does not match the output total {}", tx.amount, total));
+        }
+        let total = total.checked_add(fee).ok_or("Amount plus fee overflows")?;
+        if self.get_balance(&tx.sender) < total {
+            return Err("Insufficient funds".to_string());
+        }
+        let mut credits: HashMap<&Address, Amount> = HashMap::new();
+        for output in outputs.iter().filter(|output| output.receiver != tx.sender) {
+            let credit = credits.entry(&output.receiver).or_default();
+            *credit = credit.checked_add(output.amount).ok_or("Batch total overflows")?;
+        }
+        for (receiver, credit) in credits {
+            self.check_credit(receiver, credit)?;
+        }
+        Ok(())
+    }
+
+    /// Validates `tx` and applies it as part of the block at `height`, returning the gas it used.
+    pub fn apply_transaction(&mut self, tx: &Transaction, height: u64) -> Result<u64, String> {
+        // Applying runs contracts anyway, so they are not run twice
+        self.check_transaction(tx, &[])?;
+        self.apply_verified(tx, height)
+    }
+
+    /// Applies `tx`, returning the gas it used. A contract that fails when run leaves
+    /// the transaction valid: it changes nothing but still pays for its gas.
+    fn apply_verified(&mut self, tx: &Transaction, height: u64) -> Result<u64, String> {
+        let gas = self.check_state(tx, &[])?;
+        let mut execution = None;
+        match &tx.kind {
+            TransactionKind::Transfer => {
+                // The memo fee is burned
+                let fee = self.memo_fee(tx)?;
+                self.deduct_funds(&tx.sender, tx.amount.checked_add(fee).ok_or("Amount plus fee overflows")?)?;
+                self.add_funds(&tx.receiver, tx.amount)?;
+            }
+            TransactionKind::Bond => {
+                self.deduct_funds(&tx.sender, tx.amount)?;
+                self.staking.bond(&tx.sender, tx.amount)?;
+            }
+            TransactionKind::Unbond => {
+                self.staking.unbond(&tx.sender, tx.amount, height)?;
+            }
+            TransactionKind::Mint => match self.genesis.ledger {
+                LedgerModel::Account => self.add_funds(&tx.receiver, tx.amount)?,
+                LedgerModel::Utxo => self.utxos.insert(Self::mint_outpoint(tx), Utxo { owner: tx.receiver.clone(), amount: tx.amount }),
+            },
+            TransactionKind::Evidence { first, .. } => {
+                let burned = self.staking.slash(&tx.receiver, first.index)?;
+                warn!("Slashed {} of stake from {} for equivocating at height {}", burned, tx.receiver, first.index);
+            }
+            TransactionKind::Batch { outputs } => {
+                let fee = self.memo_fee(tx)?;
+                self.deduct_funds(&tx.sender, tx.amount.checked_add(fee).ok_or("Amount plus fee overflows")?)?;
+                for output in outputs {
+                    self.add_funds(&output.receiver, output.amount)?;
+                }
+            }
+            TransactionKind::Spend { inputs, outputs } => {
+                // Whatever the inputs hold beyond the outputs is burned as the fee
+                for input in inputs {
+                    self.utxos.remove(&input.outpoint);
+                }
+                let id = tx.id();
+                for (index, output) in outputs.iter().enumerate() {
+                    let outpoint = OutPoint { tx: id.clone(), index: index as u32 };
+                    self.utxos.insert(outpoint, Utxo { owner: output.receiver.clone(), amount: output.amount });
+                }
+            }
+            TransactionKind::HtlcLock { hashlock, timeout_height } => {
+                self.deduct_funds(&tx.sender, tx.amount)?;
+                self.htlcs.insert(Htlc {
+                    id: tx.id(),
+                    sender: tx.sender.clone(),
+                    receiver: tx.receiver.clone(),
+                    amount: tx.amount,
+                    hashlock: hashlock.clone(),
+                    timeout_height: *timeout_height,
+                    status: HtlcStatus::Open,
+                });
+            }
+            TransactionKind::HtlcClaim { htlc, preimage } => {
+                self.htlcs.settle(htlc, HtlcStatus::Claimed { preimage: preimage.clone(), height })?;
+                self.add_funds(&tx.sender, tx.amount)?;
+            }
+            TransactionKind::HtlcRefund { htlc } => {
+                self.htlcs.settle(htlc, HtlcStatus::Refunded { height })?;
+                self.add_funds(&tx.sender, tx.amount)?;
+            }
+            TransactionKind::EscrowCreate { arbiter } => {
+                self.deduct_funds(&tx.sender, tx.amount)?;
+                self.escrows.insert(Escrow {
+                    id: tx.id(),
+                    payer: tx.sender.clone(),
+                    payee: tx.receiver.clone(),
+                    arbiter: arbiter.clone(),
+                    amount: tx.amount,
+                    approvals: Default::default(),
+                    status: EscrowStatus::Open,
+                });
+            }
+            TransactionKind::EscrowRelease { escrow } => self.approve_escrow(tx, escrow, EscrowDecision::Release, height)?,
+            TransactionKind::EscrowRefund { escrow } => self.approve_escrow(tx, escrow, EscrowDecision::Refund, height)?,
+            TransactionKind::TokenCreate { symbol, decimals } => self.tokens.create(Token {
+                id: tx.id(),
+                symbol: symbol.clone(),
+                decimals: *decimals,
+                supply: tx.amount,
+                issuer: tx.sender.clone(),
+            }),
+            TransactionKind::TokenTransfer { token } => self.tokens.transfer(token, &tx.sender, &tx.receiver, tx.amount)?,
+            TransactionKind::TokenMint { token } => self.tokens.mint(token, &tx.receiver, tx.amount)?,
+            TransactionKind::TokenBurn { token } => self.tokens.burn(token, &tx.sender, tx.amount)?,
+            TransactionKind::NftCollectionCreate { name } => self.nfts.create_collection(Collection {
+                id: tx.id(),
+                name: name.clone(),
+                owner: tx.sender.clone(),
+            }),
+            TransactionKind::NftMint { collection, content_hash, metadata_uri } => self.nfts.mint(Nft {
+                id: tx.id(),
+                collection: collection.clone(),
+                content_hash: content_hash.clone(),
+                metadata_uri: metadata_uri.clone(),
+                owner: tx.receiver.clone(),
+            }),
+            TransactionKind::NftTransfer { nft } => self.nfts.transfer(nft, &tx.receiver)?,
+            TransactionKind::ContractDeploy { code, .. } => {
+                execution = Some(self.run_contract(tx, height).map(|mut run| {
+                    self.contracts.insert(Contract {
+                        address: run.address,
+                        creator: tx.sender.clone(),
+                        deployed_by: tx.id(),
+                        code_hash: Hash::digest(code),
+                        code: code.clone(),
+                        storage: std::mem::take(&mut run.outcome.storage),
+                    });
+                    self.balances.extend(run.balances);
+                    run.outcome
+                }));
+            }
+            TransactionKind::ContractCall { .. } => match self.run_contract(tx, height) {
+                Ok(mut run) => {
+                    self.contracts.set_storage(&run.address, std::mem::take(&mut run.outcome.storage))?;
+                    self.balances.extend(run.balances);
+                    execution = Some(Ok(run.outcome));
+                }
+                Err(failure) => execution = Some(Err(failure)),
+            },
+        }
+        let gas_used = match gas {
+            Some(gas) => self.pay_for_gas(tx, gas, execution, height)?,
+            None => 0,
+        };
+        // Only once nothing can fail: `mine_block` drops a failed transaction without
+        // undoing it, and the block must not consume the sender's nonce
+        if !matches!(tx.kind, TransactionKind::Spend { .. }) {
+            *self.nonces.entry(tx.sender.clone()).or_default() += 1;
+        }
+        Ok(gas_used)
+    }
+
+    /// Charges the sender of `tx` for the gas its scripts and `execution` of its contract
+    /// used, burning the fee, and records its receipt. Gas left unused is not charged.
+    fn pay_for_gas(&mut self, tx: &Transaction, gas: Gas, execution: Option<Result<Outcome, Failure>>, height: u64) -> Result<u64, String> {
+        let (contract_gas, status, output) = match execution {
+            None => (0, ReceiptStatus::Succeeded, Vec::new()),
+            Some(Ok(outcome)) => (outcome.gas_used, ReceiptStatus::Succeeded, outcome.output),
+            Some(Err(failure)) => (failure.gas_used, ReceiptStatus::Failed { error: failure.error }, Vec::new()),
+        };
+        let gas_used = tx.script_gas().saturating_add(contract_gas).min(gas.limit);
+        let fee = gas.fee(gas_used).ok_or("Gas fee overflows")?;
+        // A spend pays with what its inputs hold beyond its outputs
+        if self.genesis.ledger == LedgerModel::Account {
+            self.deduct_funds(&tx.sender, fee)?;
+        }
+        self.receipts.insert(Receipt {
+            tx: tx.id(),
+            height,
+            gas_limit: gas.limit,
+            gas_used,
+            gas_price: gas.price,
+            fee,
+            refund: gas.fee(gas.limit - gas_used).ok_or("Gas fee overflows")?,
+            status,
+            output: hex::encode(output),
+        });
+        Ok(gas_used)
+    }
+
+    /// Records the sender's approval and pays out the escrow once it settles.
+    fn approve_escrow(&mut self, tx: &Transaction, id: &Hash, decision: EscrowDecision, height: u64) -> Result<(), String> {
+        if self.escrows.approve(id, &tx.sender, decision, height)? {
+            self.add_funds(&tx.receiver, tx.amount)?;
+        }
+        Ok(())
+    }
+
+    /// Rejects transactions that a block at `height` is too late for, or that could never be
+    /// included because their time lock runs past their expiry.
+    fn check_expiry(tx: &Transaction, height: u64) -> Result<(), String> {
+        let Some(expiry) = tx.expires_at_height else {
+            return Ok(());
+        };
+        if tx.is_expired(height) {
+            return Err(format!("Transaction expired at height {}", expiry));
+        }
+        if let Some(TimeLock::Height(lock)) = tx.valid_after {
+            if lock.saturating_add(1) >= expiry {
+                return Err(format!("Transaction is locked until after height {} but expires at height {}", lock, expiry));
+            }
+        }
+        Ok(())
+    }
+
+    /// Rejects transactions whose time lock has not passed for the block at `height` with `timestamp`.
+    fn check_time_lock(tx: &Transaction, height: u64, timestamp: i64) -> Result<(), String> {
+        match tx.valid_after {
+            Some(lock) if !lock.has_passed(height, timestamp) => Err(format!("Transaction is locked until after {}", lock)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Credits every unbonding entry that has matured by `height` back to its owner.
+    fn release_unbonded(&mut self, height: u64) -> Result<(), String> {
+        for entry in self.staking.release_matured(height) {
+            self.add_funds(&entry.address, entry.amount)?;
+        }
+        Ok(())
+    }
+
+    /// Address of the validator expected to produce the next block now, if proof-of-stake
+    /// is active.
+    pub fn expected_proposer(&self) -> Option<Address> {
+        self.proposer_at(Utc::now().timestamp())
+    }
+
+    /// Validator selected for the next block with `timestamp`. Every `PROPOSER_TIMEOUT`
+    /// seconds after the parent starts a new round with its own proposer, so a silent
+    /// proposer does not stall the chain.
+    pub fn proposer_at(&self, timestamp: i64) -> Option<Address> {
+        let parent = self.chain.last()?;
+        self.staking.select_proposer(&parent.hash, Self::proposer_round(parent, timestamp))
+    }
+
+    fn proposer_round(parent: &Block, timestamp: i64) -> u64 {
+        (timestamp.saturating_sub(parent.timestamp) / PROPOSER_TIMEOUT).max(0) as u64
+    }
+
+    /// Splits `transactions` into those a block at `height` with `timestamp` may include, in
+    /// order, and those still time-locked.
+    pub fn ready_transactions(transactions: Vec<Transaction>, height: u64, timestamp: i64) -> (Vec<Transaction>, Vec<Transaction>) {
+        Self::hold_back(transactions, |tx| tx.is_unlocked(height, timestamp))
+    }
+
+    /// Partitions `transactions` in order by `ready`. Once one of a sender's transactions
+    /// has to wait, its later ones wait with it: their nonces cannot skip it, so a block
+    /// including them would drop them for good.
+    fn hold_back(transactions: Vec<Transaction>, mut ready: impl FnMut(&Transaction) -> bool) -> (Vec<Transaction>, Vec<Transaction>) {
+        let mut waiting = HashSet::new();
+        transactions.into_iter().partition(|tx| {
+            // Spends are not numbered, so they neither wait for nor hold back anything
+            let numbered = !matches!(tx.kind, TransactionKind::Spend { .. });
+            if numbered && waiting.contains(&tx.sender) {
+                return false;
+            }
+            if ready(tx) {
+                return true;
+            }
+            if numbered {
+                waiting.insert(tx.sender.clone());
+            }
+            false
+        })
+    }
+
+    /// Splits `transactions` into those the next block has gas for, in order, and those
+    /// that have to wait for a later block, along with the later transactions of their
+    /// senders. Each is counted at its full gas limit.
+    pub fn fill_block(&self, transactions: Vec<Transaction>) -> (Vec<Transaction>, Vec<Transaction>) {
+        let mut gas = 0u64;
+        Self::hold_back(transactions, |tx| match gas.checked_add(tx.gas_limit()) {
+            Some(total) if total <= self.genesis.consensus.block_gas_limit => {
+                gas = total;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    /// Produces the next block from `transactions`, dropping those that fail validation or
+    /// that the block has no gas left for.
+    ///
+    /// Once validators are bonded the block must be signed by the selected proposer, so
+    /// `proposer_key` has to belong to it.
+    pub fn mine_block(&mut self, transactions: Vec<Transaction>, proposer_key: Option<&str>) -> Result<&Block, String> {
+        let height = self.next_height();
+        // Never before the parent, so peers accept the block even if this clock lags behind
+        let timestamp = Utc::now().timestamp().max(self.chain.last().map_or(0, |block| block.timestamp));
+        let mut proposer = None;
+        if let Some(expected) = self.proposer_at(timestamp) {
+            let key = proposer_key.ok_or("Proof-of-stake is active but no validator key is configured")?;
+            let public_key = crypto::public_key_from_private(key).ok_or("Invalid validator key")?;
+            if Address::from_public_key(&public_key) != Some(expected.clone()) {
+                return Err(format!("Not the selected proposer; expected {}", expected));
+            }
+            proposer = Some((public_key, key));
+        }
+
+        let snapshot = self.snapshot();
+        if let Err(e) = self.release_unbonded(height) {
+            self.restore(snapshot);
+            return Err(e);
+        }
+        let mut included = Vec::with_capacity(transactions.len());
+        let mut gas_used = 0u64;
+        for tx in transactions {
+            // Gas used is only known after running, so the whole limit has to fit
+            if gas_used.saturating_add(tx.gas_limit()) > self.genesis.consensus.block_gas_limit {
+                warn!("Dropping transaction from {}: not enough gas left in the block", tx.sender);
+                continue;
+            }
+            match Self::check_time_lock(&tx, height, timestamp).and_then(|()| self.apply_transaction(&tx, height)) {
+                Ok(gas) => {
+                    gas_used += gas;
+                    included.push(tx);
+                }
+                Err(e) => warn!("Dropping transaction from {}: {}", tx.sender, e),
+            }
+        }
+
+        let previous_hash = self.chain.last().map_or_else(Hash::zero, |block| block.hash.clone());
+        let mut block = Block::new(&self.genesis.chain_id, height, included, previous_hash);
+        // Time locks were checked against this timestamp
+        block.timestamp = timestamp;
+        // The proposer is part of the hash, so it has to be set before mining
+        if let Some((public_key, _)) = &proposer {
+            block.proposer = public_key.clone();
+        }
+        block.mine(self.genesis.difficulty);
+        if let Some((_, key)) = proposer {
+            block.sign(key);
+        }
+        self.chain.push(block);
+        Ok(self.chain.last().unwrap())
+    }
+
+    /// Validates a block produced elsewhere against the current state and appends it.
+    ///
+    /// The block is applied atomically: if any transaction fails, state is left untouched.
+    pub fn add_block(&mut self, block: Block) -> Result<(), String> {
+        if block.chain_id != self.genesis.chain_id {
+            return Err(format!("Block belongs to chain {}", block.chain_id));
+        }
+        let previous = self.chain.last().ok_or("Chain has no genesis block")?;
+        if block.index != previous.index + 1 {
+            return Err("Unexpected block index".to_string());
+        }
+        if block.previous_hash != previous.hash {
+            return Err("Block does not extend the chain tip".to_string());
+        }
+        if block.hash != block.calculate_hash() {
+            return Err("Block hash mismatch".to_string());
+        }
+        if block.timestamp < previous.timestamp {
+            return Err("Block timestamp is before its parent".to_string());
+        }
+        if block.timestamp > Utc::now().timestamp() + MAX_FUTURE_BLOCK_TIME {
+            return Err("Block timestamp is too far in the future".to_string());
+        }
+        if !block.meets_difficulty(self.genesis.difficulty) {
+            return Err("Block does not meet the proof of work difficulty".to_string());
+        }
+        let round = Self::proposer_round(previous, block.timestamp);
+        // A later round only starts once its time has come, so a proposer cannot skip ahead
+        // to a round it is selected in by dating its block in the future
+        if round > 0 && previous.timestamp.saturating_add(round as i64 * PROPOSER_TIMEOUT) > Utc::now().timestamp() {
+            return Err("Block claims a proposer round that has not started yet".to_string());
+        }
+        match self.proposer_at(block.timestamp) {
+            Some(expected) if Address::from_public_key(&block.proposer).as_ref() != Some(&expected) => {
+                return Err(format!("Block proposer is not the selected validator {}", expected));
+            }
+            None if !block.proposer.is_empty() => {
+                return Err("Unexpected proposer while proof-of-stake is inactive".to_string());
+            }
+            _ => {}
+        }
+        if !block.verify_proposer_signature() {
+            return Err("Invalid proposer signature".to_string());
+        }
+        // Signatures do not depend on state, so the whole block is checked up front
+        if !self.signature_cache.verify_block(&block.transactions, &self.genesis.chain_id) {
+            return Err("Invalid transaction signature in block".to_string());
+        }
+
+        let snapshot = self.snapshot();
+        let block_gas_limit = self.genesis.consensus.block_gas_limit;
+        let applied = self
+            .release_unbonded(block.index)
+            .and_then(|()| {
+                block.transactions.iter().try_fold(0u64, |gas_used, tx| {
+                    Self::check_time_lock(tx, block.index, block.timestamp)?;
+                    Self::check_expiry(tx, block.index)?;
+                    let gas_used = gas_used.saturating_add(self.apply_verified(tx, block.index)?);
+                    if gas_used > block_gas_limit {
+                        return Err(format!("Block uses more than the block gas limit of {}", block_gas_limit));
+                    }
+                    Ok(gas_used)
+                })
+            });
+        if let Err(e) = applied {
+            self.restore(snapshot);
+            return Err(e);
+        }
+        self.chain.push(block);
+        Ok(())
     }
 
     pub fn is_chain_valid(&self) -> bool {
+        if *self.genesis_hash() != self.genesis.block().hash {
+            return false;
+        }
         for i in 1..self.chain.len() {
             let current = &self.chain[i];
             let previous = &self.chain[i - 1];
@@ -36,6 +1237,15 @@ impl Blockchain {
             if current.previous_hash != previous.hash {
                 return false;
             }
+            if current.chain_id != self.genesis.chain_id {
+                return false;
+            }
+            if !current.meets_difficulty(self.genesis.difficulty) {
+                return false;
+            }
+            if !current.verify_proposer_signature() {
+                return false;
+            }
         }
         true
     }
@@ -48,7 +1258,32 @@ impl Blockchain {
 
     pub fn load_from_file(filename: &str) -> io::Result<Self> {
         let file = File::open(filename)?;
-        let blockchain = serde_json::from_reader(file)?;
+        let blockchain: Blockchain = serde_json::from_reader(file)?;
+        if blockchain.chain.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Blockchain has no genesis block"));
+        }
         Ok(blockchain)
     }
-}
\ No newline at end of file
+}
+
+/// What applying a contract deploy or call changes.
+struct ContractRun {
+    address: Address,
+    outcome: Outcome,
+    /// Final balance of every account the run touched.
+    balances: HashMap<Address, Amount>,
+}
+
+/// State saved by `Blockchain::snapshot`.
+struct Snapshot {
+    balances: HashMap<Address, Amount>,
+    utxos: UtxoSet,
+    staking: Staking,
+    htlcs: Htlcs,
+    escrows: Escrows,
+    tokens: Tokens,
+    nfts: Nfts,
+    contracts: Contracts,
+    receipts: Receipts,
+    nonces: HashMap<Address, u64>,
+}
@@ -0,0 +1,403 @@
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use wasmi::core::TrapCode;
+use wasmi::{Caller, Config, Engine, Error, Extern, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder};
+use crate::address::Address;
+use crate::types::{Amount, Hash};
+
+/// Largest contract code, in bytes. Storing it costs `GAS_PER_STORED_BYTE` per byte, so
+/// the largest contracts need most of `MAX_CONTRACT_GAS` to deploy.
+pub const MAX_CODE_BYTES: usize = 64 * 1024;
+/// Largest input a deploy or call passes to a contract, and largest output it returns, in bytes.
+pub const MAX_INPUT_BYTES: usize = 4 * 1024;
+/// Longest storage key, in bytes.
+pub const MAX_KEY_BYTES: usize = 256;
+/// Longest storage value, in bytes.
+pub const MAX_VALUE_BYTES: usize = 4 * 1024;
+/// Linear memory a contract may grow to: 16 pages of 64 KiB.
+pub const MAX_MEMORY_BYTES: usize = 16 * 64 * 1024;
+/// Most gas one deploy or call may use, whatever its gas limit.
+pub const MAX_CONTRACT_GAS: u64 = 10_000_000;
+/// Longest method name.
+pub const MAX_METHOD_LEN: usize = 64;
+/// Export run once on deploy, if the contract has it; it cannot be called afterwards.
+pub const INIT_METHOD: &str = "init";
+
+/// Gas for loading a contract, per byte of code.
+pub const GAS_PER_CODE_BYTE: u64 = 1;
+/// Gas for every host function call, on top of the Wasm instructions executed.
+pub const GAS_PER_HOST_CALL: u64 = 100;
+/// Gas per byte copied between a contract and the host.
+pub const GAS_PER_BYTE: u64 = 1;
+/// Gas per byte written to state: code on deploy, keys and values on storage writes.
+pub const GAS_PER_STORED_BYTE: u64 = 100;
+
+/// Contract storage: hex encoded keys to hex encoded values.
+pub type Storage = BTreeMap<String, String>;
+
+/// Address of the contract created by the deploy transaction `id`. It has no private key,
+/// so only the contract itself can move its funds.
+pub fn contract_address(id: &Hash) -> Address {
+    Address::from_digest(&Sha256::digest(format!("contract:{}", id)))
+}
+
+/// Rejects method names that are not 1 to 64 ASCII letters, digits and underscores.
+pub fn check_method(method: &str) -> Result<(), String> {
+    if method.is_empty() || method.len() > MAX_METHOD_LEN || !method.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("Method must be 1 to {} letters, digits and underscores", MAX_METHOD_LEN));
+    }
+    Ok(())
+}
+
+/// Decodes hex contract input, enforcing the size limit.
+pub fn decode_input(input: &str) -> Result<Vec<u8>, String> {
+    let bytes = hex::decode(input).map_err(|e| format!("Invalid contract input: {}", e))?;
+    if bytes.len() > MAX_INPUT_BYTES {
+        return Err(format!("Contract input is limited to {} bytes", MAX_INPUT_BYTES));
+    }
+    Ok(bytes)
+}
+
+/// Decodes hex contract code, enforcing the size limit.
+pub fn decode_code(code: &str) -> Result<Vec<u8>, String> {
+    let bytes = hex::decode(code).map_err(|e| format!("Invalid contract code: {}", e))?;
+    if bytes.is_empty() || bytes.len() > MAX_CODE_BYTES {
+        return Err(format!("Contract code must be 1 to {} bytes", MAX_CODE_BYTES));
+    }
+    Ok(bytes)
+}
+
+/// Rejects hex code that is not a valid Wasm module using only the supported features.
+pub fn check_code(code: &str) -> Result<(), String> {
+    Module::new(engine(), &decode_code(code)?[..]).map_err(|e| format!("Invalid contract code: {}", e))?;
+    Ok(())
+}
+
+/// A deployed WebAssembly contract and its storage.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct Contract {
+    pub address: Address,
+    pub creator: Address,
+    /// Id of the deploy transaction.
+    pub deployed_by: Hash,
+    /// SHA-256 of the code.
+    pub code_hash: Hash,
+    /// Hex encoded Wasm module.
+    pub code: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schema(value_type = BTreeMap<String, String>)]
+    pub storage: Storage,
+}
+
+/// Every deployed contract, by address.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, Default, PartialEq, Debug)]
+#[serde(transparent)]
+pub struct Contracts {
+    contracts: BTreeMap<Address, Contract>,
+}
+
+#[allow(dead_code)]
+impl Contracts {
+    pub fn is_empty(&self) -> bool {
+        self.contracts.is_empty()
+    }
+
+    pub fn get(&self, address: &Address) -> Option<&Contract> {
+        self.contracts.get(address)
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.contracts.contains_key(address)
+    }
+
+    pub fn insert(&mut self, contract: Contract) {
+        self.contracts.insert(contract.address.clone(), contract);
+    }
+
+    pub fn set_storage(&mut self, address: &Address, storage: Storage) -> Result<(), String> {
+        let contract = self.contracts.get_mut(address).ok_or_else(|| format!("No contract {}", address))?;
+        contract.storage = storage;
+        Ok(())
+    }
+}
+
+/// What a contract execution runs with. Everything a contract can observe comes from
+/// here, so every node executing it gets the same result.
+pub struct Execution {
+    pub caller: Address,
+    pub contract: Address,
+    pub height: u64,
+    /// Native funds the caller sends along, already counted in `balance`.
+    pub value: Amount,
+    /// The contract's native balance, which its transfers are paid from.
+    pub balance: Amount,
+    pub input: Vec<u8>,
+    pub gas_limit: u64,
+}
+
+/// The effects of a successful execution, for the caller to commit.
+#[derive(Debug, Default)]
+#[allow(dead_code)]
+pub struct Outcome {
+    pub gas_used: u64,
+    pub output: Vec<u8>,
+    /// The contract's storage after the execution.
+    pub storage: Storage,
+    /// Native payments from the contract, in order.
+    pub transfers: Vec<(Address, Amount)>,
+}
+
+/// A failed execution: why it failed, and the gas it used until then, which is still paid for.
+#[derive(Debug)]
+pub struct Failure {
+    pub error: String,
+    pub gas_used: u64,
+}
+
+impl From<Failure> for String {
+    fn from(failure: Failure) -> String {
+        failure.error
+    }
+}
+
+/// Failing before the contract runs uses no gas.
+impl From<String> for Failure {
+    fn from(error: String) -> Failure {
+        Failure { error, gas_used: 0 }
+    }
+}
+
+impl From<&str> for Failure {
+    fn from(error: &str) -> Failure {
+        Failure::from(error.to_string())
+    }
+}
+
+struct Host {
+    execution: Execution,
+    storage: Storage,
+    spent: Amount,
+    transfers: Vec<(Address, Amount)>,
+    output: Vec<u8>,
+    limits: StoreLimits,
+}
+
+/// The engine shared by every execution. Floats are disabled because NaN bit patterns are
+/// not guaranteed to be the same on every platform.
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut config = Config::default();
+        config.consume_fuel(true).floats(false);
+        Engine::new(&config)
+    })
+}
+
+/// Deploys `code`, charging for storing it, and runs its `init` export if it has one.
+pub fn deploy(code: &[u8], execution: Execution) -> Result<Outcome, Failure> {
+    let store_gas = (code.len() as u64).saturating_mul(GAS_PER_STORED_BYTE);
+    run(code, Storage::new(), None, execution, store_gas)
+}
+
+/// Runs `method` of a deployed contract with its `storage`.
+pub fn call(code: &[u8], storage: Storage, method: &str, execution: Execution) -> Result<Outcome, Failure> {
+    run(code, storage, Some(method), execution, 0)
+}
+
+/// Runs `method`, or `init` if there is no method and the contract exports it, metering
+/// every instruction and host call as gas on top of `upfront_gas`. A trap, running out of
+/// gas or exceeding a limit fails the whole execution, with none of its effects returned.
+fn run(code: &[u8], storage: Storage, method: Option<&str>, execution: Execution, upfront_gas: u64) -> Result<Outcome, Failure> {
+    let gas_limit = execution.gas_limit;
+    if gas_limit > MAX_CONTRACT_GAS {
+        return Err(Failure { error: format!("Gas limit exceeds the maximum of {}", MAX_CONTRACT_GAS), gas_used: 0 });
+    }
+    let upfront_gas = upfront_gas.saturating_add((code.len() as u64).saturating_mul(GAS_PER_CODE_BYTE));
+    let Some(fuel) = gas_limit.checked_sub(upfront_gas) else {
+        return Err(Failure { error: "Out of gas".to_string(), gas_used: gas_limit });
+    };
+    let module = Module::new(engine(), code).map_err(|e| Failure { error: format!("Invalid contract code: {}", e), gas_used: upfront_gas })?;
+    let host = Host {
+        execution,
+        storage,
+        spent: Amount::ZERO,
+        transfers: Vec::new(),
+        output: Vec::new(),
+        limits: StoreLimitsBuilder::new().memory_size(MAX_MEMORY_BYTES).memories(1).tables(1).instances(1).build(),
+    };
+    let mut store = Store::new(engine(), host);
+    store.limiter(|host| &mut host.limits);
+    store.set_fuel(fuel).map_err(|e| failure(&store, e.to_string()))?;
+
+    let instance = linker()
+        .instantiate(&mut store, &module)
+        .and_then(|instance| instance.start(&mut store))
+        .map_err(|e| trapped(&store, &e))?;
+    let method = match method {
+        Some(method) => Some(method),
+        None => instance.get_export(&store, INIT_METHOD).map(|_| INIT_METHOD),
+    };
+    if let Some(method) = method {
+        let run = instance.get_typed_func::<(), ()>(&store, method).map_err(|_| failure(&store, format!("Contract has no method {}", method)))?;
+        run.call(&mut store, ()).map_err(|e| trapped(&store, &e))?;
+    }
+
+    let gas_used = gas_used(&store);
+    let host = store.into_data();
+    Ok(Outcome { gas_used, output: host.output, storage: host.storage, transfers: host.transfers })
+}
+
+fn gas_used(store: &Store<Host>) -> u64 {
+    store.data().execution.gas_limit - store.get_fuel().unwrap_or(0)
+}
+
+fn failure(store: &Store<Host>, error: String) -> Failure {
+    Failure { error, gas_used: gas_used(store) }
+}
+
+/// An execution that trapped. Running out of gas uses all of it, even if the instruction
+/// that did not fit left a little over.
+fn trapped(store: &Store<Host>, error: &Error) -> Failure {
+    match error.as_trap_code() {
+        Some(TrapCode::OutOfFuel) => Failure { error: execution_error(error), gas_used: store.data().execution.gas_limit },
+        _ => failure(store, execution_error(error)),
+    }
+}
+
+fn execution_error(error: &Error) -> String {
+    if error.as_trap_code() == Some(TrapCode::OutOfFuel) {
+        return "Out of gas".to_string();
+    }
+    format!("Contract execution failed: {}", error)
+}
+
+/// Takes `gas` from what the execution has left, failing once it runs out.
+fn charge(caller: &mut Caller<'_, Host>, gas: u64) -> Result<(), Error> {
+    let fuel = caller.get_fuel().map_err(|e| Error::new(e.to_string()))?;
+    match fuel.checked_sub(gas) {
+        Some(left) => caller.set_fuel(left).map_err(|e| Error::new(e.to_string())),
+        None => {
+            caller.set_fuel(0).map_err(|e| Error::new(e.to_string()))?;
+            Err(Error::from(TrapCode::OutOfFuel))
+        }
+    }
+}
+
+fn memory(caller: &Caller<'_, Host>) -> Result<Memory, Error> {
+    caller.get_export("memory").and_then(Extern::into_memory).ok_or_else(|| Error::new("Contract does not export its memory"))
+}
+
+fn read(caller: &mut Caller<'_, Host>, ptr: i32, len: i32, limit: usize) -> Result<Vec<u8>, Error> {
+    let len = len as u32 as usize;
+    if len > limit {
+        return Err(Error::new(format!("Contract passed {} bytes where at most {} are allowed", len, limit)));
+    }
+    charge(caller, GAS_PER_HOST_CALL + len as u64 * GAS_PER_BYTE)?;
+    let mut buffer = vec![0; len];
+    memory(caller)?.read(&*caller, ptr as u32 as usize, &mut buffer).map_err(|e| Error::new(e.to_string()))?;
+    Ok(buffer)
+}
+
+fn write(caller: &mut Caller<'_, Host>, ptr: i32, bytes: &[u8]) -> Result<(), Error> {
+    charge(caller, GAS_PER_HOST_CALL + bytes.len() as u64 * GAS_PER_BYTE)?;
+    memory(caller)?.write(&mut *caller, ptr as u32 as usize, bytes).map_err(|e| Error::new(e.to_string()))
+}
+
+/// Host functions contracts import from `env`. Pointers and lengths address the
+/// contract's exported `memory`; amounts are unsigned 64-bit values passed as i64.
+fn linker() -> Linker<Host> {
+    let mut linker = Linker::new(engine());
+    linker
+        // Writes the caller's address, at most 42 bytes, to `ptr` and returns its length
+        .func_wrap("env", "caller", |mut caller: Caller<'_, Host>, ptr: i32| -> Result<i32, Error> {
+            let address = caller.data().execution.caller.to_string();
+            write(&mut caller, ptr, address.as_bytes())?;
+            Ok(address.len() as i32)
+        })
+        // Writes the contract's own address to `ptr` and returns its length
+        .and_then(|linker| {
+            linker.func_wrap("env", "address", |mut caller: Caller<'_, Host>, ptr: i32| -> Result<i32, Error> {
+                let address = caller.data().execution.contract.to_string();
+                write(&mut caller, ptr, address.as_bytes())?;
+                Ok(address.len() as i32)
+            })
+        })
+        .and_then(|linker| {
+            linker.func_wrap("env", "block_height", |mut caller: Caller<'_, Host>| -> Result<i64, Error> {
+                charge(&mut caller, GAS_PER_HOST_CALL)?;
+                Ok(caller.data().execution.height as i64)
+            })
+        })
+        // Native funds sent with the call
+        .and_then(|linker| {
+            linker.func_wrap("env", "value", |mut caller: Caller<'_, Host>| -> Result<i64, Error> {
+                charge(&mut caller, GAS_PER_HOST_CALL)?;
+                Ok(caller.data().execution.value.value() as i64)
+            })
+        })
+        .and_then(|linker| {
+            linker.func_wrap("env", "input_len", |mut caller: Caller<'_, Host>| -> Result<i32, Error> {
+                charge(&mut caller, GAS_PER_HOST_CALL)?;
+                Ok(caller.data().execution.input.len() as i32)
+            })
+        })
+        .and_then(|linker| {
+            linker.func_wrap("env", "input", |mut caller: Caller<'_, Host>, ptr: i32| -> Result<(), Error> {
+                let input = caller.data().execution.input.clone();
+                write(&mut caller, ptr, &input)
+            })
+        })
+        // Sets what the call returns, replacing earlier output
+        .and_then(|linker| {
+            linker.func_wrap("env", "output", |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> Result<(), Error> {
+                caller.data_mut().output = read(&mut caller, ptr, len, MAX_INPUT_BYTES)?;
+                Ok(())
+            })
+        })
+        // Copies at most `cap` bytes of the value under the key to `ptr` and returns the
+        // value's full length, or -1 if the key is not set
+        .and_then(|linker| {
+            linker.func_wrap("env", "storage_get", |mut caller: Caller<'_, Host>, key: i32, key_len: i32, ptr: i32, cap: i32| -> Result<i32, Error> {
+                let key = hex::encode(read(&mut caller, key, key_len, MAX_KEY_BYTES)?);
+                let Some(value) = caller.data().storage.get(&key).cloned() else {
+                    return Ok(-1);
+                };
+                let value = hex::decode(value).map_err(|e| Error::new(e.to_string()))?;
+                write(&mut caller, ptr, &value[..value.len().min(cap as u32 as usize)])?;
+                Ok(value.len() as i32)
+            })
+        })
+        .and_then(|linker| {
+            linker.func_wrap("env", "storage_set", |mut caller: Caller<'_, Host>, key: i32, key_len: i32, ptr: i32, len: i32| -> Result<(), Error> {
+                let key = read(&mut caller, key, key_len, MAX_KEY_BYTES)?;
+                let value = read(&mut caller, ptr, len, MAX_VALUE_BYTES)?;
+                charge(&mut caller, (key.len() + value.len()) as u64 * GAS_PER_STORED_BYTE)?;
+                caller.data_mut().storage.insert(hex::encode(key), hex::encode(value));
+                Ok(())
+            })
+        })
+        .and_then(|linker| {
+            linker.func_wrap("env", "storage_remove", |mut caller: Caller<'_, Host>, key: i32, key_len: i32| -> Result<(), Error> {
+                let key = hex::encode(read(&mut caller, key, key_len, MAX_KEY_BYTES)?);
+                caller.data_mut().storage.remove(&key);
+                Ok(())
+            })
+        })
+        // Pays `amount` of the contract's native balance to the address at `ptr`
+        .and_then(|linker| {
+            linker.func_wrap("env", "transfer", |mut caller: Caller<'_, Host>, ptr: i32, len: i32, amount: i64| -> Result<(), Error> {
+                let address = read(&mut caller, ptr, len, MAX_KEY_BYTES)?;
+                let address = std::str::from_utf8(&address).map_err(|e| Error::new(e.to_string())).and_then(|address| Address::parse(address).map_err(Error::new))?;
+                let amount = Amount::new(amount as u64);
+                let host = caller.data_mut();
+                let spent = host.spent.checked_add(amount).filter(|spent| *spent <= host.execution.balance);
+                host.spent = spent.ok_or_else(|| Error::new("Contract balance is too low for the transfer"))?;
+                host.transfers.push((address, amount));
+                Ok(())
+            })
+        })
+        .expect("Host functions have distinct names");
+    linker
+}
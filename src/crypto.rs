@@ -0,0 +1,189 @@
+use serde::{Serialize, Deserialize};
+use secp256k1::{Message, ecdsa::Signature, schnorr, Keypair, SecretKey, PublicKey, SECP256K1};
+use sha2::{Sha256, Digest};
+use k256::elliptic_curve::group::Group;
+use k256::elliptic_curve::ops::{LinearCombinationExt, Reduce};
+use k256::elliptic_curve::point::DecompressPoint;
+use k256::elliptic_curve::subtle::Choice;
+use k256::elliptic_curve::{Field, PrimeField};
+use k256::{AffinePoint, FieldBytes, ProjectivePoint, Scalar, U256};
+
+/// Signature algorithm a transaction is signed with. Both use the same secp256k1 keys,
+/// so an account can switch schemes without changing its address.
+#[derive(Serialize, Deserialize, Clone, Copy, utoipa::ToSchema, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureScheme {
+    /// DER encoded ECDSA.
+    #[default]
+    Ecdsa,
+    /// 64 byte BIP340 Schnorr signature over the x-only public key.
+    Schnorr,
+}
+
+#[allow(dead_code)]
+impl SignatureScheme {
+    pub fn is_ecdsa(&self) -> bool {
+        *self == SignatureScheme::Ecdsa
+    }
+}
+
+// Every function uses the process wide SECP256K1 context: building a context precomputes
+// tables, which used to dominate the cost of verifying a single signature.
+
+/// Signs `message` with `scheme`.
+pub fn sign(scheme: SignatureScheme, message: &str, private_key: &str) -> String {
+    match scheme {
+        SignatureScheme::Ecdsa => sign_message(message, private_key),
+        SignatureScheme::Schnorr => sign_schnorr(message, private_key),
+    }
+}
+
+/// Checks a signature made by `sign` with `scheme`.
+pub fn verify(scheme: SignatureScheme, message: &str, signature: &str, public_key: &str) -> bool {
+    match scheme {
+        SignatureScheme::Ecdsa => verify_message(message, signature, public_key),
+        SignatureScheme::Schnorr => verify_schnorr(message, signature, public_key),
+    }
+}
+
+/// Signs the SHA-256 digest of `message` with a hex encoded secp256k1 secret key and
+/// returns the DER signature as hex.
+pub fn sign_message(message: &str, private_key: &str) -> String {
+    let secret_key_bytes = hex::decode(private_key).expect("Invalid private key hex");
+    let secret_key = SecretKey::from_slice(&secret_key_bytes).expect("Invalid private key");
+    let msg_hash = Sha256::digest(message.as_bytes());
+    let message = Message::from_digest_slice(&msg_hash).expect("Invalid message hash");
+    let signature = SECP256K1.sign_ecdsa(&message, &secret_key);
+    hex::encode(signature.serialize_der())
+}
+
+/// Checks a hex DER signature produced by `sign_message` against a hex encoded public key.
+pub fn verify_message(message: &str, signature: &str, public_key: &str) -> bool {
+    let public_key_bytes = match hex::decode(public_key) {
+        Ok(bytes) => bytes,
+        Err(_) => return false,
+    };
+    let public_key = match PublicKey::from_slice(&public_key_bytes) {
+        Ok(key) => key,
+        Err(_) => return false,
+    };
+    let msg_hash = Sha256::digest(message.as_bytes());
+    let message = match Message::from_digest_slice(&msg_hash) {
+        Ok(msg) => msg,
+        Err(_) => return false,
+    };
+    let signature_bytes = match hex::decode(signature) {
+        Ok(bytes) => bytes,
+        Err(_) => return false,
+    };
+    let signature = match Signature::from_der(&signature_bytes) {
+        Ok(sig) => sig,
+        Err(_) => return false,
+    };
+    SECP256K1.verify_ecdsa(&message, &signature, &public_key).is_ok()
+}
+
+/// Derives the hex encoded compressed public key for a hex encoded secret key.
+#[allow(dead_code)]
+pub fn public_key_from_private(private_key: &str) -> Option<String> {
+    let secret_key_bytes = hex::decode(private_key).ok()?;
+    let secret_key = SecretKey::from_slice(&secret_key_bytes).ok()?;
+    Some(hex::encode(PublicKey::from_secret_key(SECP256K1, &secret_key).serialize()))
+}
+
+/// Signs the SHA-256 digest of `message` with BIP340 Schnorr and returns the 64 byte
+/// signature as hex.
+pub fn sign_schnorr(message: &str, private_key: &str) -> String {
+    let keypair = Keypair::from_seckey_str(SECP256K1, private_key).expect("Invalid private key");
+    let msg_hash = Sha256::digest(message.as_bytes());
+    let message = Message::from_digest_slice(&msg_hash).expect("Invalid message hash");
+    hex::encode(SECP256K1.sign_schnorr(&message, &keypair).serialize())
+}
+
+/// Checks a hex Schnorr signature against a hex compressed public key; only its x
+/// coordinate takes part, as BIP340 specifies.
+pub fn verify_schnorr(message: &str, signature: &str, public_key: &str) -> bool {
+    let public_key = match hex::decode(public_key).ok().and_then(|bytes| PublicKey::from_slice(&bytes).ok()) {
+        Some(key) => key.x_only_public_key().0,
+        None => return false,
+    };
+    let signature = match hex::decode(signature).ok().and_then(|bytes| schnorr::Signature::from_slice(&bytes).ok()) {
+        Some(signature) => signature,
+        None => return false,
+    };
+    let msg_hash = Sha256::digest(message.as_bytes());
+    let message = match Message::from_digest_slice(&msg_hash) {
+        Ok(msg) => msg,
+        Err(_) => return false,
+    };
+    SECP256K1.verify_schnorr(&signature, &message, &public_key).is_ok()
+}
+
+/// A Schnorr signature checked as part of a batch: the message, the hex signature and
+/// the hex compressed public key, as `verify_schnorr` takes them.
+pub type SchnorrItem<'a> = (&'a str, &'a str, &'a str);
+
+/// Checks BIP340 signatures together, with the same result as `verify_schnorr` on each.
+///
+/// With random weights `a_i` (the first being one), every signature `(r_i, s_i)` over
+/// key `P_i` is valid only if `(Σ a_i·s_i)·G - Σ a_i·R_i - Σ a_i·e_i·P_i` is the point at
+/// infinity, where `R_i` is the even-y point with x coordinate `r_i` and `e_i` the
+/// challenge. A single multi-scalar multiplication settles that, which costs less than
+/// verifying the signatures one by one. The weights keep a forger from choosing
+/// signatures whose errors cancel out. A malformed item fails the batch outright; if the
+/// equation does not hold, the signatures are verified one by one.
+pub fn verify_schnorr_batch(items: &[SchnorrItem]) -> bool {
+    if items.len() < 2 {
+        return items.iter().all(|(message, signature, public_key)| verify_schnorr(message, signature, public_key));
+    }
+    let mut terms = Vec::with_capacity(2 * items.len() + 1);
+    let mut s_total = Scalar::ZERO;
+    let mut rng = rand::thread_rng();
+    for (index, (message, signature, public_key)) in items.iter().enumerate() {
+        let Some(parts) = SchnorrTerms::parse(message, signature, public_key) else {
+            return false;
+        };
+        let weight = if index == 0 { Scalar::ONE } else { Scalar::random(&mut rng) };
+        s_total += weight * parts.s;
+        terms.push((parts.r, -weight));
+        terms.push((parts.key, -(weight * parts.e)));
+    }
+    terms.push((ProjectivePoint::GENERATOR, s_total));
+    if bool::from(ProjectivePoint::lincomb_ext(terms.as_slice()).is_identity()) {
+        return true;
+    }
+    items.iter().all(|(message, signature, public_key)| verify_schnorr(message, signature, public_key))
+}
+
+/// The parts of a BIP340 signature that enter the batch equation.
+struct SchnorrTerms {
+    r: ProjectivePoint,
+    s: Scalar,
+    e: Scalar,
+    key: ProjectivePoint,
+}
+
+impl SchnorrTerms {
+    /// Lifts the nonce and key to their even-y points and computes the challenge
+    /// `e = H("BIP0340/challenge", r || x(P) || m)`, where `m` is the SHA-256 digest of
+    /// `message` that `sign_schnorr` signs. None if any part is out of range.
+    fn parse(message: &str, signature: &str, public_key: &str) -> Option<Self> {
+        let public_key = PublicKey::from_slice(&hex::decode(public_key).ok()?).ok()?;
+        let x = FieldBytes::from(public_key.x_only_public_key().0.serialize());
+        let signature: [u8; 64] = hex::decode(signature).ok()?.try_into().ok()?;
+        let (r_bytes, s_bytes) = signature.split_at(32);
+        let r = Option::<AffinePoint>::from(AffinePoint::decompress(FieldBytes::from_slice(r_bytes), Choice::from(0)))?;
+        let s = Option::<Scalar>::from(Scalar::from_repr(*FieldBytes::from_slice(s_bytes)))?;
+        let key = Option::<AffinePoint>::from(AffinePoint::decompress(&x, Choice::from(0)))?;
+        let tag = Sha256::digest(b"BIP0340/challenge");
+        let challenge = Sha256::new()
+            .chain_update(tag)
+            .chain_update(tag)
+            .chain_update(r_bytes)
+            .chain_update(x)
+            .chain_update(Sha256::digest(message.as_bytes()))
+            .finalize();
+        let e = <Scalar as Reduce<U256>>::reduce_bytes(&challenge);
+        Some(SchnorrTerms { r: r.into(), s, e, key: key.into() })
+    }
+}
@@ -0,0 +1,119 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use crate::address::Address;
+use crate::types::{Amount, Hash};
+
+/// Matching approvals, out of the three parties, that settle an escrow.
+pub const ESCROW_APPROVALS: usize = 2;
+
+/// How a party wants an escrow settled.
+#[derive(Serialize, Deserialize, Clone, Copy, utoipa::ToSchema, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum EscrowDecision {
+    /// Pay the amount to the payee.
+    Release,
+    /// Return the amount to the payer.
+    Refund,
+}
+
+/// Where an escrow stands.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum EscrowStatus {
+    /// Funds are held until two parties approve the same decision.
+    Open,
+    /// Paid to the payee at `height`.
+    Released { height: u64 },
+    /// Returned to the payer at `height`.
+    Refunded { height: u64 },
+}
+
+/// Funds the payer locked naming a payee and an arbiter. Any two of the three can release
+/// them to the payee or refund them to the payer, each by signing an approval.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct Escrow {
+    /// Id of the transaction that created the escrow.
+    pub id: Hash,
+    pub payer: Address,
+    pub payee: Address,
+    pub arbiter: Address,
+    pub amount: Amount,
+    /// The latest decision each party approved; a party may change its mind until settlement.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub approvals: BTreeMap<Address, EscrowDecision>,
+    #[serde(flatten)]
+    pub status: EscrowStatus,
+}
+
+#[allow(dead_code)]
+impl Escrow {
+    pub fn is_open(&self) -> bool {
+        self.status == EscrowStatus::Open
+    }
+
+    pub fn is_party(&self, address: &Address) -> bool {
+        [&self.payer, &self.payee, &self.arbiter].contains(&address)
+    }
+
+    /// Who `decision` pays.
+    pub fn beneficiary(&self, decision: EscrowDecision) -> &Address {
+        match decision {
+            EscrowDecision::Release => &self.payee,
+            EscrowDecision::Refund => &self.payer,
+        }
+    }
+
+    /// Whether `party` approving `decision` would settle the escrow.
+    pub fn settles(&self, party: &Address, decision: EscrowDecision) -> bool {
+        let others = self.approvals.iter().filter(|(voter, vote)| *voter != party && **vote == decision).count();
+        others + 1 >= ESCROW_APPROVALS
+    }
+}
+
+/// Every escrow created on chain, by id. Settled escrows are kept so that their creation
+/// cannot be replayed and their outcome stays queryable.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, Default, PartialEq, Debug)]
+#[serde(transparent)]
+pub struct Escrows {
+    escrows: BTreeMap<Hash, Escrow>,
+}
+
+#[allow(dead_code)]
+impl Escrows {
+    pub fn is_empty(&self) -> bool {
+        self.escrows.is_empty()
+    }
+
+    pub fn get(&self, id: &Hash) -> Option<&Escrow> {
+        self.escrows.get(id)
+    }
+
+    pub fn contains(&self, id: &Hash) -> bool {
+        self.escrows.contains_key(id)
+    }
+
+    pub fn insert(&mut self, escrow: Escrow) {
+        self.escrows.insert(escrow.id.clone(), escrow);
+    }
+
+    /// Records `party` approving `decision` for the open escrow `id`, settling it at
+    /// `height` once enough parties agree. Returns whether it settled.
+    pub fn approve(&mut self, id: &Hash, party: &Address, decision: EscrowDecision, height: u64) -> Result<bool, String> {
+        let escrow = self.escrows.get_mut(id).ok_or_else(|| format!("No escrow {}", id))?;
+        if !escrow.is_open() {
+            return Err(format!("Escrow {} is already settled", id));
+        }
+        if !escrow.is_party(party) {
+            return Err(format!("{} is not a party to escrow {}", party, id));
+        }
+        let settles = escrow.settles(party, decision);
+        escrow.approvals.insert(party.clone(), decision);
+        if settles {
+            escrow.status = match decision {
+                EscrowDecision::Release => EscrowStatus::Released { height },
+                EscrowDecision::Refund => EscrowStatus::Refunded { height },
+            };
+        }
+        Ok(settles)
+    }
+}
@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+use crate::address::Address;
+use crate::genesis::FaucetConfig;
+use crate::types::Amount;
+
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Node-side rate limiting for faucet grants. This is policy of the node handing out
+/// funds, not consensus, so it lives outside `Blockchain` and is not persisted.
+#[derive(Default)]
+pub struct FaucetLimiter {
+    last_grant: HashMap<Address, i64>,
+    day: i64,
+    granted_today: Amount,
+}
+
+impl FaucetLimiter {
+    /// Records a grant to `address` at unix time `now`, or explains why it is refused.
+    pub fn check_and_record(&mut self, config: &FaucetConfig, address: &Address, now: i64) -> Result<(), String> {
+        if let Some(last) = self.last_grant.get(address) {
+            let ready_at = last + config.cooldown_secs;
+            if now < ready_at {
+                return Err(format!("Faucet cooldown: try again in {} seconds", ready_at - now));
+            }
+        }
+        let today = now.div_euclid(SECONDS_PER_DAY);
+        if today != self.day {
+            self.day = today;
+            self.granted_today = Amount::ZERO;
+        }
+        let granted = self
+            .granted_today
+            .checked_add(config.amount)
+            .filter(|granted| *granted <= config.daily_cap)
+            .ok_or("Faucet daily cap reached")?;
+        self.granted_today = granted;
+        self.last_grant.insert(address.clone(), now);
+        Ok(())
+    }
+}
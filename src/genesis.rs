@@ -0,0 +1,171 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use crate::address::Address;
+use crate::block::Block;
+use crate::staking::{DEFAULT_MIN_STAKE, DEFAULT_SLASH_PERCENT, DEFAULT_UNBONDING_DELAY};
+use crate::types::{Amount, Hash};
+
+pub const DEFAULT_CHAIN_ID: &str = "chainrust-dev";
+pub const DEFAULT_MEMO_FEE_PER_BYTE: Amount = Amount::new(1);
+pub const DEFAULT_BLOCK_GAS_LIMIT: u64 = 30_000_000;
+pub const DEFAULT_MIN_GAS_PRICE: Amount = Amount::ZERO;
+
+/// Staking and fee parameters fixed at genesis.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+#[serde(default)]
+pub struct ConsensusParams {
+    pub unbonding_delay: u64,
+    pub min_stake: Amount,
+    pub slash_percent: u64,
+    /// Burned per byte of a transaction memo. Left out of the encoding at its default so
+    /// configs written before memos existed keep their genesis hash.
+    #[serde(skip_serializing_if = "is_default_memo_fee")]
+    pub memo_fee_per_byte: Amount,
+    /// Most gas the transactions of one block may use together. Left out of the encoding
+    /// at its default, as are the gas parameters below.
+    #[serde(skip_serializing_if = "is_default_block_gas_limit")]
+    pub block_gas_limit: u64,
+    /// Lowest gas price a transaction may offer. At zero, scripted transactions may leave
+    /// out gas and run their scripts for free.
+    #[serde(skip_serializing_if = "is_default_min_gas_price")]
+    pub min_gas_price: Amount,
+}
+
+fn is_default_memo_fee(fee: &Amount) -> bool {
+    *fee == DEFAULT_MEMO_FEE_PER_BYTE
+}
+
+fn is_default_block_gas_limit(limit: &u64) -> bool {
+    *limit == DEFAULT_BLOCK_GAS_LIMIT
+}
+
+fn is_default_min_gas_price(price: &Amount) -> bool {
+    *price == DEFAULT_MIN_GAS_PRICE
+}
+
+impl Default for ConsensusParams {
+    fn default() -> Self {
+        ConsensusParams {
+            unbonding_delay: DEFAULT_UNBONDING_DELAY,
+            min_stake: DEFAULT_MIN_STAKE,
+            slash_percent: DEFAULT_SLASH_PERCENT,
+            memo_fee_per_byte: DEFAULT_MEMO_FEE_PER_BYTE,
+            block_gas_limit: DEFAULT_BLOCK_GAS_LIMIT,
+            min_gas_price: DEFAULT_MIN_GAS_PRICE,
+        }
+    }
+}
+
+/// How a chain tracks who owns what.
+#[derive(Serialize, Deserialize, Clone, Copy, utoipa::ToSchema, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerModel {
+    /// A balance per address, debited and credited by transfers.
+    #[default]
+    Account,
+    /// Bitcoin-style unspent outputs, consumed and created by spend transactions.
+    Utxo,
+}
+
+impl LedgerModel {
+    pub fn is_account(&self) -> bool {
+        *self == LedgerModel::Account
+    }
+}
+
+/// A faucet that mints test funds from a designated account. Only dev networks may
+/// configure one; without it mint transactions are invalid.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct FaucetConfig {
+    /// Address of the faucet account that signs mint transactions.
+    pub address: Address,
+    /// Amount minted per grant.
+    pub amount: Amount,
+    /// Seconds an address has to wait between grants.
+    pub cooldown_secs: i64,
+    /// Total amount the faucet grants per UTC day.
+    pub daily_cap: Amount,
+}
+
+/// Everything needed to reproduce the genesis block and the initial state.
+///
+/// Maps are ordered so the config serializes identically on every node. Accounts are
+/// typed, so a config naming a malformed address fails to load.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+#[serde(default)]
+pub struct GenesisConfig {
+    pub chain_id: String,
+    pub timestamp: i64,
+    /// Number of leading zero hex digits a block hash needs to satisfy proof of work.
+    pub difficulty: u32,
+    pub balances: BTreeMap<Address, Amount>,
+    /// Stake bonded at genesis, which activates proof-of-stake from the first block.
+    pub validators: BTreeMap<Address, Amount>,
+    pub consensus: ConsensusParams,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub faucet: Option<FaucetConfig>,
+    /// Ledger model for the whole life of the chain. Left out of the encoding for the
+    /// account model so existing configs keep their genesis hash.
+    #[serde(skip_serializing_if = "LedgerModel::is_account")]
+    pub ledger: LedgerModel,
+    /// Marks a chain other than the default dev chain as a dev network, which may then
+    /// configure a faucet. Left out of the encoding when false.
+    #[serde(skip_serializing_if = "is_false")]
+    pub dev: bool,
+}
+
+fn is_false(flag: &bool) -> bool {
+    !flag
+}
+
+impl Default for GenesisConfig {
+    fn default() -> Self {
+        GenesisConfig {
+            chain_id: DEFAULT_CHAIN_ID.to_string(),
+            timestamp: 0,
+            difficulty: 0,
+            balances: BTreeMap::new(),
+            validators: BTreeMap::new(),
+            consensus: ConsensusParams::default(),
+            faucet: None,
+            ledger: LedgerModel::Account,
+            dev: false,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl GenesisConfig {
+    pub fn load_from_file(filename: &str) -> io::Result<Self> {
+        let file = File::open(filename)?;
+        let config: GenesisConfig = serde_json::from_reader(file)?;
+        config.validate().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(config)
+    }
+
+    /// Whether this is a dev network: the default dev chain, or one marked `dev`.
+    pub fn is_dev(&self) -> bool {
+        self.dev || self.chain_id == DEFAULT_CHAIN_ID
+    }
+
+    /// Rejects settings a node must not run with. A faucet mints from a published key,
+    /// so it is refused outside dev networks.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.faucet.is_some() && !self.is_dev() {
+            return Err(format!("Chain {} is not a dev network and cannot configure a faucet", self.chain_id));
+        }
+        Ok(())
+    }
+
+    /// SHA-256 of the canonical JSON encoding of the config.
+    pub fn hash(&self) -> Hash {
+        let bytes = serde_json::to_vec(self).expect("Failed to serialize genesis config");
+        Hash::digest(bytes)
+    }
+
+    pub fn block(&self) -> Block {
+        Block::genesis(&self.chain_id, self.timestamp, self.hash())
+    }
+}
@@ -0,0 +1,132 @@
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use crate::address::Address;
+use crate::types::{Amount, Hash};
+
+/// Length of an HTLC preimage in bytes. A fixed size keeps a preimage revealed on one
+/// chain usable on the other side of a swap, which may not accept arbitrary lengths.
+pub const PREIMAGE_BYTES: usize = 32;
+
+/// Hashlock a hex encoded preimage unlocks: the SHA-256 of its bytes.
+pub fn hashlock_of(preimage: &str) -> Result<Hash, String> {
+    let bytes = hex::decode(preimage).map_err(|e| format!("Invalid preimage: {}", e))?;
+    if bytes.len() != PREIMAGE_BYTES {
+        return Err(format!("Preimage must be {} bytes", PREIMAGE_BYTES));
+    }
+    Ok(Hash::digest(bytes))
+}
+
+/// A fresh random preimage, hex encoded, for the party that starts a swap.
+#[allow(dead_code)]
+pub fn generate_preimage() -> String {
+    let mut bytes = [0u8; PREIMAGE_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Where a hash time-locked contract stands.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum HtlcStatus {
+    /// The amount is locked and can still be claimed or, after the timeout, refunded.
+    Open,
+    /// Paid to the receiver at `height`, revealing `preimage`.
+    Claimed { preimage: String, height: u64 },
+    /// Returned to the sender at `height` after the timeout passed.
+    Refunded { height: u64 },
+}
+
+/// Funds locked by `sender` that `receiver` can claim by revealing the preimage of
+/// `hashlock` in a block below `timeout_height`. From that height on only the sender can
+/// take them back.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct Htlc {
+    /// Id of the transaction that created the contract.
+    pub id: Hash,
+    pub sender: Address,
+    pub receiver: Address,
+    pub amount: Amount,
+    pub hashlock: Hash,
+    pub timeout_height: u64,
+    #[serde(flatten)]
+    pub status: HtlcStatus,
+}
+
+#[allow(dead_code)]
+impl Htlc {
+    pub fn is_open(&self) -> bool {
+        self.status == HtlcStatus::Open
+    }
+
+    /// Whether a block at `height` is too late to claim the contract, and so may refund it.
+    pub fn has_timed_out(&self, height: u64) -> bool {
+        height >= self.timeout_height
+    }
+}
+
+/// Every HTLC created on chain, by id. Settled contracts are kept so that their lock
+/// transaction cannot be replayed and their preimages stay available to swap partners.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, Default, PartialEq, Debug)]
+#[serde(transparent)]
+pub struct Htlcs {
+    contracts: BTreeMap<Hash, Htlc>,
+}
+
+#[allow(dead_code)]
+impl Htlcs {
+    pub fn is_empty(&self) -> bool {
+        self.contracts.is_empty()
+    }
+
+    pub fn get(&self, id: &Hash) -> Option<&Htlc> {
+        self.contracts.get(id)
+    }
+
+    pub fn contains(&self, id: &Hash) -> bool {
+        self.contracts.contains_key(id)
+    }
+
+    pub fn insert(&mut self, htlc: Htlc) {
+        self.contracts.insert(htlc.id.clone(), htlc);
+    }
+
+    /// Records how the open contract `id` was settled.
+    pub fn settle(&mut self, id: &Hash, status: HtlcStatus) -> Result<(), String> {
+        let htlc = self.contracts.get_mut(id).ok_or_else(|| format!("No HTLC {}", id))?;
+        if !htlc.is_open() {
+            return Err(format!("HTLC {} is already settled", id));
+        }
+        htlc.status = status;
+        Ok(())
+    }
+
+    /// Open contracts, ordered by timeout, optionally only those `address` sent or receives.
+    pub fn open(&self, address: Option<&Address>) -> Vec<&Htlc> {
+        let mut open: Vec<&Htlc> = self
+            .contracts
+            .values()
+            .filter(|htlc| htlc.is_open())
+            .filter(|htlc| match address {
+                Some(address) => htlc.sender == *address || htlc.receiver == *address,
+                None => true,
+            })
+            .collect();
+        open.sort_by(|a, b| a.timeout_height.cmp(&b.timeout_height).then_with(|| a.id.cmp(&b.id)));
+        open
+    }
+
+    /// The first claimed contract locked to `hashlock`, which holds its preimage.
+    pub fn revealed(&self, hashlock: &Hash) -> Option<&Htlc> {
+        self.contracts
+            .values()
+            .filter(|htlc| htlc.hashlock == *hashlock)
+            .filter_map(|htlc| match &htlc.status {
+                HtlcStatus::Claimed { height, .. } => Some((*height, htlc)),
+                _ => None,
+            })
+            .min_by_key(|(height, _)| *height)
+            .map(|(_, htlc)| htlc)
+    }
+}
@@ -0,0 +1,235 @@
+use serde::{Serialize, Deserialize};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::fs;
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+use crate::address::Address;
+use crate::wallet::Wallet;
+
+pub const KEYSTORE_VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+/// Highest Argon2 costs a stored or imported entry may ask for, a few times the defaults.
+/// Without a cap, a crafted entry could make every unlock attempt exhaust memory or CPU.
+pub const MAX_KDF_MEMORY_KIB: u32 = 64 * 1024;
+pub const MAX_KDF_ITERATIONS: u32 = 8;
+pub const MAX_KDF_PARALLELISM: u32 = 4;
+
+/// Argon2id cost parameters, stored with each key so they can be raised later without
+/// breaking existing files.
+#[derive(Serialize, Deserialize, Clone, Copy, utoipa::ToSchema, PartialEq, Debug)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// Refuses costs above the `MAX_KDF_*` caps.
+    pub fn check(&self) -> Result<(), String> {
+        if self.memory_kib > MAX_KDF_MEMORY_KIB || self.iterations > MAX_KDF_ITERATIONS || self.parallelism > MAX_KDF_PARALLELISM {
+            return Err(format!(
+                "KDF parameters exceed the limits of {} KiB, {} iterations and parallelism {}",
+                MAX_KDF_MEMORY_KIB, MAX_KDF_ITERATIONS, MAX_KDF_PARALLELISM
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: 1,
+        }
+    }
+}
+
+/// A secret key sealed with XChaCha20-Poly1305 under an Argon2id password hash.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct EncryptedKey {
+    pub kdf: String,
+    pub kdf_params: KdfParams,
+    pub salt: String,
+    pub cipher: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+/// One key in the keystore. The address is bound into the ciphertext as associated data,
+/// so an entry relabelled with another address fails to decrypt.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct KeystoreEntry {
+    pub version: u32,
+    pub address: Address,
+    pub public_key: String,
+    pub crypto: EncryptedKey,
+}
+
+#[allow(dead_code)]
+impl KeystoreEntry {
+    /// Encrypts the secret key of `wallet` with `password`.
+    pub fn encrypt(wallet: &Wallet, password: &str, params: KdfParams) -> Result<Self, String> {
+        let secret = Zeroizing::new(hex::decode(&wallet.private_key).map_err(|e| format!("Invalid private key: {}", e))?);
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+        let key = derive_key(password, &salt, params)?;
+        let cipher = XChaCha20Poly1305::new(key.as_slice().into());
+        let payload = Payload { msg: &secret, aad: wallet.address.as_str().as_bytes() };
+        let ciphertext = cipher
+            .encrypt(XNonce::from_slice(&nonce), payload)
+            .map_err(|_| "Encryption failed".to_string())?;
+        Ok(KeystoreEntry {
+            version: KEYSTORE_VERSION,
+            address: wallet.address.clone(),
+            public_key: wallet.public_key.clone(),
+            crypto: EncryptedKey {
+                kdf: "argon2id".to_string(),
+                kdf_params: params,
+                salt: hex::encode(salt),
+                cipher: "xchacha20poly1305".to_string(),
+                nonce: hex::encode(nonce),
+                ciphertext: hex::encode(ciphertext),
+            },
+        })
+    }
+
+    /// Recovers the wallet, failing on a wrong password or a tampered entry.
+    pub fn decrypt(&self, password: &str) -> Result<Wallet, String> {
+        if self.version != KEYSTORE_VERSION || self.crypto.kdf != "argon2id" || self.crypto.cipher != "xchacha20poly1305" {
+            return Err("Unsupported keystore format".to_string());
+        }
+        let salt = hex::decode(&self.crypto.salt).map_err(|_| "Malformed keystore salt")?;
+        let nonce = hex::decode(&self.crypto.nonce).map_err(|_| "Malformed keystore nonce")?;
+        let ciphertext = hex::decode(&self.crypto.ciphertext).map_err(|_| "Malformed keystore ciphertext")?;
+        if nonce.len() != NONCE_LEN {
+            return Err("Malformed keystore nonce".to_string());
+        }
+        self.crypto.kdf_params.check()?;
+        let key = derive_key(password, &salt, self.crypto.kdf_params)?;
+        let cipher = XChaCha20Poly1305::new(key.as_slice().into());
+        let payload = Payload { msg: &ciphertext, aad: self.address.as_str().as_bytes() };
+        let secret = Zeroizing::new(
+            cipher
+                .decrypt(XNonce::from_slice(&nonce), payload)
+                .map_err(|_| "Wrong password or corrupted keystore entry".to_string())?,
+        );
+        let wallet = Wallet::from_private_key(&hex::encode(secret.as_slice()))?;
+        if wallet.address != self.address {
+            return Err("Keystore entry does not match its address".to_string());
+        }
+        Ok(wallet)
+    }
+}
+
+fn derive_key(password: &str, salt: &[u8], params: KdfParams) -> Result<Zeroizing<[u8; KEY_LEN]>, String> {
+    let params = Params::new(params.memory_kib, params.iterations, params.parallelism, Some(KEY_LEN))
+        .map_err(|e| format!("Invalid KDF parameters: {}", e))?;
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, key.as_mut())
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+/// A directory of encrypted keys, one `<address>.json` file per key.
+#[derive(Clone)]
+pub struct Keystore {
+    dir: PathBuf,
+    params: KdfParams,
+}
+
+#[allow(dead_code)]
+impl Keystore {
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        Self::with_params(dir, KdfParams::default())
+    }
+
+    /// Opens a keystore that seals new keys with `params`; lower costs are only meant for tests.
+    pub fn with_params(dir: impl AsRef<Path>, params: KdfParams) -> io::Result<Self> {
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        builder.mode(0o700);
+        builder.create(dir.as_ref())?;
+        Ok(Keystore { dir: dir.as_ref().to_path_buf(), params })
+    }
+
+    fn path(&self, address: &Address) -> PathBuf {
+        self.dir.join(format!("{}.json", address))
+    }
+
+    /// Addresses of every key in the keystore, sorted.
+    pub fn list(&self) -> Result<Vec<Address>, String> {
+        let mut addresses = Vec::new();
+        for file in fs::read_dir(&self.dir).map_err(|e| e.to_string())? {
+            let path = file.map_err(|e| e.to_string())?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(address) = path.file_stem().and_then(|stem| stem.to_str()).and_then(|stem| Address::parse(stem).ok()) {
+                    addresses.push(address);
+                }
+            }
+        }
+        addresses.sort();
+        Ok(addresses)
+    }
+
+    pub fn entry(&self, address: &Address) -> Result<KeystoreEntry, String> {
+        let json = fs::read_to_string(self.path(address)).map_err(|_| format!("No key for {} in the keystore", address))?;
+        serde_json::from_str(&json).map_err(|e| format!("Corrupted keystore entry for {}: {}", address, e))
+    }
+
+    /// Encrypts and stores `wallet`, refusing to overwrite an existing key.
+    pub fn insert(&self, wallet: &Wallet, password: &str) -> Result<KeystoreEntry, String> {
+        let entry = KeystoreEntry::encrypt(wallet, password, self.params)?;
+        self.import_entry(&entry)?;
+        Ok(entry)
+    }
+
+    /// Imports a hex secret key.
+    pub fn import(&self, private_key: &str, password: &str) -> Result<Address, String> {
+        let wallet = Wallet::from_private_key(private_key)?;
+        self.insert(&wallet, password).map(|entry| entry.address)
+    }
+
+    /// Stores an entry exported from another keystore as is. The file is readable by its
+    /// owner only.
+    pub fn import_entry(&self, entry: &KeystoreEntry) -> Result<(), String> {
+        entry.crypto.kdf_params.check()?;
+        let json = serde_json::to_string_pretty(entry).map_err(|e| e.to_string())?;
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = match options.open(self.path(&entry.address)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Err(format!("Key for {} already exists", entry.address)),
+            Err(e) => return Err(e.to_string()),
+        };
+        file.write_all(json.as_bytes()).map_err(|e| e.to_string())
+    }
+
+    /// The encrypted entry for `address`, after checking `password` opens it.
+    pub fn export(&self, address: &Address, password: &str) -> Result<KeystoreEntry, String> {
+        let entry = self.entry(address)?;
+        entry.decrypt(password)?;
+        Ok(entry)
+    }
+
+    /// Decrypts the key for `address`.
+    pub fn unlock(&self, address: &Address, password: &str) -> Result<Wallet, String> {
+        self.entry(address)?.decrypt(password)
+    }
+}
@@ -1,17 +1,39 @@
 use actix_web::{HttpServer, App};
 use std::io;
 use std::sync::{Arc, Mutex};
-use log::{info, error};
+use log::{info, warn, error};
 use models::AppState;
-use api::{get_chain, add_block, faucet, add_transaction, create_wallet, check_balance, ApiDoc};
+use api::{get_chain, add_block, faucet, add_transaction, add_batch_transaction, submit_signed_transaction, list_utxos, transaction_status, search_transactions, multisig_address, script_address, create_wallet, list_keys, import_key, export_key, check_balance, next_nonce, list_tokens, token_balance, bond, unbond, get_validators, submit_evidence, lock_htlc, claim_htlc, refund_htlc, list_htlcs, htlc_preimage, create_escrow, release_escrow, refund_escrow, get_escrow, create_token, transfer_token, mint_token, burn_token, create_collection, mint_nft, transfer_nft, list_nfts, get_nft, nft_owner, nft_history, deploy_contract, execute_contract, call_contract, get_contract, get_receipt, node_info, add_peer, list_peers, ApiDoc};
 use blockchain::Blockchain;
+use faucet::FaucetLimiter;
+use genesis::GenesisConfig;
+use keystore::Keystore;
+use peers::PeerSet;
 use utoipa_swagger_ui::SwaggerUi;
 use utoipa::OpenApi;
 
+mod address;
 mod api;
 mod blockchain;
 mod block;
+mod contract;
+mod crypto;
+mod escrow;
+mod faucet;
+mod genesis;
+mod htlc;
+mod keystore;
+mod multisig;
+mod nft;
+mod peers;
+mod receipt;
+mod script;
+mod sigcache;
+mod staking;
+mod token;
 mod transaction;
+mod types;
+mod utxo;
 mod wallet;
 mod models;
 
@@ -19,21 +41,61 @@ mod models;
 async fn main() -> io::Result<()> {
     env_logger::init();
     let filename = "blockchain.json";
+    let genesis_filename = std::env::var("CHAINRUST_GENESIS").unwrap_or_else(|_| "genesis.json".to_string());
     info!("Starting blockchain application!");
+    let genesis = match GenesisConfig::load_from_file(&genesis_filename) {
+        Ok(genesis) => {
+            info!("Loaded genesis config for chain {} from {}", genesis.chain_id, genesis_filename);
+            genesis
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            warn!("No genesis config at {}, using the default dev genesis", genesis_filename);
+            GenesisConfig::default()
+        }
+        Err(e) => {
+            error!("Invalid genesis config {}: {}", genesis_filename, e);
+            return Err(e);
+        }
+    };
+    let genesis_hash = genesis.block().hash;
+    info!("Genesis hash: {}", genesis_hash);
     let blockchain: Blockchain = match Blockchain::load_from_file(filename) {
-        Ok(blockchain) => {
+        Ok(blockchain) if *blockchain.genesis_hash() == genesis_hash => {
             info!("Loaded blockchain from {} with {} blocks", filename, blockchain.chain.len());
             blockchain
         }
+        Ok(blockchain) => {
+            error!(
+                "Blockchain in {} starts from genesis {}, not {}. Creating new blockchain.",
+                filename,
+                blockchain.genesis_hash(),
+                genesis_hash
+            );
+            Blockchain::from_genesis(genesis)
+        }
         Err(e) => {
             error!("Failed to load blockchain from {}: {}. Creating new blockchain.", filename, e);
-            Blockchain::new()
+            Blockchain::from_genesis(genesis)
         }
     };
 
+    let keystore_dir = std::env::var("CHAINRUST_KEYSTORE").unwrap_or_else(|_| "keystore".to_string());
+    let keystore = Keystore::open(&keystore_dir)?;
+    info!("Keystore at {}", keystore_dir);
+    let dev_mode = std::env::var("CHAINRUST_DEV_MODE").is_ok_and(|value| value == "1");
+    if dev_mode {
+        warn!("Dev mode is enabled: POST /wallet returns plaintext private keys");
+    }
+
     let app_state = actix_web::web::Data::new(AppState {
         blockchain: Arc::new(Mutex::new(blockchain)),
         mempool: Arc::new(Mutex::new(Vec::new())),
+        validator_key: std::env::var("CHAINRUST_VALIDATOR_KEY").ok(),
+        faucet_key: std::env::var("CHAINRUST_FAUCET_KEY").ok(),
+        faucet_limiter: Arc::new(Mutex::new(FaucetLimiter::default())),
+        peers: Arc::new(Mutex::new(PeerSet::default())),
+        keystore,
+        dev_mode,
     });
     let app_state_clone = Arc::clone(&app_state);
 
@@ -46,9 +108,54 @@ async fn main() -> io::Result<()> {
             .route("/chain", actix_web::web::get().to(get_chain))
             .route("/add_block", actix_web::web::post().to(add_block))
             .route("/faucet", actix_web::web::post().to(faucet))
+            .route("/utxos", actix_web::web::get().to(list_utxos))
             .route("/transaction", actix_web::web::post().to(add_transaction))
+            .route("/transaction/batch", actix_web::web::post().to(add_batch_transaction))
+            .route("/transaction/signed", actix_web::web::post().to(submit_signed_transaction))
+            .route("/transaction/{id}", actix_web::web::get().to(transaction_status))
+            .route("/transactions/search", actix_web::web::get().to(search_transactions))
+            .route("/multisig/address", actix_web::web::post().to(multisig_address))
+            .route("/script/address", actix_web::web::post().to(script_address))
             .route("/wallet", actix_web::web::post().to(create_wallet))
+            .route("/keys", actix_web::web::get().to(list_keys))
+            .route("/keys/import", actix_web::web::post().to(import_key))
+            .route("/keys/export", actix_web::web::post().to(export_key))
             .route("/check_balance", actix_web::web::get().to(check_balance))
+            .route("/nonce", actix_web::web::get().to(next_nonce))
+            .route("/stake/bond", actix_web::web::post().to(bond))
+            .route("/stake/unbond", actix_web::web::post().to(unbond))
+            .route("/validators", actix_web::web::get().to(get_validators))
+            .route("/evidence", actix_web::web::post().to(submit_evidence))
+            .route("/htlc/lock", actix_web::web::post().to(lock_htlc))
+            .route("/htlc/claim", actix_web::web::post().to(claim_htlc))
+            .route("/htlc/refund", actix_web::web::post().to(refund_htlc))
+            .route("/htlcs", actix_web::web::get().to(list_htlcs))
+            .route("/htlc/preimage/{hashlock}", actix_web::web::get().to(htlc_preimage))
+            .route("/escrow/create", actix_web::web::post().to(create_escrow))
+            .route("/escrow/release", actix_web::web::post().to(release_escrow))
+            .route("/escrow/refund", actix_web::web::post().to(refund_escrow))
+            .route("/escrow/{id}", actix_web::web::get().to(get_escrow))
+            .route("/tokens", actix_web::web::get().to(list_tokens))
+            .route("/tokens/create", actix_web::web::post().to(create_token))
+            .route("/tokens/transfer", actix_web::web::post().to(transfer_token))
+            .route("/tokens/mint", actix_web::web::post().to(mint_token))
+            .route("/tokens/burn", actix_web::web::post().to(burn_token))
+            .route("/tokens/{id}/balance", actix_web::web::get().to(token_balance))
+            .route("/nft/collection", actix_web::web::post().to(create_collection))
+            .route("/nft/mint", actix_web::web::post().to(mint_nft))
+            .route("/nft/transfer", actix_web::web::post().to(transfer_nft))
+            .route("/nfts", actix_web::web::get().to(list_nfts))
+            .route("/nft/{id}", actix_web::web::get().to(get_nft))
+            .route("/nft/{id}/owner", actix_web::web::get().to(nft_owner))
+            .route("/nft/{id}/history", actix_web::web::get().to(nft_history))
+            .route("/contract/deploy", actix_web::web::post().to(deploy_contract))
+            .route("/contract/execute", actix_web::web::post().to(execute_contract))
+            .route("/contract/call", actix_web::web::post().to(call_contract))
+            .route("/contract/{address}", actix_web::web::get().to(get_contract))
+            .route("/receipt/{id}", actix_web::web::get().to(get_receipt))
+            .route("/node_info", actix_web::web::get().to(node_info))
+            .route("/peers", actix_web::web::post().to(add_peer))
+            .route("/peers", actix_web::web::get().to(list_peers))
             .service(
                 SwaggerUi::new("/swagger-ui/{_:.*}")
                     .url("/api-docs/openapi.json", ApiDoc::openapi())
@@ -61,7 +168,7 @@ async fn main() -> io::Result<()> {
     info!("Server shutting down, validating and saving blockchain");
     let blockchain = app_state_clone.blockchain.lock().map_err(|e| {
         error!("Mutex poisoned: {}", e);
-        io::Error::new(io::ErrorKind::Other, format!("Mutex poisoned: {}", e))
+        io::Error::other(format!("Mutex poisoned: {}", e))
     })?;
     if blockchain.is_chain_valid() {
         info!("Blockchain is valid, saving to {}", filename);
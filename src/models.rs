@@ -1,28 +1,363 @@
-use serde::Deserialize;
+use serde::{Serialize, Deserialize};
 use std::sync::{Arc, Mutex};
+use crate::address::Address;
+use crate::block::SignedHeader;
+use crate::crypto::SignatureScheme;
 use crate::blockchain::Blockchain;
-use crate::transaction::Transaction;
+use crate::faucet::FaucetLimiter;
+use crate::genesis::LedgerModel;
+use crate::keystore::Keystore;
+use crate::multisig::MultisigPolicy;
+use crate::peers::PeerSet;
+use crate::script::Script;
+use crate::transaction::{BatchOutput, TimeLock, Transaction, TransactionStatus};
+use crate::types::{Amount, Hash};
 
 #[derive(Clone)]
 pub struct AppState {
     pub blockchain: Arc<Mutex<Blockchain>>,
     pub mempool: Arc<Mutex<Vec<Transaction>>>,
+    /// Hex secret key this node signs blocks with once proof-of-stake is active.
+    pub validator_key: Option<String>,
+    /// Hex secret key of the genesis faucet account, set only on dev nodes that hand out funds.
+    pub faucet_key: Option<String>,
+    pub faucet_limiter: Arc<Mutex<FaucetLimiter>>,
+    /// Peers whose handshake reported this node's chain id and genesis hash.
+    pub peers: Arc<Mutex<PeerSet>>,
+    /// Encrypted keys created or imported through the API.
+    pub keystore: Keystore,
+    /// Dev mode lets `POST /wallet` return plaintext private keys. Never enable it on a
+    /// node reachable by others.
+    pub dev_mode: bool,
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct WalletRequest {
+    /// Password the new key is encrypted with in the node keystore.
+    pub password: String,
+}
+
+/// Public half of a wallet, returned when the secret key stays in the keystore.
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct WalletInfo {
+    pub address: Address,
+    pub public_key: String,
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct ImportKeyRequest {
+    pub private_key: String,
+    pub password: String,
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct ExportKeyRequest {
+    pub address: Address,
+    pub password: String,
 }
 
 #[derive(Deserialize, utoipa::ToSchema)]
 pub struct FaucetRequest {
-    pub address: String,
+    pub address: Address,
 }
 
 #[derive(Deserialize, utoipa::ToSchema)]
 pub struct TransactionRequest {
-    pub sender: String,
-    pub receiver: String,
-    pub amount: u64,
+    pub sender: Address,
+    pub receiver: Address,
+    pub amount: Amount,
+    pub private_key: String,
+    /// Signature algorithm, ECDSA unless given.
+    #[serde(default)]
+    pub scheme: SignatureScheme,
+    /// Holds the transaction in the mempool until after this height or timestamp.
+    #[serde(default)]
+    pub valid_after: Option<TimeLock>,
+    /// First height at which the transaction can no longer be included.
+    #[serde(default)]
+    pub expires_at_height: Option<u64>,
+    /// Note such as an invoice reference; costs a fee per byte.
+    #[serde(default)]
+    pub memo: Option<String>,
+}
+
+/// Pays several receivers from `sender` in one transaction that is applied all or none.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct BatchTransactionRequest {
+    pub sender: Address,
+    pub outputs: Vec<BatchOutput>,
     pub private_key: String,
+    #[serde(default)]
+    pub scheme: SignatureScheme,
+    #[serde(default)]
+    pub valid_after: Option<TimeLock>,
+    #[serde(default)]
+    pub expires_at_height: Option<u64>,
+    /// Note such as a payroll reference; costs a fee per byte.
+    #[serde(default)]
+    pub memo: Option<String>,
 }
 
 #[derive(Deserialize, utoipa::ToSchema)]
 pub struct BalanceRequest {
-    pub address: String,
-}
\ No newline at end of file
+    pub address: Address,
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct StakeRequest {
+    pub address: Address,
+    pub amount: Amount,
+    pub private_key: String,
+}
+
+/// Locks `amount` from `sender` for `receiver` behind a SHA-256 hashlock.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct HtlcLockRequest {
+    pub sender: Address,
+    pub receiver: Address,
+    pub amount: Amount,
+    /// SHA-256 of the 32 byte preimage that unlocks the funds.
+    pub hashlock: Hash,
+    /// First height at which the receiver can no longer claim and the sender can refund.
+    pub timeout_height: u64,
+    pub private_key: String,
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct HtlcClaimRequest {
+    pub receiver: Address,
+    pub htlc: Hash,
+    /// Hex encoded preimage of the hashlock.
+    pub preimage: String,
+    pub private_key: String,
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct HtlcRefundRequest {
+    pub sender: Address,
+    pub htlc: Hash,
+    pub private_key: String,
+}
+
+#[derive(Deserialize, utoipa::IntoParams)]
+pub struct HtlcQuery {
+    /// Only list contracts this address locked or can claim.
+    pub address: Option<Address>,
+}
+
+/// A preimage revealed by claiming an HTLC, which unlocks the other side of a swap.
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct RevealedPreimage {
+    pub hashlock: Hash,
+    pub preimage: String,
+    /// The claimed HTLC and the height of the block that claimed it.
+    pub htlc: Hash,
+    pub height: u64,
+}
+
+/// Escrows `amount` from `payer` for `payee`, with `arbiter` as third party.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct EscrowCreateRequest {
+    pub payer: Address,
+    pub payee: Address,
+    pub arbiter: Address,
+    pub amount: Amount,
+    pub private_key: String,
+}
+
+/// One party's approval to release or refund an escrow.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct EscrowApprovalRequest {
+    /// The payer, payee or arbiter approving.
+    pub party: Address,
+    pub escrow: Hash,
+    pub private_key: String,
+}
+
+/// Issues a token with `supply` initial units held by `issuer`.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct TokenCreateRequest {
+    pub issuer: Address,
+    pub symbol: String,
+    #[serde(default)]
+    pub decimals: u8,
+    pub supply: Amount,
+    pub private_key: String,
+}
+
+/// Sends units of a token from `sender` to `receiver`; for a mint, `sender` is the issuer.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct TokenTransferRequest {
+    pub sender: Address,
+    pub receiver: Address,
+    pub token: Hash,
+    pub amount: Amount,
+    pub private_key: String,
+}
+
+/// Destroys units of a token held by `holder`.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct TokenBurnRequest {
+    pub holder: Address,
+    pub token: Hash,
+    pub amount: Amount,
+    pub private_key: String,
+}
+
+/// Creates an NFT collection owned by `owner`.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct NftCollectionRequest {
+    pub owner: Address,
+    pub name: String,
+    pub private_key: String,
+}
+
+/// Mints an NFT of `collection` for `receiver`, signed by the collection owner.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct NftMintRequest {
+    pub minter: Address,
+    pub collection: Hash,
+    pub receiver: Address,
+    pub content_hash: Hash,
+    pub metadata_uri: String,
+    pub private_key: String,
+}
+
+/// Hands an NFT from its current `owner` to `receiver`.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct NftTransferRequest {
+    pub owner: Address,
+    pub receiver: Address,
+    pub nft: Hash,
+    pub private_key: String,
+}
+
+/// Deploys hex Wasm `code` from `creator`, endowing the contract with `amount`.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct ContractDeployRequest {
+    pub creator: Address,
+    pub code: String,
+    /// Hex input passed to the contract's `init` export.
+    #[serde(default)]
+    pub input: String,
+    #[serde(default)]
+    pub amount: Amount,
+    pub gas_limit: u64,
+    /// Defaults to the chain's minimum gas price.
+    pub gas_price: Option<Amount>,
+    pub private_key: String,
+}
+
+/// A deployed contract: the deploy transaction and the address it creates.
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct DeployedContract {
+    pub id: Hash,
+    pub address: Address,
+}
+
+/// Calls `method` of `contract` in a transaction from `caller`, paying it `amount`.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct ContractCallRequest {
+    pub caller: Address,
+    pub contract: Address,
+    pub method: String,
+    #[serde(default)]
+    pub input: String,
+    #[serde(default)]
+    pub amount: Amount,
+    pub gas_limit: u64,
+    /// Defaults to the chain's minimum gas price.
+    pub gas_price: Option<Amount>,
+    pub private_key: String,
+}
+
+/// Runs `method` of `contract` against current state without a transaction.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct ContractQuery {
+    pub contract: Address,
+    pub method: String,
+    #[serde(default)]
+    pub input: String,
+    /// Address the contract sees as its caller; the contract itself if not set.
+    pub caller: Option<Address>,
+    /// Defaults to the most a transaction may use.
+    pub gas_limit: Option<u64>,
+}
+
+/// What a read-only contract call returned.
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ContractOutput {
+    /// Hex output of the call.
+    pub output: String,
+    pub gas_used: u64,
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct EvidenceRequest {
+    pub reporter: Address,
+    pub private_key: String,
+    pub first: SignedHeader,
+    pub second: SignedHeader,
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct MultisigAddressRequest {
+    pub threshold: usize,
+    /// Hex compressed public keys of the cosigners, in any order.
+    pub public_keys: Vec<String>,
+}
+
+/// A multisig address with the canonical policy that controls it.
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct MultisigAccount {
+    pub address: Address,
+    pub policy: MultisigPolicy,
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct ScriptAddressRequest {
+    /// Locking script, e.g. `dup hash <hex> equal verify checksig`.
+    pub script: Script,
+}
+
+/// A script address with the locking script that controls it.
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct ScriptAccount {
+    pub address: Address,
+    pub script: Script,
+}
+
+/// A transaction with its status, as returned by lookups and searches.
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct TransactionDetails {
+    pub id: Hash,
+    #[serde(flatten)]
+    pub status: TransactionStatus,
+    /// Missing when the status is unknown.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<Transaction>,
+}
+
+#[derive(Deserialize, utoipa::IntoParams)]
+pub struct MemoSearchQuery {
+    /// Text the memo has to contain.
+    pub memo: String,
+    /// Maximum number of results, 100 by default.
+    pub limit: Option<usize>,
+}
+
+/// What a client or peer needs to confirm it is talking to the same network.
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct NodeInfo {
+    pub chain_id: String,
+    pub genesis_hash: Hash,
+    pub height: u64,
+    /// Whether wallets should build account transfers or UTXO spends.
+    pub ledger: LedgerModel,
+    /// Fee per memo byte, which a UTXO spend has to leave over from its inputs.
+    pub memo_fee_per_byte: Amount,
+    /// Most gas the transactions of one block may use together.
+    pub block_gas_limit: u64,
+    /// Lowest gas price a contract or scripted transaction may offer.
+    pub min_gas_price: Amount,
+}
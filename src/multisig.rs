@@ -0,0 +1,206 @@
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+use std::collections::BTreeSet;
+use crate::address::Address;
+use crate::crypto::{self, SignatureScheme};
+use crate::transaction::{Transaction, TransactionKind};
+use crate::types::Amount;
+
+/// Upper bound on the keys of one policy, which also bounds the signature checks per transaction.
+pub const MAX_MULTISIG_KEYS: usize = 16;
+pub const PSBT_VERSION: u32 = 1;
+
+/// An m-of-n spending policy: any `threshold` of `public_keys` may authorise a transaction.
+///
+/// Keys are kept sorted so the same set of keys always yields the same address.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct MultisigPolicy {
+    pub threshold: usize,
+    /// Hex compressed public keys, sorted.
+    pub public_keys: Vec<String>,
+}
+
+#[allow(dead_code)]
+impl MultisigPolicy {
+    /// Builds a policy from keys in any order.
+    pub fn new(threshold: usize, public_keys: &[String]) -> Result<Self, String> {
+        let mut public_keys: Vec<String> = public_keys.iter().map(|key| key.to_lowercase()).collect();
+        public_keys.sort();
+        let policy = MultisigPolicy { threshold, public_keys };
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// Checks the threshold and that the keys are valid, distinct and in canonical order.
+    pub fn validate(&self) -> Result<(), String> {
+        let n = self.public_keys.len();
+        if n == 0 || n > MAX_MULTISIG_KEYS {
+            return Err(format!("A multisig policy needs between 1 and {} keys", MAX_MULTISIG_KEYS));
+        }
+        if self.threshold == 0 || self.threshold > n {
+            return Err(format!("Threshold must be between 1 and {}", n));
+        }
+        if let Some(key) = self.public_keys.iter().find(|key| Address::from_public_key(key).is_none()) {
+            return Err(format!("Invalid public key {}", key));
+        }
+        if self.public_keys.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err("Multisig keys must be distinct and sorted".to_string());
+        }
+        Ok(())
+    }
+
+    /// Address controlled by the policy. The hashed encoding is tagged so it can never equal
+    /// the 33 key bytes behind a single-key address.
+    pub fn address(&self) -> Address {
+        let encoded = format!("multisig:{}:{}", self.threshold, self.public_keys.join(","));
+        Address::from_digest(&Sha256::digest(encoded.as_bytes()))
+    }
+
+    pub fn contains(&self, public_key: &str) -> bool {
+        self.public_keys.binary_search_by(|key| key.as_str().cmp(public_key)).is_ok()
+    }
+}
+
+/// One cosigner's signature over the transaction's signing message.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct MultisigSignature {
+    pub public_key: String,
+    pub signature: String,
+}
+
+/// The policy of a multisig sender and the signatures collected for it.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct MultisigAuth {
+    pub policy: MultisigPolicy,
+    pub signatures: Vec<MultisigSignature>,
+}
+
+#[allow(dead_code)]
+impl MultisigAuth {
+    /// Number of distinct policy keys with a valid `scheme` signature over `message`.
+    pub fn valid_signers(&self, scheme: SignatureScheme, message: &str) -> usize {
+        let mut signers = BTreeSet::new();
+        for entry in &self.signatures {
+            if self.policy.contains(&entry.public_key)
+                && !signers.contains(&entry.public_key)
+                && crypto::verify(scheme, message, &entry.signature, &entry.public_key)
+            {
+                signers.insert(entry.public_key.clone());
+            }
+        }
+        signers.len()
+    }
+
+    /// Checks that the policy is well formed, controls `sender` and has at least `threshold`
+    /// distinct valid signatures. Extra entries are refused so verification work stays bounded.
+    pub fn verify(&self, scheme: SignatureScheme, message: &str, sender: &Address) -> bool {
+        self.policy.validate().is_ok()
+            && self.policy.address() == *sender
+            && self.signatures.len() <= self.policy.public_keys.len()
+            && self.valid_signers(scheme, message) >= self.policy.threshold
+    }
+}
+
+/// A multisig transaction that is still collecting signatures. Cosigners pass it around
+/// as JSON, each adding their signature, until it can be finalized and submitted.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct PartiallySignedTransaction {
+    pub version: u32,
+    pub transaction: Transaction,
+}
+
+#[allow(dead_code)]
+impl PartiallySignedTransaction {
+    /// An unsigned transaction spending from the address of `policy`. Cosigners sign with
+    /// ECDSA unless `transaction.scheme` is changed before the first signature, as are any
+    /// time lock, expiry, memo and gas.
+    pub fn new(chain_id: &str, policy: MultisigPolicy, kind: TransactionKind, receiver: &Address, amount: Amount) -> Result<Self, String> {
+        policy.validate()?;
+        let transaction = Transaction {
+            sender: policy.address(),
+            receiver: receiver.clone(),
+            amount,
+            signature: String::new(),
+            sender_public_key: String::new(),
+            chain_id: chain_id.to_string(),
+            kind,
+            multisig: Some(MultisigAuth { policy, signatures: Vec::new() }),
+            script: None,
+            scheme: SignatureScheme::Ecdsa,
+            valid_after: None,
+            expires_at_height: None,
+            memo: None,
+            gas: None,
+            nonce: 0,
+        };
+        Ok(PartiallySignedTransaction { version: PSBT_VERSION, transaction })
+    }
+
+    fn auth(&self) -> Result<&MultisigAuth, String> {
+        if self.version != PSBT_VERSION {
+            return Err(format!("Unsupported partially signed transaction version {}", self.version));
+        }
+        self.transaction.multisig.as_ref().ok_or("Not a multisig transaction".to_string())
+    }
+
+    fn auth_mut(&mut self) -> Result<&mut MultisigAuth, String> {
+        self.auth()?;
+        Ok(self.transaction.multisig.as_mut().expect("Checked above"))
+    }
+
+    /// Adds the signature of `private_key`, which must belong to the policy.
+    pub fn sign(&mut self, private_key: &str) -> Result<(), String> {
+        let public_key = crypto::public_key_from_private(private_key).ok_or("Invalid private key")?;
+        let (scheme, message) = (self.transaction.scheme, self.transaction.signing_message());
+        let auth = self.auth_mut()?;
+        if !auth.policy.contains(&public_key) {
+            return Err(format!("Key {} is not part of the multisig policy", public_key));
+        }
+        if auth.signatures.iter().any(|entry| entry.public_key == public_key) {
+            return Err(format!("Key {} has already signed", public_key));
+        }
+        let signature = crypto::sign(scheme, &message, private_key);
+        auth.signatures.push(MultisigSignature { public_key, signature });
+        auth.signatures.sort_by(|a, b| a.public_key.cmp(&b.public_key));
+        Ok(())
+    }
+
+    /// Merges valid signatures from another copy of the same transaction.
+    pub fn combine(&mut self, other: &PartiallySignedTransaction) -> Result<(), String> {
+        let (scheme, message) = (self.transaction.scheme, self.transaction.signing_message());
+        if other.transaction.signing_message() != message || other.transaction.scheme != scheme || other.auth()?.policy != self.auth()?.policy {
+            return Err("Partially signed transactions are for different transactions".to_string());
+        }
+        let incoming = other.auth()?.signatures.clone();
+        let auth = self.auth_mut()?;
+        for entry in incoming {
+            let known = auth.signatures.iter().any(|existing| existing.public_key == entry.public_key);
+            if !known && auth.policy.contains(&entry.public_key) && crypto::verify(scheme, &message, &entry.signature, &entry.public_key) {
+                auth.signatures.push(entry);
+            }
+        }
+        auth.signatures.sort_by(|a, b| a.public_key.cmp(&b.public_key));
+        Ok(())
+    }
+
+    /// How many more signatures are needed before the transaction can be finalized.
+    pub fn signatures_missing(&self) -> usize {
+        match self.auth() {
+            Ok(auth) => auth.policy.threshold.saturating_sub(auth.valid_signers(self.transaction.scheme, &self.transaction.signing_message())),
+            Err(_) => 0,
+        }
+    }
+
+    /// The transaction ready for submission, once enough cosigners have signed.
+    pub fn finalize(self) -> Result<Transaction, String> {
+        self.auth()?;
+        let missing = self.signatures_missing();
+        if missing > 0 {
+            return Err(format!("{} more signature(s) required", missing));
+        }
+        if !self.transaction.verify(&self.transaction.chain_id) {
+            return Err("Invalid multisig transaction".to_string());
+        }
+        Ok(self.transaction)
+    }
+}
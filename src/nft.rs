@@ -0,0 +1,108 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use crate::address::Address;
+use crate::types::Hash;
+
+/// Longest collection name, in bytes.
+pub const MAX_COLLECTION_NAME_BYTES: usize = 64;
+/// Longest metadata URI, in bytes.
+pub const MAX_METADATA_URI_BYTES: usize = 256;
+
+/// Rejects collection names that are empty, too long or contain control characters.
+pub fn check_collection_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() || name.len() > MAX_COLLECTION_NAME_BYTES || name.chars().any(char::is_control) {
+        return Err(format!("Collection name must be 1 to {} bytes of printable text", MAX_COLLECTION_NAME_BYTES));
+    }
+    Ok(())
+}
+
+/// Rejects metadata URIs that are empty, too long or contain whitespace or control characters.
+pub fn check_metadata_uri(uri: &str) -> Result<(), String> {
+    if uri.is_empty() || uri.len() > MAX_METADATA_URI_BYTES || uri.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(format!("Metadata URI must be 1 to {} bytes without whitespace", MAX_METADATA_URI_BYTES));
+    }
+    Ok(())
+}
+
+/// A named set of NFTs. Only `owner` can mint into it.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct Collection {
+    /// Id of the transaction that created the collection.
+    pub id: Hash,
+    pub name: String,
+    pub owner: Address,
+}
+
+/// A non-fungible token: one item of a collection, held by a single owner.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct Nft {
+    /// Id of the transaction that minted the token.
+    pub id: Hash,
+    pub collection: Hash,
+    /// SHA-256 of the content the token stands for, so the content can be checked
+    /// wherever `metadata_uri` points.
+    pub content_hash: Hash,
+    pub metadata_uri: String,
+    pub owner: Address,
+}
+
+/// One change of ownership of an NFT, as recorded in a block. A mint has no `from`.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct NftTransfer {
+    /// Id of the mint or transfer transaction.
+    pub tx: Hash,
+    pub height: u64,
+    pub timestamp: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<Address>,
+    pub to: Address,
+}
+
+/// Every NFT collection and token on chain, by id.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct Nfts {
+    collections: BTreeMap<Hash, Collection>,
+    tokens: BTreeMap<Hash, Nft>,
+}
+
+#[allow(dead_code)]
+impl Nfts {
+    pub fn is_empty(&self) -> bool {
+        self.collections.is_empty()
+    }
+
+    pub fn collection(&self, id: &Hash) -> Option<&Collection> {
+        self.collections.get(id)
+    }
+
+    pub fn get(&self, id: &Hash) -> Option<&Nft> {
+        self.tokens.get(id)
+    }
+
+    /// Whether `collection` already holds a token for `content_hash`.
+    pub fn has_content(&self, collection: &Hash, content_hash: &Hash) -> bool {
+        self.tokens.values().any(|nft| nft.collection == *collection && nft.content_hash == *content_hash)
+    }
+
+    /// Tokens held by `owner`, ordered by collection, then id.
+    pub fn owned_by(&self, owner: &Address) -> Vec<&Nft> {
+        let mut owned: Vec<&Nft> = self.tokens.values().filter(|nft| nft.owner == *owner).collect();
+        owned.sort_by(|a, b| a.collection.cmp(&b.collection).then_with(|| a.id.cmp(&b.id)));
+        owned
+    }
+
+    pub fn create_collection(&mut self, collection: Collection) {
+        self.collections.insert(collection.id.clone(), collection);
+    }
+
+    pub fn mint(&mut self, nft: Nft) {
+        self.tokens.insert(nft.id.clone(), nft);
+    }
+
+    pub fn transfer(&mut self, id: &Hash, to: &Address) -> Result<(), String> {
+        let nft = self.tokens.get_mut(id).ok_or_else(|| format!("No NFT {}", id))?;
+        nft.owner = to.clone();
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use crate::types::Hash;
+
+/// Most peers a node keeps; handshakes from new peers beyond it are refused.
+pub const MAX_PEERS: usize = 128;
+
+/// What a peer reports when it connects: where to reach it and which network it is on.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Eq, Debug)]
+pub struct Handshake {
+    /// Base URL of the peer's API.
+    pub url: String,
+    pub chain_id: String,
+    pub genesis_hash: Hash,
+}
+
+/// Peers whose handshake matched this node's network. Like the faucet limiter this is
+/// node state, not consensus, and is not persisted.
+#[derive(Default)]
+pub struct PeerSet {
+    peers: BTreeMap<String, Handshake>,
+}
+
+#[allow(dead_code)]
+impl PeerSet {
+    /// Accepts the peer if it is on the chain `chain_id` starting from `genesis_hash`, or
+    /// explains why it is refused. A peer handshaking again replaces its entry.
+    pub fn accept(&mut self, handshake: Handshake, chain_id: &str, genesis_hash: &Hash) -> Result<(), String> {
+        if handshake.chain_id != chain_id {
+            return Err(format!("Peer is on chain {}, not {}", handshake.chain_id, chain_id));
+        }
+        if handshake.genesis_hash != *genesis_hash {
+            return Err(format!("Peer starts from genesis {}, not {}", handshake.genesis_hash, genesis_hash));
+        }
+        if handshake.url.is_empty() {
+            return Err("Peer URL is required".to_string());
+        }
+        if !self.peers.contains_key(&handshake.url) && self.peers.len() >= MAX_PEERS {
+            return Err(format!("Peer limit of {} reached", MAX_PEERS));
+        }
+        self.peers.insert(handshake.url.clone(), handshake);
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<Handshake> {
+        self.peers.values().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+}
@@ -0,0 +1,60 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use crate::types::{Amount, Hash};
+
+/// Whether a programmable transaction did what it asked. A failed transaction is still
+/// included and pays for the gas it used, but changes nothing else.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Eq, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ReceiptStatus {
+    Succeeded,
+    Failed { error: String },
+}
+
+/// The gas a programmable transaction used and what it paid, recorded when its block is applied.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct Receipt {
+    pub tx: Hash,
+    pub height: u64,
+    pub gas_limit: u64,
+    pub gas_used: u64,
+    pub gas_price: Amount,
+    /// `gas_used` times `gas_price`, burned.
+    pub fee: Amount,
+    /// What the unused gas would have cost, which the sender keeps.
+    pub refund: Amount,
+    #[serde(flatten)]
+    pub status: ReceiptStatus,
+    /// Hex output of a contract call.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub output: String,
+}
+
+#[allow(dead_code)]
+impl Receipt {
+    pub fn succeeded(&self) -> bool {
+        self.status == ReceiptStatus::Succeeded
+    }
+}
+
+/// Receipts of every programmable transaction on chain, by transaction id.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, Default, PartialEq, Debug)]
+#[serde(transparent)]
+pub struct Receipts {
+    receipts: BTreeMap<Hash, Receipt>,
+}
+
+#[allow(dead_code)]
+impl Receipts {
+    pub fn is_empty(&self) -> bool {
+        self.receipts.is_empty()
+    }
+
+    pub fn get(&self, tx: &Hash) -> Option<&Receipt> {
+        self.receipts.get(tx)
+    }
+
+    pub fn insert(&mut self, receipt: Receipt) {
+        self.receipts.insert(receipt.tx.clone(), receipt);
+    }
+}
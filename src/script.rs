@@ -0,0 +1,350 @@
+use serde::{Serialize, Deserialize, Deserializer, Serializer};
+use sha2::{Sha256, Digest};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::openapi::schema::{ObjectBuilder, Schema, SchemaType};
+use utoipa::openapi::RefOr;
+use crate::address::Address;
+use crate::crypto::{self, SignatureScheme};
+use crate::transaction::TimeLock;
+
+/// Most operations one script may contain.
+pub const MAX_SCRIPT_OPS: usize = 201;
+/// Largest value a push may place on the stack, in bytes.
+pub const MAX_ELEMENT_BYTES: usize = 520;
+/// Deepest the stack may grow while unlocking and locking scripts run.
+pub const MAX_STACK_DEPTH: usize = 100;
+/// Most signature checks one evaluation may perform; `checkmultisig` counts one per key.
+pub const MAX_SIG_OPS: usize = 20;
+/// Most keys one `checkmultisig` may name.
+pub const MAX_CHECKMULTISIG_KEYS: usize = 16;
+/// Gas per script operation.
+pub const GAS_PER_OP: u64 = 10;
+/// Gas per signature a script checks, on top of the operation checking it.
+pub const GAS_PER_SIG_OP: u64 = 1_000;
+/// `checklocktime` values below this are block heights, the rest Unix timestamps, as in Bitcoin.
+pub const LOCKTIME_THRESHOLD: u64 = 500_000_000;
+
+/// One instruction. There are no loops or jumps, so a script runs each operation at most once.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Op {
+    /// Pushes bytes, written as hex in script text, or `empty` for no bytes.
+    Push(Vec<u8>),
+    /// Pushes a copy of the top item.
+    Dup,
+    /// Replaces the top item with its SHA-256.
+    Hash,
+    /// Pops two items and pushes whether they are equal.
+    Equal,
+    /// Pops the top item and fails the script unless it is true.
+    Verify,
+    /// Pops a public key and a signature and pushes whether the signature is valid over the
+    /// transaction's signing message.
+    CheckSig,
+    /// Pops `n`, `n` public keys, `m` and `m` signatures, and pushes whether the signatures
+    /// match distinct keys in the order the keys were pushed.
+    CheckMultisig,
+    /// Pops a height or timestamp and fails the script unless the transaction's signed
+    /// `valid_after` keeps it out of blocks up to at least that point.
+    CheckLockTime,
+}
+
+impl Op {
+    fn name(&self) -> &'static str {
+        match self {
+            Op::Push(_) => "push",
+            Op::Dup => "dup",
+            Op::Hash => "hash",
+            Op::Equal => "equal",
+            Op::Verify => "verify",
+            Op::CheckSig => "checksig",
+            Op::CheckMultisig => "checkmultisig",
+            Op::CheckLockTime => "checklocktime",
+        }
+    }
+}
+
+/// A program in the stack language, written as space separated operation names and hex
+/// pushes, e.g. `dup hash <hex> equal verify checksig`. Numbers are pushed as big-endian
+/// unsigned integers of at most 8 bytes.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Script {
+    pub ops: Vec<Op>,
+}
+
+#[allow(dead_code)]
+impl Script {
+    pub fn new(ops: Vec<Op>) -> Self {
+        Script { ops }
+    }
+
+    /// Pushes the signature over a transaction's signing message, for an unlocking script.
+    pub fn push_signature(signature: &str) -> Result<Op, String> {
+        hex::decode(signature).map(Op::Push).map_err(|_| "Signature is not hex".to_string())
+    }
+
+    /// Pushes `value` with the fewest bytes, for `checklocktime` and `checkmultisig`.
+    pub fn push_number(value: u64) -> Op {
+        let bytes = value.to_be_bytes();
+        let start = bytes.iter().position(|byte| *byte != 0).unwrap_or(bytes.len() - 1);
+        Op::Push(bytes[start..].to_vec())
+    }
+
+    /// Whether the script only pushes data, as unlocking scripts must.
+    pub fn is_push_only(&self) -> bool {
+        self.ops.iter().all(|op| matches!(op, Op::Push(_)))
+    }
+
+    /// Address that can only be spent from by satisfying this script. The hashed encoding
+    /// is tagged so it cannot collide with key or multisig addresses.
+    pub fn address(&self) -> Address {
+        Address::from_digest(&Sha256::digest(format!("script:{}", self).as_bytes()))
+    }
+
+    /// Gas running the script costs: every operation, plus every signature it can check.
+    /// A `checkmultisig` is charged for the key count pushed right before it, or for the
+    /// most keys allowed when that is not a constant.
+    pub fn gas(&self) -> u64 {
+        let sig_ops: usize = self
+            .ops
+            .iter()
+            .enumerate()
+            .map(|(index, op)| match op {
+                Op::CheckSig => 1,
+                Op::CheckMultisig => match self.ops[..index].last() {
+                    Some(Op::Push(data)) => as_number(data).map_or(MAX_CHECKMULTISIG_KEYS, |n| (n as usize).min(MAX_CHECKMULTISIG_KEYS)),
+                    _ => MAX_CHECKMULTISIG_KEYS,
+                },
+                _ => 0,
+            })
+            .sum();
+        self.ops.len() as u64 * GAS_PER_OP + sig_ops as u64 * GAS_PER_SIG_OP
+    }
+
+    fn check_limits(&self) -> Result<(), String> {
+        if self.ops.len() > MAX_SCRIPT_OPS {
+            return Err(format!("Script has more than {} operations", MAX_SCRIPT_OPS));
+        }
+        if self.ops.iter().any(|op| matches!(op, Op::Push(data) if data.len() > MAX_ELEMENT_BYTES)) {
+            return Err(format!("Script pushes more than {} bytes", MAX_ELEMENT_BYTES));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tokens: Vec<String> = self
+            .ops
+            .iter()
+            .map(|op| match op {
+                Op::Push(data) if data.is_empty() => "empty".to_string(),
+                Op::Push(data) => hex::encode(data),
+                op => op.name().to_string(),
+            })
+            .collect();
+        f.write_str(&tokens.join(" "))
+    }
+}
+
+impl FromStr for Script {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ops = s
+            .split_whitespace()
+            .map(|token| match token.to_lowercase().as_str() {
+                "empty" => Ok(Op::Push(Vec::new())),
+                "dup" => Ok(Op::Dup),
+                "hash" => Ok(Op::Hash),
+                "equal" => Ok(Op::Equal),
+                "verify" => Ok(Op::Verify),
+                "checksig" => Ok(Op::CheckSig),
+                "checkmultisig" => Ok(Op::CheckMultisig),
+                "checklocktime" => Ok(Op::CheckLockTime),
+                data => hex::decode(data).map(Op::Push).map_err(|_| format!("Unknown script token {}", token)),
+            })
+            .collect::<Result<Vec<Op>, String>>()?;
+        let script = Script { ops };
+        script.check_limits()?;
+        Ok(script)
+    }
+}
+
+impl Serialize for Script {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Script {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl<'s> utoipa::ToSchema<'s> for Script {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        let schema = ObjectBuilder::new()
+            .schema_type(SchemaType::String)
+            .description(Some("Operation names and hex pushes separated by spaces"))
+            .example(Some(serde_json::json!("dup hash 5feceb66ffc86f38d952786c6d696c79c2dbc239dd4e91b46729d73a27fb57e9 equal verify checksig")));
+        ("Script", schema.into())
+    }
+}
+
+/// What a script may inspect about the transaction it authorises.
+pub struct ScriptContext<'a> {
+    /// The message signatures are checked against.
+    pub message: &'a str,
+    pub scheme: SignatureScheme,
+    pub valid_after: Option<TimeLock>,
+}
+
+/// Runs `unlocking` and then `locking` on the same stack. Succeeds if both stay within the
+/// resource limits and leave a true value on top.
+pub fn evaluate(locking: &Script, unlocking: &Script, context: &ScriptContext) -> Result<(), String> {
+    locking.check_limits()?;
+    unlocking.check_limits()?;
+    // Signatures cannot commit to the unlocking script, so it may only supply data
+    if !unlocking.is_push_only() {
+        return Err("Unlocking script may only push data".to_string());
+    }
+    let mut machine = Machine { stack: Vec::new(), sig_ops: 0, context };
+    for op in unlocking.ops.iter().chain(&locking.ops) {
+        machine.step(op)?;
+    }
+    match machine.stack.last() {
+        Some(top) if is_true(top) => Ok(()),
+        _ => Err("Script finished without a true value on the stack".to_string()),
+    }
+}
+
+fn is_true(item: &[u8]) -> bool {
+    item.iter().any(|byte| *byte != 0)
+}
+
+fn as_number(item: &[u8]) -> Result<u64, String> {
+    if item.len() > 8 {
+        return Err("Number is longer than 8 bytes".to_string());
+    }
+    Ok(item.iter().fold(0, |value, byte| (value << 8) | u64::from(*byte)))
+}
+
+fn boolean(value: bool) -> Vec<u8> {
+    if value { vec![1] } else { Vec::new() }
+}
+
+struct Machine<'a> {
+    stack: Vec<Vec<u8>>,
+    sig_ops: usize,
+    context: &'a ScriptContext<'a>,
+}
+
+impl Machine<'_> {
+    fn pop(&mut self) -> Result<Vec<u8>, String> {
+        self.stack.pop().ok_or("Stack underflow".to_string())
+    }
+
+    fn push(&mut self, item: Vec<u8>) -> Result<(), String> {
+        if self.stack.len() == MAX_STACK_DEPTH {
+            return Err(format!("Stack exceeds {} items", MAX_STACK_DEPTH));
+        }
+        self.stack.push(item);
+        Ok(())
+    }
+
+    fn count_sig_ops(&mut self, count: usize) -> Result<(), String> {
+        self.sig_ops += count;
+        if self.sig_ops > MAX_SIG_OPS {
+            return Err(format!("Script performs more than {} signature checks", MAX_SIG_OPS));
+        }
+        Ok(())
+    }
+
+    fn check_signature(&self, signature: &[u8], public_key: &[u8]) -> bool {
+        crypto::verify(self.context.scheme, self.context.message, &hex::encode(signature), &hex::encode(public_key))
+    }
+
+    fn step(&mut self, op: &Op) -> Result<(), String> {
+        match op {
+            Op::Push(data) => self.push(data.clone()),
+            Op::Dup => {
+                let top = self.stack.last().cloned().ok_or("Stack underflow")?;
+                self.push(top)
+            }
+            Op::Hash => {
+                let top = self.pop()?;
+                self.push(Sha256::digest(&top).to_vec())
+            }
+            Op::Equal => {
+                let (a, b) = (self.pop()?, self.pop()?);
+                self.push(boolean(a == b))
+            }
+            Op::Verify => {
+                if !is_true(&self.pop()?) {
+                    return Err("Verify failed".to_string());
+                }
+                Ok(())
+            }
+            Op::CheckSig => {
+                self.count_sig_ops(1)?;
+                let (public_key, signature) = (self.pop()?, self.pop()?);
+                let valid = self.check_signature(&signature, &public_key);
+                self.push(boolean(valid))
+            }
+            Op::CheckMultisig => {
+                let n = as_number(&self.pop()?)? as usize;
+                if n == 0 || n > MAX_CHECKMULTISIG_KEYS {
+                    return Err(format!("checkmultisig needs between 1 and {} keys", MAX_CHECKMULTISIG_KEYS));
+                }
+                self.count_sig_ops(n)?;
+                let keys = (0..n).map(|_| self.pop()).collect::<Result<Vec<_>, _>>()?;
+                let m = as_number(&self.pop()?)? as usize;
+                if m > n {
+                    return Err("checkmultisig needs more signatures than keys".to_string());
+                }
+                let signatures = (0..m).map(|_| self.pop()).collect::<Result<Vec<_>, _>>()?;
+                // Both lists were popped in reverse, so signatures have to be pushed in key order
+                let mut keys = keys.iter();
+                let valid = signatures.iter().all(|signature| keys.any(|key| self.check_signature(signature, key)));
+                self.push(boolean(valid))
+            }
+            Op::CheckLockTime => {
+                let lock = as_number(&self.pop()?)?;
+                let locked = match self.context.valid_after {
+                    Some(TimeLock::Height(height)) => lock < LOCKTIME_THRESHOLD && height >= lock,
+                    Some(TimeLock::Timestamp(timestamp)) => lock >= LOCKTIME_THRESHOLD && timestamp >= 0 && timestamp as u64 >= lock,
+                    None => false,
+                };
+                if !locked {
+                    return Err(format!("Transaction is not time locked until {}", lock));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The scripts authorising a transaction from a script address, or the spend of an output
+/// owned by one.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct ScriptAuth {
+    /// The script the address commits to.
+    pub locking: Script,
+    /// Data satisfying it, such as signatures over the signing message.
+    pub unlocking: Script,
+}
+
+#[allow(dead_code)]
+impl ScriptAuth {
+    /// Gas running the unlocking and then the locking script costs.
+    pub fn gas(&self) -> u64 {
+        self.locking.gas() + self.unlocking.gas()
+    }
+
+    /// Checks that `locking` is the script behind `owner` and that `unlocking` satisfies it.
+    pub fn verify(&self, owner: &Address, context: &ScriptContext) -> bool {
+        self.locking.address() == *owner && evaluate(&self.locking, &self.unlocking, context).is_ok()
+    }
+}
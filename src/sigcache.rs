@@ -0,0 +1,96 @@
+use rayon::prelude::*;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use crate::transaction::Transaction;
+use crate::types::Hash;
+
+/// Verified transactions remembered before the oldest are evicted.
+pub const DEFAULT_CAPACITY: usize = 100_000;
+
+/// Remembers transactions whose signatures verified, so one checked on mempool entry is
+/// not verified again when its block is produced or applied.
+///
+/// Entries are keyed by a hash of the chain id and the whole serialized transaction, so
+/// any change to a signed field, signature or public key misses the cache.
+pub struct SignatureCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Default)]
+struct Entries {
+    known: HashSet<Hash>,
+    order: VecDeque<Hash>,
+}
+
+impl Default for SignatureCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+#[allow(dead_code)]
+impl SignatureCache {
+    pub fn new(capacity: usize) -> Self {
+        SignatureCache { capacity, entries: Mutex::new(Entries::default()) }
+    }
+
+    fn key(tx: &Transaction, chain_id: &str) -> Hash {
+        let json = serde_json::to_vec(tx).expect("Failed to serialize transaction");
+        Hash::digest([chain_id.as_bytes(), b":", &json].concat())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().known.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, tx: &Transaction, chain_id: &str) -> bool {
+        self.entries.lock().unwrap().known.contains(&Self::key(tx, chain_id))
+    }
+
+    fn insert(&self, keys: impl IntoIterator<Item = Hash>) {
+        let mut entries = self.entries.lock().unwrap();
+        for key in keys {
+            if entries.known.insert(key.clone()) {
+                entries.order.push_back(key);
+            }
+        }
+        while entries.order.len() > self.capacity {
+            let oldest = entries.order.pop_front().expect("Non-empty queue");
+            entries.known.remove(&oldest);
+        }
+    }
+
+    /// Verifies `tx` unless it is cached, remembering it if the signature is valid.
+    pub fn verify(&self, tx: &Transaction, chain_id: &str) -> bool {
+        let key = Self::key(tx, chain_id);
+        if self.entries.lock().unwrap().known.contains(&key) {
+            return true;
+        }
+        let valid = tx.verify(chain_id);
+        if valid {
+            self.insert([key]);
+        }
+        valid
+    }
+
+    /// Verifies the uncached transactions of a block with `Transaction::verify_all`; the
+    /// lock is not held while signatures are checked.
+    pub fn verify_block(&self, transactions: &[Transaction], chain_id: &str) -> bool {
+        let keyed: Vec<(Hash, &Transaction)> = transactions.par_iter().map(|tx| (Self::key(tx, chain_id), tx)).collect();
+        let pending: Vec<(Hash, &Transaction)> = {
+            let entries = self.entries.lock().unwrap();
+            keyed.into_iter().filter(|(key, _)| !entries.known.contains(key)).collect()
+        };
+        let uncached: Vec<&Transaction> = pending.iter().map(|(_, tx)| *tx).collect();
+        if !Transaction::verify_all(&uncached, chain_id) {
+            return false;
+        }
+        self.insert(pending.into_iter().map(|(key, _)| key));
+        true
+    }
+}
@@ -0,0 +1,177 @@
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+use std::collections::BTreeMap;
+use crate::address::Address;
+use crate::types::{Amount, Hash};
+
+pub const DEFAULT_UNBONDING_DELAY: u64 = 10;
+pub const DEFAULT_MIN_STAKE: Amount = Amount::new(1);
+pub const DEFAULT_SLASH_PERCENT: u64 = 50;
+
+/// Stake that has been unbonded and becomes spendable again at `release_height`.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct Unbonding {
+    pub address: Address,
+    pub amount: Amount,
+    pub release_height: u64,
+}
+
+/// Stake burned from a validator caught signing two blocks at `height`.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct Slash {
+    pub address: Address,
+    pub height: u64,
+    pub amount: Amount,
+}
+
+/// Bonded stake per validator plus the queue of pending unbonds.
+///
+/// Stakes live in a `BTreeMap` so that proposer selection walks validators in the same
+/// order on every node.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+#[serde(default)]
+pub struct Staking {
+    pub stakes: BTreeMap<Address, Amount>,
+    pub unbonding: Vec<Unbonding>,
+    pub unbonding_delay: u64,
+    pub min_stake: Amount,
+    /// Percentage of bonded and unbonding stake burned per equivocation.
+    pub slash_percent: u64,
+    pub slashed: Vec<Slash>,
+}
+
+impl Default for Staking {
+    fn default() -> Self {
+        Staking {
+            stakes: BTreeMap::new(),
+            unbonding: Vec::new(),
+            unbonding_delay: DEFAULT_UNBONDING_DELAY,
+            min_stake: DEFAULT_MIN_STAKE,
+            slash_percent: DEFAULT_SLASH_PERCENT,
+            slashed: Vec::new(),
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl Staking {
+    pub fn stake_of(&self, address: &Address) -> Amount {
+        self.stakes.get(address).copied().unwrap_or_default()
+    }
+
+    /// Validators eligible to propose, i.e. those holding at least `min_stake`.
+    pub fn validators(&self) -> impl Iterator<Item = (&Address, &Amount)> {
+        self.stakes.iter().filter(move |(_, stake)| **stake >= self.min_stake)
+    }
+
+    pub fn total_stake(&self) -> Amount {
+        self.validators().fold(Amount::ZERO, |total, (_, stake)| total.saturating_add(*stake))
+    }
+
+    /// Proof-of-stake is active once any validator is bonded; until then blocks are unsigned.
+    pub fn is_active(&self) -> bool {
+        !self.total_stake().is_zero()
+    }
+
+    pub fn bond(&mut self, address: &Address, amount: Amount) -> Result<(), String> {
+        let stake = self.stakes.entry(address.clone()).or_default();
+        *stake = stake.checked_add(amount).ok_or("Stake overflow")?;
+        Ok(())
+    }
+
+    /// Moves `amount` of stake into the unbonding queue, released `unbonding_delay` blocks after `height`.
+    pub fn unbond(&mut self, address: &Address, amount: Amount, height: u64) -> Result<(), String> {
+        let stake = self.stakes.get_mut(address).ok_or("No stake bonded")?;
+        *stake = stake.checked_sub(amount).ok_or("Insufficient stake")?;
+        if stake.is_zero() {
+            self.stakes.remove(address);
+        }
+        self.unbonding.push(Unbonding {
+            address: address.clone(),
+            amount,
+            release_height: height + self.unbonding_delay,
+        });
+        Ok(())
+    }
+
+    /// Removes and returns every unbonding entry that has matured by `height`.
+    pub fn release_matured(&mut self, height: u64) -> Vec<Unbonding> {
+        let (released, pending) = self
+            .unbonding
+            .drain(..)
+            .partition(|entry| entry.release_height <= height);
+        self.unbonding = pending;
+        released
+    }
+
+    /// Picks the proposer for the block following `previous_hash` in proposer round
+    /// `round`, weighted by stake.
+    ///
+    /// The first eight bytes of SHA-256(previous_hash), or of SHA-256(previous_hash:round)
+    /// after the first round, select a point in the cumulative stake range, so every node
+    /// with the same state agrees on the result. The previous proposer can grind its block
+    /// hash to influence the next pick; later rounds hand the block to other validators
+    /// if the selected one stays silent.
+    pub fn select_proposer(&self, previous_hash: &Hash, round: u64) -> Option<Address> {
+        let total = self.total_stake().value();
+        if total == 0 {
+            return None;
+        }
+        let digest = match round {
+            0 => Sha256::digest(previous_hash.as_str().as_bytes()),
+            _ => Sha256::digest(format!("{}:{}", previous_hash, round).as_bytes()),
+        };
+        let mut seed = [0u8; 8];
+        seed.copy_from_slice(&digest[..8]);
+        let mut target = u64::from_be_bytes(seed) % total;
+        for (address, stake) in self.validators() {
+            if target < stake.value() {
+                return Some(address.clone());
+            }
+            target -= stake.value();
+        }
+        None
+    }
+
+    /// What `slash` would burn from `address`: `slash_percent` of its bonded stake and of
+    /// each unbonding entry, each rounded down.
+    pub fn slash_amount(&self, address: &Address) -> Amount {
+        self.unbonding
+            .iter()
+            .filter(|entry| &entry.address == address)
+            .fold(self.stake_of(address).percent(self.slash_percent), |total, entry| {
+                total.saturating_add(entry.amount.percent(self.slash_percent))
+            })
+    }
+
+    pub fn is_slashed(&self, address: &Address, height: u64) -> bool {
+        self.slashed.iter().any(|slash| &slash.address == address && slash.height == height)
+    }
+
+    /// Burns `slash_percent` of the offender's bonded stake and of any stake still
+    /// unbonding, so unbonding right after equivocating does not escape the penalty.
+    pub fn slash(&mut self, address: &Address, height: u64) -> Result<Amount, String> {
+        if self.is_slashed(address, height) {
+            return Err("Equivocation at this height was already punished".to_string());
+        }
+        let burned = self.slash_amount(address);
+        if burned.is_zero() {
+            return Err("Offender has no stake to slash".to_string());
+        }
+        let percent = self.slash_percent;
+        if let Some(stake) = self.stakes.get(address).copied() {
+            let cut = stake.percent(percent);
+            if stake == cut {
+                self.stakes.remove(address);
+            } else {
+                self.stakes.insert(address.clone(), stake.checked_sub(cut).unwrap_or_default());
+            }
+        }
+        for entry in self.unbonding.iter_mut().filter(|entry| &entry.address == address) {
+            let cut = entry.amount.percent(percent);
+            entry.amount = entry.amount.checked_sub(cut).unwrap_or_default();
+        }
+        self.slashed.push(Slash { address: address.clone(), height, amount: burned });
+        Ok(burned)
+    }
+}
@@ -0,0 +1,116 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use crate::address::Address;
+use crate::types::{Amount, Hash};
+
+/// Longest token symbol, in characters.
+pub const MAX_SYMBOL_LEN: usize = 12;
+/// Most decimal places a token may display; amounts are always whole smallest units.
+pub const MAX_TOKEN_DECIMALS: u8 = 18;
+
+/// Rejects symbols that are not 1 to 12 uppercase letters and digits starting with a letter,
+/// so that symbols cannot be made to look alike with case or punctuation.
+pub fn check_symbol(symbol: &str) -> Result<(), String> {
+    let valid = symbol.len() <= MAX_SYMBOL_LEN
+        && symbol.starts_with(|c: char| c.is_ascii_uppercase())
+        && symbol.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+    if !valid {
+        return Err(format!("Token symbol must be 1 to {} uppercase letters and digits, starting with a letter", MAX_SYMBOL_LEN));
+    }
+    Ok(())
+}
+
+/// A fungible token issued on chain. Only `issuer` can mint more of it.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct Token {
+    /// Id of the transaction that created the token.
+    pub id: Hash,
+    pub symbol: String,
+    /// Decimal places wallets display; `decimals = 2` shows 150 units as 1.50.
+    pub decimals: u8,
+    /// Units in circulation, after every mint and burn.
+    pub supply: Amount,
+    pub issuer: Address,
+}
+
+/// Every token created on chain and the balances held in each.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct Tokens {
+    tokens: BTreeMap<Hash, Token>,
+    balances: BTreeMap<Hash, BTreeMap<Address, Amount>>,
+}
+
+#[allow(dead_code)]
+impl Tokens {
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn get(&self, id: &Hash) -> Option<&Token> {
+        self.tokens.get(id)
+    }
+
+    pub fn by_symbol(&self, symbol: &str) -> Option<&Token> {
+        self.tokens.values().find(|token| token.symbol == symbol)
+    }
+
+    /// Every token, ordered by symbol.
+    pub fn list(&self) -> Vec<&Token> {
+        let mut tokens: Vec<&Token> = self.tokens.values().collect();
+        tokens.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        tokens
+    }
+
+    /// Units of token `id` held by `address`.
+    pub fn balance(&self, id: &Hash, address: &Address) -> Amount {
+        self.balances.get(id).and_then(|holders| holders.get(address)).copied().unwrap_or_default()
+    }
+
+    /// Registers `token` with its whole supply held by the issuer.
+    pub fn create(&mut self, token: Token) {
+        if !token.supply.is_zero() {
+            self.balances.entry(token.id.clone()).or_default().insert(token.issuer.clone(), token.supply);
+        }
+        self.tokens.insert(token.id.clone(), token);
+    }
+
+    pub fn transfer(&mut self, id: &Hash, from: &Address, to: &Address, amount: Amount) -> Result<(), String> {
+        self.debit(id, from, amount)?;
+        self.credit(id, to, amount)
+    }
+
+    /// Creates `amount` new units for `to`.
+    pub fn mint(&mut self, id: &Hash, to: &Address, amount: Amount) -> Result<(), String> {
+        let token = self.tokens.get_mut(id).ok_or_else(|| format!("No token {}", id))?;
+        token.supply = token.supply.checked_add(amount).ok_or("Token supply would overflow")?;
+        self.credit(id, to, amount)
+    }
+
+    /// Destroys `amount` units held by `from`.
+    pub fn burn(&mut self, id: &Hash, from: &Address, amount: Amount) -> Result<(), String> {
+        self.debit(id, from, amount)?;
+        let token = self.tokens.get_mut(id).ok_or_else(|| format!("No token {}", id))?;
+        token.supply = token.supply.checked_sub(amount).ok_or("Token supply would underflow")?;
+        Ok(())
+    }
+
+    // Balances never sum to more than the supply, which fits in an Amount, so credits
+    // cannot overflow once the supply is checked
+    fn credit(&mut self, id: &Hash, to: &Address, amount: Amount) -> Result<(), String> {
+        let balance = self.balances.entry(id.clone()).or_default().entry(to.clone()).or_default();
+        *balance = balance.checked_add(amount).ok_or("Token balance would overflow")?;
+        Ok(())
+    }
+
+    fn debit(&mut self, id: &Hash, from: &Address, amount: Amount) -> Result<(), String> {
+        let holders = self.balances.entry(id.clone()).or_default();
+        let balance = holders.get(from).copied().unwrap_or_default().checked_sub(amount).ok_or("Insufficient token balance")?;
+        if balance.is_zero() {
+            holders.remove(from);
+        } else {
+            holders.insert(from.clone(), balance);
+        }
+        Ok(())
+    }
+}
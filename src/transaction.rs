@@ -1,31 +1,603 @@
 use serde::{Serialize, Deserialize};
-use secp256k1::{Secp256k1, Message, ecdsa::Signature, SecretKey, PublicKey};
-use sha2::{Sha256, Digest};
-use hex;
+use rayon::prelude::*;
+use crate::address::Address;
+use crate::block::SignedHeader;
+use crate::crypto::{self, SchnorrItem, SignatureScheme};
+use crate::multisig::MultisigAuth;
+use crate::script::{self, Script, ScriptAuth, ScriptContext};
+use crate::types::{Amount, Hash};
+use crate::utxo::{OutPoint, MAX_SPEND_INPUTS};
+
+/// Longest memo a transaction may carry, in bytes.
+#[allow(dead_code)]
+pub const MAX_MEMO_BYTES: usize = 256;
+/// Upper bound on the outputs of one batch transfer.
+#[allow(dead_code)]
+pub const MAX_BATCH_OUTPUTS: usize = 256;
+/// Schnorr signatures combined into one batch equation by `verify_batch`.
+pub const SCHNORR_BATCH_SIZE: usize = 64;
+
+/// What a transaction does to chain state. The kind is tagged into the signing message
+/// so a signature for one kind cannot be replayed as another.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionKind {
+    #[default]
+    Transfer,
+    /// Moves `amount` from the sender's balance into its validator stake; the receiver is the sender.
+    Bond,
+    /// Starts releasing `amount` of the sender's stake after the unbonding delay; the receiver is the sender.
+    Unbond,
+    /// Creates `amount` for `receiver`, signed by the faucet account named in genesis.
+    Mint,
+    /// Proof that `receiver` signed two different blocks at the same height.
+    Evidence {
+        first: Box<SignedHeader>,
+        second: Box<SignedHeader>,
+    },
+    /// Pays every output from the sender's balance, all or none. The receiver is the sender
+    /// and `amount` is the total of the outputs.
+    Batch { outputs: Vec<BatchOutput> },
+    /// Consumes unspent outputs and creates new ones, on a chain using the UTXO ledger. The
+    /// sender owns the first input, the receiver is the sender and `amount` is the total of
+    /// the outputs; inputs beyond the outputs and memo fee are burned as a fee.
+    Spend { inputs: Vec<TxInput>, outputs: Vec<BatchOutput> },
+    /// Locks `amount` of the sender's balance for `receiver`, who can claim it with the
+    /// preimage of `hashlock` in a block below `timeout_height`. The transaction id names the HTLC.
+    HtlcLock { hashlock: Hash, timeout_height: u64 },
+    /// Pays the open HTLC `htlc` to its receiver, who signs and reveals the hex `preimage`.
+    /// The receiver is the sender and `amount` is the locked amount.
+    HtlcClaim { htlc: Hash, preimage: String },
+    /// Returns the timed out HTLC `htlc` to the sender that locked it, who signs. The
+    /// receiver is the sender and `amount` is the locked amount.
+    HtlcRefund { htlc: Hash },
+    /// Locks `amount` of the sender's balance in escrow for `receiver`, the payee, with
+    /// `arbiter` as third party. The transaction id names the escrow.
+    EscrowCreate { arbiter: Address },
+    /// One party's approval to pay escrow `escrow` to its payee, who is the receiver;
+    /// `amount` is the escrowed amount. The second approval pays it out.
+    EscrowRelease { escrow: Hash },
+    /// One party's approval to return escrow `escrow` to its payer, who is the receiver;
+    /// `amount` is the escrowed amount. The second approval pays it out.
+    EscrowRefund { escrow: Hash },
+    /// Issues a token called `symbol` with `amount` units of initial supply, all held by the
+    /// sender, who becomes its issuer. The receiver is the sender and the transaction id names the token.
+    TokenCreate { symbol: String, decimals: u8 },
+    /// Moves `amount` units of token `token` from the sender to `receiver`.
+    TokenTransfer { token: Hash },
+    /// Creates `amount` new units of token `token` for `receiver`, signed by its issuer.
+    TokenMint { token: Hash },
+    /// Destroys `amount` units of token `token` held by the sender; the receiver is the sender.
+    TokenBurn { token: Hash },
+    /// Creates an NFT collection named `name` owned by the sender. The receiver is the
+    /// sender, `amount` is zero and the transaction id names the collection.
+    NftCollectionCreate { name: String },
+    /// Mints an NFT of `collection` for `receiver`, signed by the collection owner; `amount`
+    /// is zero and the transaction id names the NFT.
+    NftMint { collection: Hash, content_hash: Hash, metadata_uri: String },
+    /// Hands NFT `nft` to `receiver`, signed by its current owner; `amount` is zero.
+    NftTransfer { nft: Hash },
+    /// Deploys the hex Wasm module `code` and runs its `init` export, if any, with the hex
+    /// `input`. The receiver is the sender, `amount` is paid to the new contract and the
+    /// transaction id names it.
+    ContractDeploy { code: String, input: String },
+    /// Runs `method` of the contract at `receiver` with the hex `input`, paying it `amount`.
+    ContractCall { method: String, input: String },
+}
+
+/// An output being spent, with the signature of its owner over the signing message, or
+/// for an output owned by a script address the scripts satisfying it.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct TxInput {
+    pub outpoint: OutPoint,
+    /// Hex compressed public key of the output's owner; empty when `script` is set.
+    pub public_key: String,
+    /// Not part of the signing message, so the transaction id does not depend on it.
+    #[serde(default)]
+    pub signature: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<ScriptAuth>,
+}
+
+#[allow(dead_code)]
+impl TxInput {
+    /// Address that has to own the spent output.
+    pub fn owner(&self) -> Option<Address> {
+        match &self.script {
+            Some(auth) => Some(auth.locking.address()),
+            None => Address::from_public_key(&self.public_key),
+        }
+    }
+
+    fn verify(&self, context: &ScriptContext) -> bool {
+        match &self.script {
+            Some(auth) => self.public_key.is_empty() && self.signature.is_empty() && script::evaluate(&auth.locking, &auth.unlocking, context).is_ok(),
+            None => crypto::verify(context.scheme, context.message, &self.signature, &self.public_key),
+        }
+    }
+}
+
+/// One payment of a batch transfer, or one output created by a spend.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct BatchOutput {
+    pub receiver: Address,
+    pub amount: Amount,
+}
+
+#[allow(dead_code)]
+impl BatchOutput {
+    /// Sum of the output amounts, or None on overflow.
+    pub fn total(outputs: &[BatchOutput]) -> Option<Amount> {
+        outputs.iter().try_fold(Amount::ZERO, |total, output| total.checked_add(output.amount))
+    }
+}
+
+#[allow(dead_code)]
+impl TransactionKind {
+    pub fn tag(&self) -> &'static str {
+        match self {
+            TransactionKind::Transfer => "transfer",
+            TransactionKind::Bond => "bond",
+            TransactionKind::Unbond => "unbond",
+            TransactionKind::Mint => "mint",
+            TransactionKind::Evidence { .. } => "evidence",
+            TransactionKind::Batch { .. } => "batch",
+            TransactionKind::Spend { .. } => "spend",
+            TransactionKind::HtlcLock { .. } => "htlc_lock",
+            TransactionKind::HtlcClaim { .. } => "htlc_claim",
+            TransactionKind::HtlcRefund { .. } => "htlc_refund",
+            TransactionKind::EscrowCreate { .. } => "escrow_create",
+            TransactionKind::EscrowRelease { .. } => "escrow_release",
+            TransactionKind::EscrowRefund { .. } => "escrow_refund",
+            TransactionKind::TokenCreate { .. } => "token_create",
+            TransactionKind::TokenTransfer { .. } => "token_transfer",
+            TransactionKind::TokenMint { .. } => "token_mint",
+            TransactionKind::TokenBurn { .. } => "token_burn",
+            TransactionKind::NftCollectionCreate { .. } => "nft_collection_create",
+            TransactionKind::NftMint { .. } => "nft_mint",
+            TransactionKind::NftTransfer { .. } => "nft_transfer",
+            TransactionKind::ContractDeploy { .. } => "contract_deploy",
+            TransactionKind::ContractCall { .. } => "contract_call",
+        }
+    }
+
+    /// Kind specific data appended to the signing message.
+    pub fn payload(&self) -> String {
+        match self {
+            TransactionKind::Evidence { first, second } => format!(
+                "{}{}{}{}{}",
+                first.index, first.hash, first.proposer_signature, second.hash, second.proposer_signature
+            ),
+            TransactionKind::Batch { outputs } => Self::outputs_payload(outputs),
+            TransactionKind::Spend { inputs, outputs } => {
+                let inputs: Vec<String> = inputs.iter().map(|input| format!("{}/{}", input.outpoint, input.public_key)).collect();
+                format!(":inputs={}{}", inputs.join(","), Self::outputs_payload(outputs))
+            }
+            TransactionKind::HtlcLock { hashlock, timeout_height } => format!(":hashlock={}:timeout_height={}", hashlock, timeout_height),
+            TransactionKind::HtlcClaim { htlc, preimage } => format!(":htlc={}:preimage={}", htlc, preimage),
+            TransactionKind::HtlcRefund { htlc } => format!(":htlc={}", htlc),
+            TransactionKind::EscrowCreate { arbiter } => format!(":arbiter={}", arbiter),
+            TransactionKind::EscrowRelease { escrow } | TransactionKind::EscrowRefund { escrow } => format!(":escrow={}", escrow),
+            TransactionKind::TokenCreate { symbol, decimals } => format!(":symbol={}:decimals={}", symbol, decimals),
+            TransactionKind::TokenTransfer { token } | TransactionKind::TokenMint { token } | TransactionKind::TokenBurn { token } => {
+                format!(":token={}", token)
+            }
+            // Names and URIs may contain ':', so like memos they are hex encoded to keep the
+            // optional fields that follow from being moved into them
+            TransactionKind::NftCollectionCreate { name } => format!(":name={}", hex::encode(name)),
+            TransactionKind::NftMint { collection, content_hash, metadata_uri } => {
+                format!(":collection={}:content_hash={}:metadata_uri={}", collection, content_hash, hex::encode(metadata_uri))
+            }
+            TransactionKind::NftTransfer { nft } => format!(":nft={}", nft),
+            // The code can be large, so the message commits to its hash
+            TransactionKind::ContractDeploy { code, input } => format!(":code_hash={}:input={}", Hash::digest(code), input),
+            TransactionKind::ContractCall { method, input } => format!(":method={}:input={}", method, input),
+            _ => String::new(),
+        }
+    }
+
+    /// Whether the kind runs a contract, and so has to pay for gas.
+    pub fn runs_contract(&self) -> bool {
+        matches!(self, TransactionKind::ContractDeploy { .. } | TransactionKind::ContractCall { .. })
+    }
+
+    fn outputs_payload(outputs: &[BatchOutput]) -> String {
+        let outputs: Vec<String> = outputs.iter().map(|output| format!("{}={}", output.receiver, output.amount.value())).collect();
+        format!(":outputs={}", outputs.join(","))
+    }
+}
+
+/// The point after which a time-locked transaction may be included in a block.
+#[derive(Serialize, Deserialize, Clone, Copy, utoipa::ToSchema, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeLock {
+    /// Only blocks above this height may include the transaction.
+    Height(u64),
+    /// Only blocks with a later timestamp, in Unix seconds, may include the transaction.
+    Timestamp(i64),
+}
+
+impl TimeLock {
+    /// Whether a block at `height` with `timestamp` is past the lock.
+    pub fn has_passed(&self, height: u64, timestamp: i64) -> bool {
+        match *self {
+            TimeLock::Height(lock) => height > lock,
+            TimeLock::Timestamp(lock) => timestamp > lock,
+        }
+    }
+}
+
+impl std::fmt::Display for TimeLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeLock::Height(height) => write!(f, "height {}", height),
+            TimeLock::Timestamp(timestamp) => write!(f, "timestamp {}", timestamp),
+        }
+    }
+}
+
+/// Most gas a programmable transaction may use, and what it pays per unit used. The fee
+/// is burned; gas the transaction does not use is not charged.
+#[derive(Serialize, Deserialize, Clone, Copy, utoipa::ToSchema, PartialEq, Eq, Debug)]
+pub struct Gas {
+    pub limit: u64,
+    pub price: Amount,
+}
+
+#[allow(dead_code)]
+impl Gas {
+    /// Fee for `used` gas, or None on overflow.
+    pub fn fee(&self, used: u64) -> Option<Amount> {
+        self.price.checked_mul(used)
+    }
+
+    /// Fee if the whole limit is used, which the sender has to be able to pay up front.
+    pub fn max_fee(&self) -> Option<Amount> {
+        self.fee(self.limit)
+    }
+}
+
+/// Where a submitted transaction is, as reported by `GET /transaction/{id}`.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+#[allow(dead_code)]
+pub enum TransactionStatus {
+    /// Waiting in the mempool of the node that was asked.
+    Pending,
+    /// Included in block `block`; `confirmations` counts that block and every one after it.
+    Confirmed { block: u64, confirmations: u64 },
+    /// Neither in the mempool nor on chain, e.g. dropped as invalid when a block was produced.
+    Unknown,
+}
 
 #[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
 pub struct Transaction {
-    pub sender: String,
-    pub receiver: String,
-    pub amount: u64,
+    /// Address of the sender, derived from `sender_public_key`.
+    pub sender: Address,
+    pub receiver: Address,
+    pub amount: Amount,
     pub signature: String,
+    /// Hex compressed public key the signature is checked against.
+    #[serde(default)]
+    pub sender_public_key: String,
+    /// Network the transaction was signed for; a signature is only valid on that chain.
+    #[serde(default)]
+    pub chain_id: String,
+    #[serde(default)]
+    pub kind: TransactionKind,
+    /// Cosigner signatures for a sender that is a multisig address; `signature` and
+    /// `sender_public_key` are then left empty.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multisig: Option<MultisigAuth>,
+    /// Locking and unlocking scripts for a sender that is a script address; `signature`
+    /// and `sender_public_key` are then left empty.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<ScriptAuth>,
+    /// Algorithm of `signature`, or of every cosigner signature for a multisig sender.
+    /// Omitted for ECDSA so transactions from before Schnorr support hash as they did.
+    #[serde(default, skip_serializing_if = "SignatureScheme::is_ecdsa")]
+    pub scheme: SignatureScheme,
+    /// Keeps the transaction out of blocks until the lock has passed; it waits in the mempool meanwhile.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_after: Option<TimeLock>,
+    /// First height at which the transaction can no longer be included; nodes drop it from
+    /// the mempool once the chain gets there.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at_height: Option<u64>,
+    /// Free-form note such as an invoice reference, at most `MAX_MEMO_BYTES` long. The
+    /// sender pays a fee per byte on top of the amount.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    /// Gas limit and price; required to run contracts, and to pay for scripts where the
+    /// chain sets a minimum gas price.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas: Option<Gas>,
+    /// Number of earlier transactions from the sender; the chain only accepts the next
+    /// one, so a confirmed transaction cannot be replayed. Spends leave it at zero, as
+    /// their inputs can only be spent once. Omitted at zero so older transactions hash
+    /// as they did.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub nonce: u64,
+}
+
+fn is_zero(nonce: &u64) -> bool {
+    *nonce == 0
 }
 
 #[allow(dead_code)]
 impl Transaction {
-    pub fn new(sender: &str, receiver: &str, amount: u64, private_key: &str) -> Self {
-        let secp = Secp256k1::new();
-        let secret_key_bytes = hex::decode(private_key).expect("Invalid private key hex");
-        let secret_key = SecretKey::from_slice(&secret_key_bytes).expect("Invalid private key");
-        let message = Self::create_message(sender, receiver, amount);
-        let msg_hash = Sha256::digest(message.as_bytes());
-        let message = Message::from_digest_slice(&msg_hash).expect("Invalid message hash");
-        let signature = secp.sign_ecdsa(&message, &secret_key);
+    pub fn new(chain_id: &str, receiver: &Address, amount: Amount, private_key: &str) -> Self {
+        Self::with_kind(chain_id, TransactionKind::Transfer, receiver, amount, private_key)
+    }
+
+    /// Builds and signs an ECDSA transaction whose sender is the address of `private_key`.
+    pub fn with_kind(chain_id: &str, kind: TransactionKind, receiver: &Address, amount: Amount, private_key: &str) -> Self {
+        Self::with_scheme(chain_id, SignatureScheme::Ecdsa, kind, receiver, amount, private_key)
+    }
+
+    /// Like `with_kind`, signing with `scheme`.
+    pub fn with_scheme(chain_id: &str, scheme: SignatureScheme, kind: TransactionKind, receiver: &Address, amount: Amount, private_key: &str) -> Self {
+        let sender_public_key = crypto::public_key_from_private(private_key).expect("Invalid private key");
+        let sender = Address::from_public_key(&sender_public_key).expect("Invalid public key");
+        let mut transaction = Transaction {
+            sender,
+            receiver: receiver.clone(),
+            amount,
+            signature: String::new(),
+            sender_public_key,
+            chain_id: chain_id.to_string(),
+            kind,
+            multisig: None,
+            script: None,
+            scheme,
+            valid_after: None,
+            expires_at_height: None,
+            memo: None,
+            gas: None,
+            nonce: 0,
+        };
+        transaction.sign(private_key);
+        transaction
+    }
+
+    /// Bonds `amount` of the sender's balance as validator stake.
+    pub fn bond(chain_id: &str, amount: Amount, private_key: &str) -> Self {
+        Self::to_self(chain_id, TransactionKind::Bond, amount, private_key)
+    }
+
+    /// Unbonds `amount` of the sender's stake.
+    pub fn unbond(chain_id: &str, amount: Amount, private_key: &str) -> Self {
+        Self::to_self(chain_id, TransactionKind::Unbond, amount, private_key)
+    }
+
+    /// Locks `amount` for `receiver` until `timeout_height`, claimable with the preimage of `hashlock`.
+    pub fn htlc_lock(chain_id: &str, receiver: &Address, amount: Amount, hashlock: Hash, timeout_height: u64, private_key: &str) -> Self {
+        Self::with_kind(chain_id, TransactionKind::HtlcLock { hashlock, timeout_height }, receiver, amount, private_key)
+    }
+
+    /// Claims the `amount` locked in HTLC `htlc` by revealing its hex `preimage`.
+    pub fn htlc_claim(chain_id: &str, htlc: Hash, amount: Amount, preimage: &str, private_key: &str) -> Self {
+        Self::to_self(chain_id, TransactionKind::HtlcClaim { htlc, preimage: preimage.to_string() }, amount, private_key)
+    }
+
+    /// Takes back the `amount` locked in the timed out HTLC `htlc`.
+    pub fn htlc_refund(chain_id: &str, htlc: Hash, amount: Amount, private_key: &str) -> Self {
+        Self::to_self(chain_id, TransactionKind::HtlcRefund { htlc }, amount, private_key)
+    }
+
+    /// Escrows `amount` for `payee`, to be released or refunded by two of the payer, payee and `arbiter`.
+    pub fn escrow_create(chain_id: &str, payee: &Address, arbiter: &Address, amount: Amount, private_key: &str) -> Self {
+        Self::with_kind(chain_id, TransactionKind::EscrowCreate { arbiter: arbiter.clone() }, payee, amount, private_key)
+    }
+
+    /// Approves paying the `amount` held in escrow `escrow` to its `payee`.
+    pub fn escrow_release(chain_id: &str, escrow: Hash, payee: &Address, amount: Amount, private_key: &str) -> Self {
+        Self::with_kind(chain_id, TransactionKind::EscrowRelease { escrow }, payee, amount, private_key)
+    }
+
+    /// Approves returning the `amount` held in escrow `escrow` to its `payer`.
+    pub fn escrow_refund(chain_id: &str, escrow: Hash, payer: &Address, amount: Amount, private_key: &str) -> Self {
+        Self::with_kind(chain_id, TransactionKind::EscrowRefund { escrow }, payer, amount, private_key)
+    }
+
+    /// Issues a token with `supply` initial units held by the sender.
+    pub fn token_create(chain_id: &str, symbol: &str, decimals: u8, supply: Amount, private_key: &str) -> Self {
+        Self::to_self(chain_id, TransactionKind::TokenCreate { symbol: symbol.to_string(), decimals }, supply, private_key)
+    }
+
+    /// Sends `amount` units of token `token` to `receiver`.
+    pub fn token_transfer(chain_id: &str, token: Hash, receiver: &Address, amount: Amount, private_key: &str) -> Self {
+        Self::with_kind(chain_id, TransactionKind::TokenTransfer { token }, receiver, amount, private_key)
+    }
+
+    /// Mints `amount` new units of token `token` for `receiver`.
+    pub fn token_mint(chain_id: &str, token: Hash, receiver: &Address, amount: Amount, private_key: &str) -> Self {
+        Self::with_kind(chain_id, TransactionKind::TokenMint { token }, receiver, amount, private_key)
+    }
+
+    /// Burns `amount` of the sender's units of token `token`.
+    pub fn token_burn(chain_id: &str, token: Hash, amount: Amount, private_key: &str) -> Self {
+        Self::to_self(chain_id, TransactionKind::TokenBurn { token }, amount, private_key)
+    }
+
+    /// Creates an NFT collection named `name` owned by the sender.
+    pub fn nft_collection_create(chain_id: &str, name: &str, private_key: &str) -> Self {
+        Self::to_self(chain_id, TransactionKind::NftCollectionCreate { name: name.to_string() }, Amount::ZERO, private_key)
+    }
+
+    /// Mints an NFT of `collection` for `owner`, committing to `content_hash`.
+    pub fn nft_mint(chain_id: &str, collection: Hash, owner: &Address, content_hash: Hash, metadata_uri: &str, private_key: &str) -> Self {
+        let kind = TransactionKind::NftMint { collection, content_hash, metadata_uri: metadata_uri.to_string() };
+        Self::with_kind(chain_id, kind, owner, Amount::ZERO, private_key)
+    }
+
+    /// Hands NFT `nft` to `receiver`.
+    pub fn nft_transfer(chain_id: &str, nft: Hash, receiver: &Address, private_key: &str) -> Self {
+        Self::with_kind(chain_id, TransactionKind::NftTransfer { nft }, receiver, Amount::ZERO, private_key)
+    }
+
+    /// Deploys the hex Wasm `code`, endowing the contract with `amount`.
+    pub fn contract_deploy(chain_id: &str, code: &str, input: &str, amount: Amount, gas: Gas, private_key: &str) -> Self {
+        let kind = TransactionKind::ContractDeploy { code: code.to_string(), input: input.to_string() };
+        Self::to_self(chain_id, kind, amount, private_key).with_gas(gas, private_key)
+    }
+
+    /// Calls `method` of the contract at `contract` with the hex `input`, paying it `amount`.
+    pub fn contract_call(chain_id: &str, contract: &Address, method: &str, input: &str, amount: Amount, gas: Gas, private_key: &str) -> Self {
+        let kind = TransactionKind::ContractCall { method: method.to_string(), input: input.to_string() };
+        Self::with_kind(chain_id, kind, contract, amount, private_key).with_gas(gas, private_key)
+    }
+
+    /// Staking, HTLC settlements, token issuance and contract deploys act on the sender's own account, so it is also the receiver.
+    fn to_self(chain_id: &str, kind: TransactionKind, amount: Amount, private_key: &str) -> Self {
+        let public_key = crypto::public_key_from_private(private_key).expect("Invalid private key");
+        let sender = Address::from_public_key(&public_key).expect("Invalid public key");
+        Self::with_kind(chain_id, kind, &sender, amount, private_key)
+    }
+
+    /// Faucet grant minting `amount` new funds for `receiver`.
+    pub fn mint(chain_id: &str, receiver: &Address, amount: Amount, private_key: &str) -> Self {
+        Self::with_kind(chain_id, TransactionKind::Mint, receiver, amount, private_key)
+    }
+
+    /// Reports the proposer of two conflicting headers for slashing.
+    pub fn evidence(chain_id: &str, first: SignedHeader, second: SignedHeader, private_key: &str) -> Result<Self, String> {
+        let offender = Address::from_public_key(&first.proposer).ok_or("Invalid proposer public key")?;
+        let kind = TransactionKind::Evidence { first: Box::new(first), second: Box::new(second) };
+        Ok(Self::with_kind(chain_id, kind, &offender, Amount::ZERO, private_key))
+    }
+
+    /// Pays every output from the address of `private_key` under one signature.
+    pub fn batch(chain_id: &str, outputs: Vec<BatchOutput>, private_key: &str) -> Result<Self, String> {
+        Self::batch_with_scheme(chain_id, SignatureScheme::Ecdsa, outputs, private_key)
+    }
+
+    /// Like `batch`, signing with `scheme`.
+    pub fn batch_with_scheme(chain_id: &str, scheme: SignatureScheme, outputs: Vec<BatchOutput>, private_key: &str) -> Result<Self, String> {
+        let public_key = crypto::public_key_from_private(private_key).ok_or("Invalid private key")?;
+        let sender = Address::from_public_key(&public_key).ok_or("Invalid public key")?;
+        let total = BatchOutput::total(&outputs).ok_or("Batch total overflows")?;
+        Ok(Self::with_scheme(chain_id, scheme, TransactionKind::Batch { outputs }, &sender, total, private_key))
+    }
+
+    /// Spends `inputs`, all owned by the address of `private_key`, into `outputs`. The
+    /// top-level signature stays empty; every input carries its own.
+    pub fn spend(chain_id: &str, scheme: SignatureScheme, inputs: Vec<OutPoint>, outputs: Vec<BatchOutput>, private_key: &str) -> Result<Self, String> {
+        let public_key = crypto::public_key_from_private(private_key).ok_or("Invalid private key")?;
+        let sender = Address::from_public_key(&public_key).ok_or("Invalid public key")?;
+        let amount = BatchOutput::total(&outputs).ok_or("Output total overflows")?;
+        let inputs = inputs.into_iter().map(|outpoint| TxInput { outpoint, public_key: public_key.clone(), signature: String::new(), script: None }).collect();
+        let mut transaction = Transaction {
+            sender: sender.clone(),
+            receiver: sender,
+            amount,
+            signature: String::new(),
+            sender_public_key: String::new(),
+            chain_id: chain_id.to_string(),
+            kind: TransactionKind::Spend { inputs, outputs },
+            multisig: None,
+            script: None,
+            scheme,
+            valid_after: None,
+            expires_at_height: None,
+            memo: None,
+            gas: None,
+            nonce: 0,
+        };
+        transaction.sign(private_key);
+        Ok(transaction)
+    }
+
+    /// An unsigned transaction from the address of `locking`. Signatures over its signing
+    /// message, and any other data the script needs, go into `unlocking` once every signed
+    /// field is final.
+    pub fn from_script(chain_id: &str, locking: Script, kind: TransactionKind, receiver: &Address, amount: Amount) -> Self {
         Transaction {
-            sender: sender.to_string(),
-            receiver: receiver.to_string(),
+            sender: locking.address(),
+            receiver: receiver.clone(),
             amount,
-            signature: hex::encode(signature.serialize_der()),
+            signature: String::new(),
+            sender_public_key: String::new(),
+            chain_id: chain_id.to_string(),
+            kind,
+            multisig: None,
+            script: Some(ScriptAuth { locking, unlocking: Script::default() }),
+            scheme: SignatureScheme::Ecdsa,
+            valid_after: None,
+            expires_at_height: None,
+            memo: None,
+            gas: None,
+            nonce: 0,
+        }
+    }
+
+    /// Re-signs the transaction with a time lock; multisig cosigners set `valid_after` before signing instead.
+    pub fn locked_until(mut self, lock: TimeLock, private_key: &str) -> Self {
+        self.valid_after = Some(lock);
+        self.sign(private_key);
+        self
+    }
+
+    /// Re-signs the transaction so it expires at `height`.
+    pub fn expiring_at(mut self, height: u64, private_key: &str) -> Self {
+        self.expires_at_height = Some(height);
+        self.sign(private_key);
+        self
+    }
+
+    /// Re-signs the transaction with `memo` attached.
+    pub fn with_memo(mut self, memo: &str, private_key: &str) -> Self {
+        self.memo = Some(memo.to_string());
+        self.sign(private_key);
+        self
+    }
+
+    /// Re-signs the transaction with a gas limit and price.
+    pub fn with_gas(mut self, gas: Gas, private_key: &str) -> Self {
+        self.gas = Some(gas);
+        self.sign(private_key);
+        self
+    }
+
+    /// Re-signs the transaction with `nonce`; multisig cosigners and scripts set `nonce`
+    /// before signing instead.
+    pub fn with_nonce(mut self, nonce: u64, private_key: &str) -> Self {
+        self.nonce = nonce;
+        self.sign(private_key);
+        self
+    }
+
+    /// Gas the scripts authorizing the transaction cost, whether the sender's or the
+    /// inputs' of a spend. Scripts have no loops, so this is known before running them.
+    pub fn script_gas(&self) -> u64 {
+        let inputs = match &self.kind {
+            TransactionKind::Spend { inputs, .. } => inputs.as_slice(),
+            _ => &[],
+        };
+        self.script.iter().chain(inputs.iter().filter_map(|input| input.script.as_ref())).map(ScriptAuth::gas).sum()
+    }
+
+    /// Most gas the transaction may use: its gas limit, or what its scripts cost if it sets none.
+    pub fn gas_limit(&self) -> u64 {
+        self.gas.map_or_else(|| self.script_gas(), |gas| gas.limit)
+    }
+
+    /// Fee for the memo at `per_byte`, or None on overflow.
+    pub fn memo_fee(&self, per_byte: Amount) -> Option<Amount> {
+        let bytes = self.memo.as_ref().map_or(0, |memo| memo.len());
+        per_byte.checked_mul(bytes as u64)
+    }
+
+    /// Whether a block at `height` is too late to include the transaction.
+    pub fn is_expired(&self, height: u64) -> bool {
+        matches!(self.expires_at_height, Some(expiry) if height >= expiry)
+    }
+
+    /// Whether a block at `height` with `timestamp` may include the transaction.
+    pub fn is_unlocked(&self, height: u64, timestamp: i64) -> bool {
+        match self.valid_after {
+            Some(lock) => lock.has_passed(height, timestamp),
+            None => true,
         }
     }
 
@@ -33,30 +605,123 @@ impl Transaction {
         format!("{}{}{}", sender, receiver, amount)
     }
 
-    pub fn verify(&self) -> bool {
-        let secp = Secp256k1::new();
-        let public_key_bytes = match hex::decode(&self.sender) {
-            Ok(bytes) => bytes,
-            Err(_) => return false,
-        };
-        let public_key = match PublicKey::from_slice(&public_key_bytes) {
-            Ok(key) => key,
-            Err(_) => return false,
-        };
-        let message = Self::create_message(&self.sender, &self.receiver, self.amount);
-        let msg_hash = Sha256::digest(message.as_bytes());
-        let message = match Message::from_digest_slice(&msg_hash) {
-            Ok(msg) => msg,
-            Err(_) => return false,
-        };
-        let signature_bytes = match hex::decode(&self.signature) {
-            Ok(bytes) => bytes,
-            Err(_) => return false,
-        };
-        let signature = match Signature::from_der(&signature_bytes) {
-            Ok(sig) => sig,
-            Err(_) => return false,
-        };
-        secp.verify_ecdsa(&message, &signature, &public_key).is_ok()
+    /// The message covered by the signature, prefixed with the chain id and kind. Optional
+    /// conditions are appended only when set, so older transactions keep their message.
+    pub fn signing_message(&self) -> String {
+        let mut message = format!(
+            "{}:{}:{}{}",
+            self.chain_id,
+            self.kind.tag(),
+            Self::create_message(self.sender.as_str(), self.receiver.as_str(), self.amount.value()),
+            self.kind.payload()
+        );
+        if let Some(lock) = &self.valid_after {
+            message.push_str(&format!(":valid_after={}", lock));
+        }
+        if let Some(expiry) = self.expires_at_height {
+            message.push_str(&format!(":expires_at_height={}", expiry));
+        }
+        // Hex keeps separators inside the memo from blurring field boundaries
+        if let Some(memo) = &self.memo {
+            message.push_str(&format!(":memo={}", hex::encode(memo)));
+        }
+        if let Some(gas) = &self.gas {
+            message.push_str(&format!(":gas_limit={}:gas_price={}", gas.limit, gas.price.value()));
+        }
+        if self.nonce != 0 {
+            message.push_str(&format!(":nonce={}", self.nonce));
+        }
+        message
+    }
+
+    /// Identifier of the signed transaction, used to look up its status. Multisig
+    /// transactions leave `signature` empty, so their id does not depend on which
+    /// cosigners signed.
+    pub fn id(&self) -> Hash {
+        Hash::digest(format!("{}{}", self.signing_message(), self.signature))
+    }
+
+    /// Signs the transaction, or for a spend every input owned by `private_key`.
+    pub fn sign(&mut self, private_key: &str) {
+        let message = self.signing_message();
+        if let TransactionKind::Spend { inputs, .. } = &mut self.kind {
+            let public_key = crypto::public_key_from_private(private_key);
+            for input in inputs.iter_mut().filter(|input| Some(&input.public_key) == public_key.as_ref()) {
+                input.signature = crypto::sign(self.scheme, &message, private_key);
+            }
+            return;
+        }
+        self.signature = crypto::sign(self.scheme, &message, private_key);
+    }
+
+    /// Checks that the sender address belongs to `sender_public_key` and the signature is
+    /// valid, rejecting transactions signed for another network. A multisig sender instead
+    /// needs at least `threshold` distinct valid signatures from its policy keys, a script
+    /// sender scripts that hash to its address and evaluate to true, and a spend a valid
+    /// signature or script on every input, the first naming the sender.
+    pub fn verify(&self, chain_id: &str) -> bool {
+        if self.chain_id != chain_id {
+            return false;
+        }
+        let message = self.signing_message();
+        let context = ScriptContext { message: &message, scheme: self.scheme, valid_after: self.valid_after };
+        if let TransactionKind::Spend { inputs, .. } = &self.kind {
+            return self.multisig.is_none()
+                && self.script.is_none()
+                && self.signature.is_empty()
+                && self.sender_public_key.is_empty()
+                && (1..=MAX_SPEND_INPUTS).contains(&inputs.len())
+                && inputs[0].owner().as_ref() == Some(&self.sender)
+                && inputs.iter().all(|input| input.verify(&context));
+        }
+        if let Some(auth) = &self.script {
+            return self.multisig.is_none() && self.signature.is_empty() && self.sender_public_key.is_empty() && auth.verify(&self.sender, &context);
+        }
+        match &self.multisig {
+            Some(auth) => {
+                self.signature.is_empty()
+                    && self.sender_public_key.is_empty()
+                    && auth.verify(self.scheme, &message, &self.sender)
+            }
+            None => {
+                Address::from_public_key(&self.sender_public_key).as_ref() == Some(&self.sender)
+                    && crypto::verify(self.scheme, &message, &self.signature, &self.sender_public_key)
+            }
+        }
+    }
+
+    /// Verifies every transaction of a block, spreading the signature checks across threads.
+    /// Schnorr signatures by a single key are checked in BIP340 batches of
+    /// `SCHNORR_BATCH_SIZE`, falling back to one by one only when a batch fails.
+    pub fn verify_batch(transactions: &[Transaction], chain_id: &str) -> bool {
+        Self::verify_all(&transactions.iter().collect::<Vec<_>>(), chain_id)
+    }
+
+    /// `verify_batch` over borrowed transactions.
+    pub fn verify_all(transactions: &[&Transaction], chain_id: &str) -> bool {
+        let (schnorr, others): (Vec<&Transaction>, Vec<&Transaction>) =
+            transactions.iter().copied().partition(|tx| tx.is_single_schnorr());
+        let bound = schnorr.par_iter().all(|tx| {
+            tx.chain_id == chain_id && Address::from_public_key(&tx.sender_public_key).as_ref() == Some(&tx.sender)
+        });
+        if !bound {
+            return false;
+        }
+        let messages: Vec<String> = schnorr.par_iter().map(|tx| tx.signing_message()).collect();
+        let items: Vec<SchnorrItem> = schnorr
+            .iter()
+            .zip(&messages)
+            .map(|(tx, message)| (message.as_str(), tx.signature.as_str(), tx.sender_public_key.as_str()))
+            .collect();
+        items.par_chunks(SCHNORR_BATCH_SIZE).all(crypto::verify_schnorr_batch)
+            && others.par_iter().all(|tx| tx.verify(chain_id))
     }
-}
\ No newline at end of file
+
+    /// Signed by one Schnorr key, so the signature can join a batch.
+    fn is_single_schnorr(&self) -> bool {
+        self.scheme == SignatureScheme::Schnorr
+            && self.multisig.is_none()
+            && self.script.is_none()
+            && !matches!(self.kind, TransactionKind::Spend { .. })
+    }
+}
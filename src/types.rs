@@ -0,0 +1,162 @@
+use serde::{Serialize, Deserialize, Deserializer, Serializer};
+use sha2::{Sha256, Digest};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::openapi::schema::{ObjectBuilder, Schema, SchemaFormat, SchemaType};
+use utoipa::openapi::RefOr;
+
+/// A quantity of the native currency in its smallest unit.
+///
+/// Arithmetic is checked so balances and stakes can never silently wrap around.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+#[serde(transparent)]
+pub struct Amount(u64);
+
+#[allow(dead_code)]
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+    pub const MAX: Amount = Amount(u64::MAX);
+
+    pub const fn new(value: u64) -> Self {
+        Amount(value)
+    }
+
+    pub const fn value(self) -> u64 {
+        self.0
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).map(Amount)
+    }
+
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Amount)
+    }
+
+    pub fn checked_mul(self, factor: u64) -> Option<Amount> {
+        self.0.checked_mul(factor).map(Amount)
+    }
+
+    pub fn saturating_add(self, other: Amount) -> Amount {
+        Amount(self.0.saturating_add(other.0))
+    }
+
+    /// `percent` of the amount, rounded down; percentages above 100 are capped.
+    pub fn percent(self, percent: u64) -> Amount {
+        Amount((self.0 as u128 * percent.min(100) as u128 / 100) as u64)
+    }
+}
+
+impl From<u64> for Amount {
+    fn from(value: u64) -> Self {
+        Amount(value)
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for Amount {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Amount).map_err(|e| format!("Invalid amount {}: {}", s, e))
+    }
+}
+
+impl<'s> utoipa::ToSchema<'s> for Amount {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        let schema = ObjectBuilder::new()
+            .schema_type(SchemaType::Integer)
+            .format(Some(SchemaFormat::Custom("uint64".to_string())))
+            .minimum(Some(0.0))
+            .description(Some("Amount of the native currency in its smallest unit"))
+            .example(Some(serde_json::json!(100)));
+        ("Amount", schema.into())
+    }
+}
+
+/// Length of a hex encoded SHA-256 digest.
+pub const HASH_HEX_LEN: usize = 64;
+
+/// A SHA-256 digest, stored as lowercase hex.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Hash(String);
+
+#[allow(dead_code)]
+impl Hash {
+    /// SHA-256 of `data`.
+    pub fn digest(data: impl AsRef<[u8]>) -> Self {
+        Self::from_digest(Sha256::digest(data))
+    }
+
+    /// Wraps the output of a finished SHA-256 hasher.
+    pub fn from_digest(digest: impl AsRef<[u8]>) -> Self {
+        Hash(hex::encode(digest))
+    }
+
+    /// The all-zero hash, used as a placeholder before a block is hashed.
+    pub fn zero() -> Self {
+        Hash("0".repeat(HASH_HEX_LEN))
+    }
+
+    /// Parses a hash, requiring exactly 64 hex digits.
+    pub fn parse(hash: &str) -> Result<Hash, String> {
+        if hash.len() != HASH_HEX_LEN || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid hash {}: expected {} hex digits", hash, HASH_HEX_LEN));
+        }
+        Ok(Hash(hash.to_lowercase()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Hash {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Hash::parse(s)
+    }
+}
+
+impl Serialize for Hash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Hash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hash = String::deserialize(deserializer)?;
+        Hash::parse(&hash).map_err(serde::de::Error::custom)
+    }
+}
+
+impl<'s> utoipa::ToSchema<'s> for Hash {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        let schema = ObjectBuilder::new()
+            .schema_type(SchemaType::String)
+            .format(Some(SchemaFormat::Custom("sha256-hex".to_string())))
+            .pattern(Some("^[0-9a-f]{64}$"))
+            .min_length(Some(HASH_HEX_LEN))
+            .max_length(Some(HASH_HEX_LEN))
+            .description(Some("SHA-256 digest as lowercase hex"))
+            .example(Some(serde_json::json!("00a3f1c29b5e8d7e4c6b0a9f8e7d6c5b4a39281706f5e4d3c2b1a09f8e7d6c5b")));
+        ("Hash", schema.into())
+    }
+}
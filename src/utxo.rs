@@ -0,0 +1,149 @@
+use serde::{Serialize, Deserialize, Deserializer, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use utoipa::openapi::schema::{ObjectBuilder, Schema, SchemaType};
+use utoipa::openapi::RefOr;
+use crate::address::Address;
+use crate::types::{Amount, Hash};
+
+/// Upper bound on the inputs of one spend, which also bounds its signature checks.
+pub const MAX_SPEND_INPUTS: usize = 256;
+
+/// Reference to output `index` of the transaction with id `tx`, written `<tx>:<index>`.
+///
+/// Outputs of the genesis allocation use the genesis block hash as `tx`.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct OutPoint {
+    pub tx: Hash,
+    pub index: u32,
+}
+
+impl fmt::Display for OutPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.tx, self.index)
+    }
+}
+
+impl FromStr for OutPoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (tx, index) = s.split_once(':').ok_or_else(|| format!("Invalid outpoint {}: expected <tx>:<index>", s))?;
+        let index = index.parse().map_err(|e| format!("Invalid outpoint index {}: {}", index, e))?;
+        Ok(OutPoint { tx: Hash::parse(tx)?, index })
+    }
+}
+
+impl Serialize for OutPoint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for OutPoint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl<'s> utoipa::ToSchema<'s> for OutPoint {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        let schema = ObjectBuilder::new()
+            .schema_type(SchemaType::String)
+            .pattern(Some("^[0-9a-f]{64}:[0-9]+$"))
+            .description(Some("Transaction id and output index"))
+            .example(Some(serde_json::json!("00a3f1c29b5e8d7e4c6b0a9f8e7d6c5b4a39281706f5e4d3c2b1a09f8e7d6c5b:0")));
+        ("OutPoint", schema.into())
+    }
+}
+
+/// An unspent output: `amount` that only a signature by the key of `owner` can spend.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct Utxo {
+    pub owner: Address,
+    pub amount: Amount,
+}
+
+/// An unspent output together with where it was created, as listed by `GET /utxos`.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, PartialEq, Debug)]
+pub struct UnspentOutput {
+    pub outpoint: OutPoint,
+    #[serde(flatten)]
+    pub output: Utxo,
+}
+
+/// The outputs not yet consumed by any transaction, on a chain using the UTXO ledger.
+#[derive(Serialize, Deserialize, Clone, utoipa::ToSchema, Default, PartialEq, Debug)]
+#[serde(transparent)]
+pub struct UtxoSet {
+    unspent: HashMap<OutPoint, Utxo>,
+}
+
+#[allow(dead_code)]
+impl UtxoSet {
+    pub fn is_empty(&self) -> bool {
+        self.unspent.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.unspent.len()
+    }
+
+    pub fn get(&self, outpoint: &OutPoint) -> Option<&Utxo> {
+        self.unspent.get(outpoint)
+    }
+
+    pub fn contains(&self, outpoint: &OutPoint) -> bool {
+        self.unspent.contains_key(outpoint)
+    }
+
+    pub fn insert(&mut self, outpoint: OutPoint, output: Utxo) {
+        self.unspent.insert(outpoint, output);
+    }
+
+    pub fn remove(&mut self, outpoint: &OutPoint) -> Option<Utxo> {
+        self.unspent.remove(outpoint)
+    }
+
+    /// Sum of the outputs `owner` can spend.
+    pub fn balance(&self, owner: &Address) -> Amount {
+        self.unspent.values().filter(|output| output.owner == *owner).fold(Amount::ZERO, |total, output| total.saturating_add(output.amount))
+    }
+
+    /// The outputs `owner` can spend, ordered by outpoint.
+    pub fn owned_by(&self, owner: &Address) -> Vec<UnspentOutput> {
+        let mut owned: Vec<UnspentOutput> = self
+            .unspent
+            .iter()
+            .filter(|(_, output)| output.owner == *owner)
+            .map(|(outpoint, output)| UnspentOutput { outpoint: outpoint.clone(), output: output.clone() })
+            .collect();
+        owned.sort_by(|a, b| a.outpoint.cmp(&b.outpoint));
+        owned
+    }
+}
+
+/// Picks coins worth at least `target`, largest first so a payment needs as few inputs
+/// as possible. Returns the chosen outpoints and the change left over.
+#[allow(dead_code)]
+pub fn select_coins(coins: &[UnspentOutput], target: Amount) -> Result<(Vec<OutPoint>, Amount), String> {
+    let mut candidates: Vec<&UnspentOutput> = coins.iter().collect();
+    candidates.sort_by(|a, b| b.output.amount.cmp(&a.output.amount).then_with(|| a.outpoint.cmp(&b.outpoint)));
+    let mut selected = Vec::new();
+    let mut total = Amount::ZERO;
+    for coin in candidates {
+        if total >= target && !selected.is_empty() {
+            break;
+        }
+        if selected.len() == MAX_SPEND_INPUTS {
+            return Err(format!("Paying {} needs more than {} inputs", target, MAX_SPEND_INPUTS));
+        }
+        selected.push(coin.outpoint.clone());
+        total = total.checked_add(coin.output.amount).ok_or("Selected coins overflow")?;
+    }
+    match total.checked_sub(target) {
+        Some(change) if !selected.is_empty() => Ok((selected, change)),
+        _ => Err("Insufficient funds".to_string()),
+    }
+}
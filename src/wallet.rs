@@ -1,22 +1,116 @@
 use serde::{Serialize, Deserialize};
-use secp256k1::Secp256k1;
+use secp256k1::{SecretKey, PublicKey, SECP256K1};
 use rand::rngs::OsRng;
-use hex;
+use rand::RngCore;
+use bip32::{DerivationPath, XPrv};
+use bip39::Mnemonic;
+use zeroize::{Zeroize, Zeroizing};
+use crate::address::Address;
+
+/// SLIP-44 coin type 1, shared by test networks, used until ChainRust registers its own.
+pub const COIN_TYPE: u32 = 1;
 
 #[derive(Serialize, Deserialize, Clone, utoipa::ToSchema)]
 pub struct Wallet {
+    pub address: Address,
     pub public_key: String,
     pub private_key: String,
 }
 
+#[allow(dead_code)]
 impl Wallet {
     pub fn new() -> Self {
-        let secp = Secp256k1::new();
         let mut rng = OsRng;
-        let (secret_key, public_key) = secp.generate_keypair(&mut rng);
+        let (secret_key, _) = SECP256K1.generate_keypair(&mut rng);
+        Self::from_secret_key(&secret_key)
+    }
+
+    /// Wallet for an existing hex encoded secret key.
+    pub fn from_private_key(private_key: &str) -> Result<Self, String> {
+        let bytes = Zeroizing::new(hex::decode(private_key).map_err(|e| format!("Invalid private key: {}", e))?);
+        let secret_key = SecretKey::from_slice(&bytes).map_err(|e| format!("Invalid private key: {}", e))?;
+        Ok(Self::from_secret_key(&secret_key))
+    }
+
+    fn from_secret_key(secret_key: &SecretKey) -> Self {
+        let public_key = PublicKey::from_secret_key(SECP256K1, secret_key);
+        let public_key = hex::encode(public_key.serialize());
         Wallet {
-            public_key: hex::encode(public_key.serialize()),
+            address: Address::from_public_key(&public_key).expect("Valid public key"),
+            public_key,
             private_key: hex::encode(secret_key.secret_bytes()),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Wipes the private key, so a wallet can be held in `Zeroizing`.
+impl Zeroize for Wallet {
+    fn zeroize(&mut self) {
+        self.private_key.zeroize();
+    }
+}
+
+/// A hierarchical deterministic wallet: every key is derived from one BIP39 mnemonic,
+/// so backing up the words backs up all accounts.
+pub struct HdWallet {
+    mnemonic: Mnemonic,
+    seed: Zeroizing<[u8; 64]>,
+}
+
+#[allow(dead_code)]
+impl HdWallet {
+    /// Generates a fresh mnemonic of 12 or 24 words.
+    pub fn generate(word_count: usize, passphrase: &str) -> Result<Self, String> {
+        let entropy_len = match word_count {
+            12 => 16,
+            24 => 32,
+            _ => return Err(format!("Unsupported mnemonic length {}: use 12 or 24 words", word_count)),
+        };
+        let mut entropy = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(&mut entropy[..entropy_len]);
+        let mnemonic = Mnemonic::from_entropy(&entropy[..entropy_len]).map_err(|e| e.to_string())?;
+        Ok(Self::from_mnemonic(mnemonic, passphrase))
+    }
+
+    /// Restores a wallet from its mnemonic, checking the word list and checksum.
+    pub fn restore(phrase: &str, passphrase: &str) -> Result<Self, String> {
+        let mnemonic = Mnemonic::parse(phrase).map_err(|e| format!("Invalid mnemonic: {}", e))?;
+        match mnemonic.word_count() {
+            12 | 24 => Ok(Self::from_mnemonic(mnemonic, passphrase)),
+            n => Err(format!("Unsupported mnemonic length {}: use 12 or 24 words", n)),
+        }
+    }
+
+    fn from_mnemonic(mnemonic: Mnemonic, passphrase: &str) -> Self {
+        let seed = Zeroizing::new(mnemonic.to_seed(passphrase));
+        HdWallet { mnemonic, seed }
+    }
+
+    pub fn mnemonic(&self) -> String {
+        self.mnemonic.to_string()
+    }
+
+    /// BIP44 path of the `index`th receiving key of `account`.
+    pub fn path(account: u32, index: u32) -> String {
+        format!("m/44'/{}'/{}'/0/{}", COIN_TYPE, account, index)
+    }
+
+    /// Derives the key at a BIP32 path such as `m/44'/1'/0'/0/0`. The key is wiped from
+    /// memory when dropped.
+    pub fn derive_path(&self, path: &str) -> Result<Zeroizing<Wallet>, String> {
+        let path: DerivationPath = path.parse().map_err(|e| format!("Invalid derivation path {}: {}", path, e))?;
+        let key = XPrv::derive_from_path(self.seed.as_slice(), &path).map_err(|e| format!("Key derivation failed: {}", e))?;
+        let private_key = Zeroizing::new(hex::encode(Zeroizing::new(key.to_bytes()).as_slice()));
+        Wallet::from_private_key(&private_key).map(Zeroizing::new)
+    }
+
+    /// The `index`th receiving key of `account` along the standard BIP44 path.
+    pub fn derive(&self, account: u32, index: u32) -> Result<Zeroizing<Wallet>, String> {
+        self.derive_path(&Self::path(account, index))
+    }
+
+    /// Addresses of the first `count` receiving keys of `account`.
+    pub fn addresses(&self, account: u32, count: u32) -> Result<Vec<Address>, String> {
+        (0..count).map(|index| self.derive(account, index).map(|wallet| wallet.address.clone())).collect()
+    }
+}
@@ -0,0 +1,60 @@
+mod address {
+    include!("../src/address.rs");
+}
+
+use address::{Address, ADDRESS_HRP};
+use bech32::{Bech32, Bech32m, Hrp};
+
+const PUBLIC_KEY: &str = "02d524421eb3d7d4c8d4e66f536aa00e3760282cd476373e0a7ca7cb73044ce934";
+
+#[test]
+fn test_address_from_public_key() {
+    let address = Address::from_public_key(PUBLIC_KEY).unwrap();
+    assert!(address.as_str().starts_with("crt1"));
+    assert_eq!(address.as_str().len(), 42);
+    assert_eq!(Address::from_public_key(PUBLIC_KEY), Some(address.clone()));
+    assert_eq!(Address::parse(address.as_str()), Ok(address));
+    assert_eq!(Address::from_public_key("not hex"), None);
+    assert_eq!(Address::from_public_key("02d5"), None);
+}
+
+#[test]
+fn test_checksum_catches_typos() {
+    let address = Address::from_public_key(PUBLIC_KEY).unwrap().to_string();
+    let last = address.chars().last().unwrap();
+    let typo = format!("{}{}", &address[..address.len() - 1], if last == 'q' { 'p' } else { 'q' });
+    assert!(Address::parse(&typo).is_err());
+    assert!(Address::parse("receiver").is_err());
+    assert!(Address::parse(PUBLIC_KEY).is_err(), "Raw public keys are not addresses");
+}
+
+#[test]
+fn test_rejects_wrong_prefix_length_and_checksum_variant() {
+    let data = [7u8; 20];
+    let other_prefix = bech32::encode::<Bech32m>(Hrp::parse("btc").unwrap(), &data).unwrap();
+    assert!(Address::parse(&other_prefix).is_err());
+
+    let hrp = Hrp::parse(ADDRESS_HRP).unwrap();
+    let short = bech32::encode::<Bech32m>(hrp, &data[..19]).unwrap();
+    assert!(Address::parse(&short).is_err());
+
+    let legacy_checksum = bech32::encode::<Bech32>(hrp, &data).unwrap();
+    assert!(Address::parse(&legacy_checksum).is_err());
+    assert!(Address::parse(&bech32::encode::<Bech32m>(hrp, &data).unwrap()).is_ok());
+}
+
+#[test]
+fn test_uppercase_address_is_normalized() {
+    let address = Address::from_public_key(PUBLIC_KEY).unwrap();
+    assert_eq!(Address::parse(&address.as_str().to_uppercase()), Ok(address));
+}
+
+#[test]
+fn test_deserialize_rejects_malformed_address() {
+    let address = Address::from_public_key(PUBLIC_KEY).unwrap();
+    let json = serde_json::to_string(&address).unwrap();
+    assert_eq!(json, format!("\"{}\"", address));
+    assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), address);
+    assert!(serde_json::from_str::<Address>("\"receiver\"").is_err());
+    assert!(serde_json::from_str::<Address>(&format!("\"{}\"", PUBLIC_KEY)).is_err());
+}
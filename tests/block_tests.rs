@@ -4,15 +4,36 @@ mod block {
 mod transaction {
     include!("../src/transaction.rs");
 }
+mod address {
+    include!("../src/address.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
 
 use block::Block;
 use log::debug;
+use types::Hash;
 
 #[test]
 fn test_genesis_block() {
-    let block = Block::genesis();
+    let config_hash = Hash::digest("config");
+    let block = Block::genesis("chainrust-test", 0, config_hash.clone());
     assert_eq!(block.index, 0);
-    assert_eq!(block.previous_hash, "0");
+    assert_eq!(block.timestamp, 0);
+    assert_eq!(block.previous_hash, config_hash);
     assert_eq!(block.transactions.len(), 0);
     assert_eq!(block.nonce, 0);
     assert_eq!(block.hash, block.calculate_hash());
@@ -21,8 +42,8 @@ fn test_genesis_block() {
 #[test]
 fn test_new_block() {
     let transactions = vec![];
-    let previous_hash = "previous_hash".to_string();
-    let block = Block::new(1, transactions.clone(), previous_hash.clone());
+    let previous_hash = Hash::digest("previous");
+    let block = Block::new("chainrust-test", 1, transactions.clone(), previous_hash.clone());
     assert_eq!(block.index, 1);
     assert_eq!(block.previous_hash, previous_hash);
     assert_eq!(block.transactions, transactions);
@@ -32,7 +53,7 @@ fn test_new_block() {
 
 #[test]
 fn test_calculate_hash() {
-    let block = Block::genesis();
+    let block = Block::genesis("chainrust-test", 0, Hash::digest("config"));
     let expected_hash = block.calculate_hash();
     debug!("Genesis block hash: stored={}, calculated={}", block.hash, expected_hash);
     assert_eq!(block.hash, expected_hash, "Stored hash should match calculated hash");
@@ -43,4 +64,24 @@ fn test_calculate_hash() {
     let modified_hash = modified_block.calculate_hash();
     debug!("Modified block (nonce=1) hash: {}", modified_hash);
     assert_ne!(modified_hash, expected_hash, "Hash should change with different nonce");
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_mine_meets_difficulty() {
+    let mut block = Block::new("chainrust-test", 1, vec![], Hash::digest("previous"));
+    block.mine(2);
+    assert!(block.hash.as_str().starts_with("00"));
+    assert!(block.meets_difficulty(2));
+    assert_eq!(block.hash, block.calculate_hash());
+}
+
+#[test]
+fn test_hash_parsing() {
+    let hash = Hash::digest("block");
+    assert_eq!(Hash::parse(hash.as_str()), Ok(hash.clone()));
+    assert_eq!(Hash::parse(&hash.as_str().to_uppercase()), Ok(hash.clone()));
+    assert!(Hash::parse("config_hash").is_err());
+    assert!(Hash::parse(&hash.as_str()[1..]).is_err());
+    assert!(serde_json::from_str::<Hash>("\"0\"").is_err());
+    assert_eq!(serde_json::from_value::<Hash>(serde_json::to_value(&hash).unwrap()).unwrap(), hash);
+}
@@ -10,10 +10,58 @@ mod transaction {
 mod wallet {
     include!("../src/wallet.rs");
 }
+mod address {
+    include!("../src/address.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+mod sigcache {
+    include!("../src/sigcache.rs");
+}
+mod staking {
+    include!("../src/staking.rs");
+}
+mod genesis {
+    include!("../src/genesis.rs");
+}
+mod htlc {
+    include!("../src/htlc.rs");
+}
+mod escrow {
+    include!("../src/escrow.rs");
+}
+mod token {
+    include!("../src/token.rs");
+}
+mod nft {
+    include!("../src/nft.rs");
+}
+mod contract {
+    include!("../src/contract.rs");
+}
+mod receipt {
+    include!("../src/receipt.rs");
+}
 
 use blockchain::Blockchain;
 use block::Block;
-use transaction::Transaction;
+use crypto::SignatureScheme;
+use multisig::{MultisigPolicy, PartiallySignedTransaction};
+use transaction::{BatchOutput, TimeLock, Transaction, TransactionKind, MAX_BATCH_OUTPUTS, MAX_MEMO_BYTES};
+use types::{Amount, Hash};
 use wallet::Wallet;
 
 #[test]
@@ -27,20 +75,29 @@ fn test_new_blockchain() {
 #[test]
 fn test_add_funds() {
     let mut blockchain = Blockchain::new();
-    let address = "test_address";
-    blockchain.add_funds(address, 100);
-    assert_eq!(blockchain.balances.get(address), Some(&100));
-    blockchain.add_funds(address, 50);
-    assert_eq!(blockchain.balances.get(address), Some(&150));
+    let address = Wallet::new().address;
+    blockchain.add_funds(&address, Amount::new(100)).unwrap();
+    assert_eq!(blockchain.balances.get(&address), Some(&Amount::new(100)));
+    blockchain.add_funds(&address, Amount::new(50)).unwrap();
+    assert_eq!(blockchain.balances.get(&address), Some(&Amount::new(150)));
+}
+
+#[test]
+fn test_add_funds_rejects_overflow() {
+    let mut blockchain = Blockchain::new();
+    let address = Wallet::new().address;
+    blockchain.add_funds(&address, Amount::MAX).unwrap();
+    assert!(blockchain.add_funds(&address, Amount::new(1)).is_err());
+    assert_eq!(blockchain.get_balance(&address), Amount::MAX);
 }
 
 #[test]
 fn test_get_balance() {
     let mut blockchain = Blockchain::new();
-    let address = "test_address";
-    assert_eq!(blockchain.get_balance(address), 0);
-    blockchain.add_funds(address, 200);
-    assert_eq!(blockchain.get_balance(address), 200);
+    let address = Wallet::new().address;
+    assert_eq!(blockchain.get_balance(&address), Amount::ZERO);
+    blockchain.add_funds(&address, Amount::new(200)).unwrap();
+    assert_eq!(blockchain.get_balance(&address), Amount::new(200));
 }
 
 #[test]
@@ -50,12 +107,13 @@ fn test_is_chain_valid() {
 
     let wallet = Wallet::new();
     let transaction = Transaction::new(
-        &wallet.public_key,
-        "receiver",
-        50,
+        blockchain.chain_id(),
+        &Wallet::new().address,
+        Amount::new(50),
         &wallet.private_key,
     );
     let new_block = Block::new(
+        blockchain.chain_id(),
         1,
         vec![transaction],
         blockchain.chain[0].hash.clone(),
@@ -64,18 +122,376 @@ fn test_is_chain_valid() {
     assert!(blockchain.is_chain_valid());
 
     // Invalidate the chain
-    blockchain.chain[1].hash = "invalid_hash".to_string();
+    blockchain.chain[1].hash = Hash::digest("invalid_hash");
     assert!(!blockchain.is_chain_valid());
 }
 
 #[test]
 fn test_save_and_load_from_file() {
     let mut blockchain = Blockchain::new();
-    blockchain.add_funds("test_address", 100);
+    let address = Wallet::new().address;
+    blockchain.add_funds(&address, Amount::new(100)).unwrap();
     let filename = "test_blockchain.json";
     blockchain.save_to_file(filename).unwrap();
     let loaded_blockchain = Blockchain::load_from_file(filename).unwrap();
     assert_eq!(loaded_blockchain.chain.len(), 1);
-    assert_eq!(loaded_blockchain.get_balance("test_address"), 100);
+    assert_eq!(loaded_blockchain.get_balance(&address), Amount::new(100));
     std::fs::remove_file(filename).unwrap();
-}
\ No newline at end of file
+}
+#[test]
+fn test_rejects_other_chain() {
+    let mut blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    blockchain.add_funds(&wallet.address, Amount::new(100)).unwrap();
+
+    let foreign = Transaction::new("chainrust-other", &Wallet::new().address, Amount::new(50), &wallet.private_key);
+    assert!(blockchain.validate_transaction(&foreign).is_err());
+
+    let block = Block::new("chainrust-other", 1, vec![], blockchain.chain[0].hash.clone());
+    assert!(blockchain.add_block(block).is_err());
+}
+
+#[test]
+fn test_transfer_rejects_receiver_overflow() {
+    let mut blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    let receiver = Wallet::new().address;
+    blockchain.add_funds(&wallet.address, Amount::new(10)).unwrap();
+    blockchain.add_funds(&receiver, Amount::MAX).unwrap();
+
+    let transfer = Transaction::new(blockchain.chain_id(), &receiver, Amount::new(10), &wallet.private_key);
+    assert!(blockchain.validate_transaction(&transfer).is_err());
+    blockchain.mine_block(vec![transfer], None).unwrap();
+    assert_eq!(blockchain.get_balance(&wallet.address), Amount::new(10));
+    assert_eq!(blockchain.get_balance(&receiver), Amount::MAX);
+}
+
+#[test]
+fn test_find_transaction_by_id() {
+    let mut blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    blockchain.add_funds(&wallet.address, Amount::new(100)).unwrap();
+    let transfer = Transaction::new(blockchain.chain_id(), &Wallet::new().address, Amount::new(10), &wallet.private_key);
+    let id = transfer.id();
+    assert_eq!(blockchain.find_transaction(&id), None);
+
+    blockchain.mine_block(vec![transfer.clone()], None).unwrap();
+    assert_eq!(blockchain.find_transaction(&id), Some(1));
+
+    let mut altered = transfer;
+    altered.amount = Amount::new(11);
+    assert_ne!(altered.id(), id, "The id commits to every signed field");
+}
+
+#[test]
+fn test_multisig_treasury_spend() {
+    let mut blockchain = Blockchain::new();
+    let cosigners: Vec<Wallet> = (0..3).map(|_| Wallet::new()).collect();
+    let keys: Vec<String> = cosigners.iter().map(|wallet| wallet.public_key.clone()).collect();
+    let policy = MultisigPolicy::new(2, &keys).unwrap();
+    let treasury = policy.address();
+    blockchain.add_funds(&treasury, Amount::new(100)).unwrap();
+
+    let receiver = Wallet::new().address;
+    let mut psbt = PartiallySignedTransaction::new(blockchain.chain_id(), policy.clone(), TransactionKind::Transfer, &receiver, Amount::new(40)).unwrap();
+    psbt.sign(&cosigners[0].private_key).unwrap();
+    assert!(blockchain.validate_transaction(&psbt.transaction).is_err(), "One signature is not enough");
+    psbt.sign(&cosigners[1].private_key).unwrap();
+    let spend = psbt.finalize().unwrap();
+
+    blockchain.mine_block(vec![spend], None).unwrap();
+    assert_eq!(blockchain.get_balance(&treasury), Amount::new(60));
+    assert_eq!(blockchain.get_balance(&receiver), Amount::new(40));
+
+    let mut bond = PartiallySignedTransaction::new(blockchain.chain_id(), policy, TransactionKind::Bond, &treasury, Amount::new(10)).unwrap();
+    bond.sign(&cosigners[0].private_key).unwrap();
+    bond.sign(&cosigners[2].private_key).unwrap();
+    assert!(blockchain.validate_transaction(&bond.finalize().unwrap()).is_err(), "Multisig accounts cannot validate");
+}
+
+#[test]
+fn test_add_block_batch_verifies_schnorr_transactions() {
+    let mut blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    let receiver = Wallet::new().address;
+    blockchain.add_funds(&wallet.address, Amount::new(100)).unwrap();
+    let transfers: Vec<Transaction> = (0..3)
+        .map(|nonce| {
+            Transaction::with_scheme(blockchain.chain_id(), SignatureScheme::Schnorr, TransactionKind::Transfer, &receiver, Amount::new(10), &wallet.private_key)
+                .with_nonce(nonce, &wallet.private_key)
+        })
+        .collect();
+
+    let mut forged = transfers.clone();
+    forged[1].amount = Amount::new(90);
+    let block = Block::new(blockchain.chain_id(), 1, forged, blockchain.chain[0].hash.clone());
+    assert!(blockchain.add_block(block).is_err());
+    assert_eq!(blockchain.get_balance(&wallet.address), Amount::new(100));
+
+    let block = Block::new(blockchain.chain_id(), 1, transfers, blockchain.chain[0].hash.clone());
+    blockchain.add_block(block).unwrap();
+    assert_eq!(blockchain.get_balance(&receiver), Amount::new(30));
+}
+
+#[test]
+fn test_mempool_verification_is_cached() {
+    let mut blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    blockchain.add_funds(&wallet.address, Amount::new(100)).unwrap();
+    let transfer = Transaction::new(blockchain.chain_id(), &Wallet::new().address, Amount::new(10), &wallet.private_key);
+    assert!(!blockchain.signature_cache().contains(&transfer, blockchain.chain_id()));
+
+    blockchain.validate_transaction(&transfer).unwrap();
+    assert!(blockchain.signature_cache().contains(&transfer, blockchain.chain_id()));
+
+    let mut forged = transfer.clone();
+    forged.amount = Amount::new(20);
+    assert!(blockchain.validate_transaction(&forged).is_err(), "A cached signature must not cover altered fields");
+    assert_eq!(blockchain.signature_cache().len(), 1);
+
+    blockchain.mine_block(vec![transfer], None).unwrap();
+    assert_eq!(blockchain.get_balance(&wallet.address), Amount::new(90));
+}
+
+#[test]
+fn test_confirmed_transfers_cannot_be_replayed() {
+    let mut blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    let receiver = Wallet::new().address;
+    blockchain.add_funds(&wallet.address, Amount::new(100)).unwrap();
+    let transfer = Transaction::new(blockchain.chain_id(), &receiver, Amount::new(10), &wallet.private_key);
+    blockchain.mine_block(vec![transfer.clone()], None).unwrap();
+    assert_eq!(blockchain.next_nonce(&wallet.address, &[]), 1);
+
+    assert_eq!(blockchain.validate_transaction(&transfer), Err("Expected nonce 1, got 0".to_string()));
+    blockchain.mine_block(vec![transfer.clone()], None).unwrap();
+    assert!(blockchain.chain[2].transactions.is_empty(), "The replay was dropped");
+    let tip = blockchain.chain.last().unwrap().clone();
+    let block = Block::new(blockchain.chain_id(), tip.index + 1, vec![transfer.clone()], tip.hash.clone());
+    assert_eq!(blockchain.add_block(block), Err("Expected nonce 1, got 0".to_string()));
+    assert_eq!(blockchain.get_balance(&receiver), Amount::new(10));
+
+    // The nonce is signed, so it cannot be bumped to get past the check
+    let mut renumbered = transfer.clone();
+    renumbered.nonce = 1;
+    assert!(blockchain.validate_transaction(&renumbered).is_err());
+    let next = transfer.with_nonce(1, &wallet.private_key);
+    blockchain.validate_transaction(&next).unwrap();
+    assert_eq!(blockchain.validate_pending(&next, std::slice::from_ref(&next)), Err("Expected nonce 2, got 1".to_string()));
+    assert_eq!(blockchain.next_nonce(&wallet.address, &[next]), 2);
+}
+
+#[test]
+fn test_time_locked_transactions_wait_for_their_height() {
+    let mut blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    let receiver = Wallet::new().address;
+    blockchain.add_funds(&wallet.address, Amount::new(100)).unwrap();
+    let scheduled = Transaction::new(blockchain.chain_id(), &receiver, Amount::new(10), &wallet.private_key)
+        .locked_until(TimeLock::Height(1), &wallet.private_key);
+    blockchain.validate_transaction(&scheduled).expect("The mempool accepts locked transactions");
+
+    let early = Block::new(blockchain.chain_id(), 1, vec![scheduled.clone()], blockchain.chain[0].hash.clone());
+    assert!(blockchain.add_block(early).is_err(), "Block 1 is not after height 1");
+
+    blockchain.mine_block(vec![scheduled.clone()], None).unwrap();
+    assert!(blockchain.chain[1].transactions.is_empty());
+    assert_eq!(blockchain.get_balance(&receiver), Amount::ZERO);
+
+    let block = Block::new(blockchain.chain_id(), 2, vec![scheduled], blockchain.chain[1].hash.clone());
+    blockchain.add_block(block).unwrap();
+    assert_eq!(blockchain.get_balance(&receiver), Amount::new(10));
+}
+
+#[test]
+fn test_transfers_behind_a_locked_one_wait_with_it() {
+    let mut blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    let receiver = Wallet::new().address;
+    blockchain.add_funds(&wallet.address, Amount::new(100)).unwrap();
+    let scheduled = Transaction::new(blockchain.chain_id(), &receiver, Amount::new(10), &wallet.private_key)
+        .locked_until(TimeLock::Height(1), &wallet.private_key);
+    let mut mempool = vec![scheduled];
+    let nonce = blockchain.next_nonce(&wallet.address, &mempool);
+    assert_eq!(nonce, 1);
+    let second = Transaction::new(blockchain.chain_id(), &receiver, Amount::new(5), &wallet.private_key).with_nonce(nonce, &wallet.private_key);
+    blockchain.validate_pending(&second, &mempool).unwrap();
+    mempool.push(second);
+    let other = Wallet::new();
+    blockchain.add_funds(&other.address, Amount::new(10)).unwrap();
+    mempool.push(Transaction::new(blockchain.chain_id(), &receiver, Amount::new(1), &other.private_key));
+
+    let (ready, waiting) = Blockchain::ready_transactions(mempool, blockchain.next_height(), 0);
+    assert_eq!(ready.len(), 1, "Only the other sender's transfer is ready");
+    assert_eq!(waiting.iter().map(|tx| tx.nonce).collect::<Vec<_>>(), vec![0, 1]);
+    blockchain.mine_block(ready, None).unwrap();
+
+    let (ready, waiting) = Blockchain::ready_transactions(waiting, blockchain.next_height(), 0);
+    assert!(waiting.is_empty());
+    blockchain.mine_block(ready, None).unwrap();
+    assert_eq!(blockchain.chain[2].transactions.len(), 2, "Both transfers are mined in nonce order");
+    assert_eq!(blockchain.get_balance(&receiver), Amount::new(16));
+}
+
+#[test]
+fn test_timestamp_locks_and_block_time_bounds() {
+    let mut blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    blockchain.add_funds(&wallet.address, Amount::new(100)).unwrap();
+    let now = chrono::Utc::now().timestamp();
+    let scheduled = Transaction::new(blockchain.chain_id(), &Wallet::new().address, Amount::new(10), &wallet.private_key)
+        .locked_until(TimeLock::Timestamp(now + 3600), &wallet.private_key);
+
+    let early = Block::new(blockchain.chain_id(), 1, vec![scheduled.clone()], blockchain.chain[0].hash.clone());
+    assert!(blockchain.add_block(early).is_err());
+
+    // A proposer cannot skip ahead by lying about the time
+    let mut future = Block::new(blockchain.chain_id(), 1, vec![scheduled], blockchain.chain[0].hash.clone());
+    future.timestamp = now + blockchain::MAX_FUTURE_BLOCK_TIME + 60;
+    future.hash = future.calculate_hash();
+    assert!(blockchain.add_block(future).is_err());
+    assert_eq!(blockchain.get_balance(&wallet.address), Amount::new(100));
+}
+
+#[test]
+fn test_expired_transactions_are_rejected() {
+    let mut blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    let receiver = Wallet::new().address;
+    blockchain.add_funds(&wallet.address, Amount::new(100)).unwrap();
+    let chain_id = blockchain.chain_id().to_string();
+    let transfer = |expiry: u64| {
+        Transaction::new(&chain_id, &receiver, Amount::new(10), &wallet.private_key).expiring_at(expiry, &wallet.private_key)
+    };
+
+    assert!(blockchain.validate_transaction(&transfer(1)).is_err(), "Height 1 is already too late");
+    blockchain.validate_transaction(&transfer(2)).unwrap();
+
+    let never = transfer(3).locked_until(TimeLock::Height(2), &wallet.private_key);
+    assert!(blockchain.validate_transaction(&never).is_err(), "A lock past the expiry can never be mined");
+
+    let stale = Block::new(blockchain.chain_id(), 1, vec![transfer(1)], blockchain.chain[0].hash.clone());
+    assert!(blockchain.add_block(stale).is_err());
+
+    blockchain.mine_block(vec![transfer(1), transfer(2)], None).unwrap();
+    assert_eq!(blockchain.chain[1].transactions.len(), 1);
+    assert_eq!(blockchain.get_balance(&receiver), Amount::new(10));
+}
+
+#[test]
+fn test_memo_fee_is_burned_and_memos_are_searchable() {
+    let mut blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    let receiver = Wallet::new().address;
+    blockchain.add_funds(&wallet.address, Amount::new(100)).unwrap();
+    let chain_id = blockchain.chain_id().to_string();
+    let invoice = |amount: u64, memo: &str| Transaction::new(&chain_id, &receiver, Amount::new(amount), &wallet.private_key).with_memo(memo, &wallet.private_key);
+
+    // Ten bytes of memo at the default fee of one per byte
+    assert!(blockchain.validate_transaction(&invoice(91, "INV-000001")).is_err(), "The fee counts against the balance");
+    assert!(blockchain.validate_transaction(&invoice(1, &"x".repeat(MAX_MEMO_BYTES + 1))).is_err());
+    let bond = Transaction::bond(&chain_id, Amount::new(10), &wallet.private_key).with_memo("note", &wallet.private_key);
+    assert!(blockchain.validate_transaction(&bond).is_err(), "Only transfers carry memos");
+
+    blockchain.mine_block(vec![invoice(90, "INV-000001")], None).unwrap();
+    assert_eq!(blockchain.get_balance(&wallet.address), Amount::ZERO);
+    assert_eq!(blockchain.get_balance(&receiver), Amount::new(90));
+
+    let found = blockchain.search_memos("INV-0000");
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].0, 1);
+    assert_eq!(found[0].1.memo.as_deref(), Some("INV-000001"));
+    assert!(blockchain.search_memos("INV-9").is_empty());
+    assert_eq!(blockchain.get_transaction(&found[0].1.id()).map(|(block, _)| block), Some(1));
+}
+
+fn pay(receiver: &address::Address, amount: u64) -> BatchOutput {
+    BatchOutput { receiver: receiver.clone(), amount: Amount::new(amount) }
+}
+
+#[test]
+fn test_batch_transfer_pays_every_output() {
+    let mut blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    let (alice, bob) = (Wallet::new().address, Wallet::new().address);
+    blockchain.add_funds(&wallet.address, Amount::new(100)).unwrap();
+
+    let payroll = Transaction::batch(blockchain.chain_id(), vec![pay(&alice, 30), pay(&bob, 20), pay(&alice, 5), pay(&wallet.address, 10)], &wallet.private_key)
+        .unwrap()
+        .with_memo("2026-10", &wallet.private_key);
+    assert_eq!(payroll.amount, Amount::new(65));
+    blockchain.mine_block(vec![payroll], None).unwrap();
+
+    assert_eq!(blockchain.chain[1].transactions.len(), 1);
+    assert_eq!(blockchain.get_balance(&alice), Amount::new(35));
+    assert_eq!(blockchain.get_balance(&bob), Amount::new(20));
+    // 100 - 65 + 10 paid back to itself - 7 bytes of memo fee
+    assert_eq!(blockchain.get_balance(&wallet.address), Amount::new(38));
+}
+
+#[test]
+fn test_batch_transfer_is_all_or_none() {
+    let mut blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    let (alice, whale) = (Wallet::new().address, Wallet::new().address);
+    blockchain.add_funds(&wallet.address, Amount::new(100)).unwrap();
+    blockchain.add_funds(&whale, Amount::MAX.checked_sub(Amount::new(5)).unwrap()).unwrap();
+    let chain_id = blockchain.chain_id().to_string();
+    let batch = |outputs: Vec<BatchOutput>| Transaction::batch(&chain_id, outputs, &wallet.private_key).unwrap();
+
+    // The whale can take either payment but not both, so alice is not paid either
+    let overflowing = batch(vec![pay(&alice, 10), pay(&whale, 3), pay(&whale, 3)]);
+    assert!(blockchain.validate_transaction(&overflowing).is_err());
+    blockchain.mine_block(vec![overflowing], None).unwrap();
+    assert!(blockchain.chain[1].transactions.is_empty());
+    assert_eq!(blockchain.get_balance(&wallet.address), Amount::new(100));
+    assert_eq!(blockchain.get_balance(&alice), Amount::ZERO);
+
+    assert!(blockchain.validate_transaction(&batch(vec![pay(&alice, 60), pay(&alice, 41)])).is_err(), "The total must be covered");
+    assert!(blockchain.validate_transaction(&batch(vec![])).is_err());
+    assert!(blockchain.validate_transaction(&batch(vec![pay(&alice, 1); MAX_BATCH_OUTPUTS + 1])).is_err());
+    assert!(blockchain.validate_transaction(&batch(vec![pay(&alice, 0)])).is_err());
+    assert!(Transaction::batch(&chain_id, vec![pay(&alice, u64::MAX), pay(&alice, 1)], &wallet.private_key).is_err());
+
+    let mut understated = batch(vec![pay(&alice, 60), pay(&alice, 40)]);
+    understated.amount = Amount::new(10);
+    understated.sign(&wallet.private_key);
+    assert!(blockchain.validate_transaction(&understated).is_err(), "The signed amount must equal the output total");
+    assert!(blockchain.validate_transaction(&batch(vec![pay(&alice, 60), pay(&alice, 40)])).is_ok());
+}
+
+#[test]
+fn test_script_accounts_spend_with_unlocking_data() {
+    let (alice, bob) = (Wallet::new(), Wallet::new());
+    let locking: script::Script = format!("02 {} {} 02 checkmultisig", alice.public_key, bob.public_key).parse().unwrap();
+    let mut blockchain = Blockchain::new();
+    blockchain.add_funds(&locking.address(), Amount::new(100)).unwrap();
+    let chain_id = blockchain.chain_id().to_string();
+
+    let mut tx = Transaction::from_script(&chain_id, locking.clone(), TransactionKind::Transfer, &bob.address, Amount::new(40));
+    assert!(!tx.verify(&chain_id), "Nothing unlocks the script yet");
+    let message = tx.signing_message();
+    let signatures = [&alice, &bob].map(|wallet| crypto::sign(SignatureScheme::Ecdsa, &message, &wallet.private_key));
+    tx.script.as_mut().unwrap().unlocking = signatures[0].parse().unwrap();
+    assert!(blockchain.validate_transaction(&tx).is_err(), "One of two signatures");
+    tx.script.as_mut().unwrap().unlocking = signatures.join(" ").parse().unwrap();
+    assert!(tx.verify(&chain_id));
+    assert_eq!(tx.id(), Transaction { script: Some(script::ScriptAuth { locking: locking.clone(), unlocking: Default::default() }), ..tx.clone() }.id());
+
+    let mut redirected = tx.clone();
+    redirected.receiver = alice.address.clone();
+    assert!(!redirected.verify(&chain_id));
+    let mut other_script = tx.clone();
+    other_script.script.as_mut().unwrap().locking = format!("02 {} {} 02 checkmultisig", bob.public_key, alice.public_key).parse().unwrap();
+    assert!(!other_script.verify(&chain_id), "The locking script must hash to the sender");
+
+    blockchain.mine_block(vec![tx], None).unwrap();
+    assert_eq!(blockchain.get_balance(&locking.address()), Amount::new(60));
+    assert_eq!(blockchain.get_balance(&bob.address), Amount::new(40));
+
+    let mut bond = Transaction::from_script(&chain_id, locking.clone(), TransactionKind::Bond, &locking.address(), Amount::new(10));
+    let message = bond.signing_message();
+    let signatures = [&alice, &bob].map(|wallet| crypto::sign(SignatureScheme::Ecdsa, &message, &wallet.private_key));
+    bond.script.as_mut().unwrap().unlocking = signatures.join(" ").parse().unwrap();
+    assert_eq!(blockchain.validate_transaction(&bond), Err("Script accounts cannot bond stake".to_string()));
+}
@@ -0,0 +1,243 @@
+mod blockchain {
+    include!("../src/blockchain.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod transaction {
+    include!("../src/transaction.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+mod sigcache {
+    include!("../src/sigcache.rs");
+}
+mod staking {
+    include!("../src/staking.rs");
+}
+mod genesis {
+    include!("../src/genesis.rs");
+}
+mod htlc {
+    include!("../src/htlc.rs");
+}
+mod escrow {
+    include!("../src/escrow.rs");
+}
+mod token {
+    include!("../src/token.rs");
+}
+mod nft {
+    include!("../src/nft.rs");
+}
+mod contract {
+    include!("../src/contract.rs");
+}
+mod receipt {
+    include!("../src/receipt.rs");
+}
+
+use address::Address;
+use blockchain::Blockchain;
+use block::Block;
+use contract::MAX_CONTRACT_GAS;
+use transaction::{Gas, Transaction};
+use types::Amount;
+use wallet::Wallet;
+
+const GAS: Gas = Gas { limit: 1_000_000, price: Amount::ZERO };
+
+fn code(wat: &str) -> String {
+    hex::encode(wat::parse_str(wat).unwrap())
+}
+
+fn counter() -> String {
+    code(include_str!("contracts/counter.wat"))
+}
+
+fn token() -> String {
+    code(include_str!("contracts/token.wat"))
+}
+
+/// Deploys `code` from `creator` in the next block and returns the contract address.
+fn deploy(blockchain: &mut Blockchain, creator: &Wallet, code: &str, input: &str) -> Address {
+    let deploy = Transaction::contract_deploy(blockchain.chain_id(), code, input, Amount::ZERO, GAS, &creator.private_key);
+    let address = contract::contract_address(&deploy.id());
+    blockchain.mine_block(vec![deploy], None).unwrap();
+    assert!(blockchain.contracts.contains(&address), "Deploy was dropped");
+    address
+}
+
+/// A call numbered as the caller's next transaction.
+fn call(blockchain: &Blockchain, caller: &Wallet, contract: &Address, method: &str, input: &str, amount: u64) -> Transaction {
+    Transaction::contract_call(blockchain.chain_id(), contract, method, input, Amount::new(amount), GAS, &caller.private_key)
+        .with_nonce(blockchain.next_nonce(&caller.address, &[]), &caller.private_key)
+}
+
+/// Output of a read-only call, read as a little-endian integer.
+fn query(blockchain: &Blockchain, contract: &Address, method: &str, input: &str) -> u64 {
+    let outcome = blockchain.query_contract(contract, method, input, contract, GAS.limit).unwrap();
+    u64::from_le_bytes(outcome.output.try_into().unwrap())
+}
+
+/// Token contract input moving `amount` to `receiver`.
+fn transfer_input(amount: u64, receiver: &Address) -> String {
+    hex::encode([&amount.to_le_bytes()[..], receiver.as_str().as_bytes()].concat())
+}
+
+#[test]
+fn test_counter_keeps_its_count() {
+    let mut blockchain = Blockchain::new();
+    let creator = Wallet::new();
+    let address = deploy(&mut blockchain, &creator, &counter(), "");
+    let deployed = blockchain.contracts.get(&address).unwrap();
+    assert_eq!(deployed.creator, creator.address);
+    assert!(deployed.storage.is_empty(), "The counter has no init");
+    assert_eq!(query(&blockchain, &address, "get", ""), 0);
+
+    let user = Wallet::new();
+    let increments = vec![call(&blockchain, &user, &address, "increment", "", 0), call(&blockchain, &creator, &address, "increment", "", 0)];
+    blockchain.mine_block(increments, None).unwrap();
+    assert_eq!(blockchain.chain[2].transactions.len(), 2);
+    assert_eq!(query(&blockchain, &address, "get", ""), 2);
+    assert_eq!(blockchain.contracts.get(&address).unwrap().storage.get(&hex::encode("count")), Some(&hex::encode(2u64.to_le_bytes())));
+    // Queries run as part of the next block
+    assert_eq!(query(&blockchain, &address, "height", ""), 3);
+
+    let reloaded: Blockchain = serde_json::from_str(&serde_json::to_string(&blockchain).unwrap()).unwrap();
+    assert_eq!(reloaded.contracts, blockchain.contracts);
+    assert_eq!(query(&reloaded, &address, "get", ""), 2);
+}
+
+#[test]
+fn test_token_contract_transfers() {
+    let mut blockchain = Blockchain::new();
+    let (issuer, holder) = (Wallet::new(), Wallet::new());
+    let address = deploy(&mut blockchain, &issuer, &token(), &hex::encode(1_000u64.to_le_bytes()));
+    let balance_of = |blockchain: &Blockchain, wallet: &Wallet| query(blockchain, &address, "balance_of", &hex::encode(wallet.address.as_str()));
+    assert_eq!(balance_of(&blockchain, &issuer), 1_000);
+
+    let transfer = call(&blockchain, &issuer, &address, "transfer", &transfer_input(300, &holder.address), 0);
+    blockchain.mine_block(vec![transfer], None).unwrap();
+    assert_eq!(balance_of(&blockchain, &issuer), 700);
+    assert_eq!(balance_of(&blockchain, &holder), 300);
+
+    // The contract traps on an overdraft, so nothing it wrote is kept
+    let overdraft = call(&blockchain, &holder, &address, "transfer", &transfer_input(301, &issuer.address), 0);
+    let error = blockchain.validate_transaction(&overdraft).unwrap_err();
+    assert!(error.starts_with("Contract execution failed"), "{}", error);
+    // A block still includes it, as a failure that only pays for its gas
+    let id = overdraft.id();
+    blockchain.mine_block(vec![overdraft], None).unwrap();
+    assert_eq!(blockchain.chain[3].transactions.len(), 1);
+    assert!(!blockchain.receipts.get(&id).unwrap().succeeded());
+    assert_eq!(balance_of(&blockchain, &holder), 300);
+}
+
+#[test]
+fn test_contract_moves_native_funds() {
+    let mut blockchain = Blockchain::new();
+    let (issuer, buyer) = (Wallet::new(), Wallet::new());
+    blockchain.add_funds(&buyer.address, Amount::new(100)).unwrap();
+    let address = deploy(&mut blockchain, &issuer, &token(), &hex::encode(0u64.to_le_bytes()));
+
+    blockchain.mine_block(vec![call(&blockchain, &buyer, &address, "buy", "", 60)], None).unwrap();
+    assert_eq!(blockchain.get_balance(&buyer.address), Amount::new(40));
+    assert_eq!(blockchain.get_balance(&address), Amount::new(60));
+    assert_eq!(query(&blockchain, &address, "balance_of", &hex::encode(buyer.address.as_str())), 60);
+
+    let redeem = call(&blockchain, &buyer, &address, "redeem", &hex::encode(25u64.to_le_bytes()), 0);
+    blockchain.mine_block(vec![redeem], None).unwrap();
+    assert_eq!(blockchain.get_balance(&buyer.address), Amount::new(65));
+    assert_eq!(blockchain.get_balance(&address), Amount::new(35));
+    assert_eq!(query(&blockchain, &address, "balance_of", &hex::encode(buyer.address.as_str())), 35);
+
+    let overpaid = call(&blockchain, &buyer, &address, "buy", "", 66);
+    assert_eq!(blockchain.validate_transaction(&overpaid), Err("Insufficient funds".to_string()));
+}
+
+#[test]
+fn test_gas_is_metered() {
+    let mut blockchain = Blockchain::new();
+    let creator = Wallet::new();
+    let address = deploy(&mut blockchain, &creator, &counter(), "");
+
+    let get = blockchain.query_contract(&address, "get", "", &creator.address, GAS.limit).unwrap();
+    assert!(get.gas_used > 0);
+    assert_eq!(blockchain.query_contract(&address, "get", "", &creator.address, GAS.limit).unwrap().gas_used, get.gas_used, "Gas is deterministic");
+    let increment = blockchain.query_contract(&address, "increment", "", &creator.address, GAS.limit).unwrap();
+    assert!(increment.gas_used > get.gas_used, "Storage writes cost more than reads");
+    assert!(blockchain.query_contract(&address, "get", "", &creator.address, get.gas_used - 1).is_err());
+
+    let spin = call(&blockchain, &creator, &address, "spin", "", 0);
+    assert_eq!(blockchain.validate_transaction(&spin), Err("Out of gas".to_string()));
+    let unbounded = Transaction::contract_call(blockchain.chain_id(), &address, "get", "", Amount::ZERO, Gas { limit: MAX_CONTRACT_GAS + 1, ..GAS }, &creator.private_key);
+    assert!(blockchain.validate_transaction(&unbounded).is_err());
+    // Storing the code is paid for too
+    let cheap = Transaction::contract_deploy(blockchain.chain_id(), &counter(), "", Amount::ZERO, Gas { limit: 1_000, ..GAS }, &creator.private_key).with_nonce(1, &creator.private_key);
+    assert_eq!(blockchain.validate_transaction(&cheap), Err("Out of gas".to_string()));
+}
+
+#[test]
+fn test_contract_rules() {
+    let mut blockchain = Blockchain::new();
+    let creator = Wallet::new();
+    let chain_id = blockchain.chain_id().to_string();
+    let address = deploy(&mut blockchain, &creator, &token(), &hex::encode(10u64.to_le_bytes()));
+
+    let garbage = Transaction::contract_deploy(&chain_id, "0061736d", "", Amount::ZERO, GAS, &creator.private_key);
+    assert!(blockchain.validate_transaction(&garbage).unwrap_err().starts_with("Invalid contract code"));
+    let floats = code(r#"(module (func (export "init") (drop (f32.add (f32.const 1) (f32.const 2)))))"#);
+    let floats = Transaction::contract_deploy(&chain_id, &floats, "", Amount::ZERO, GAS, &creator.private_key);
+    assert!(blockchain.validate_transaction(&floats).is_err(), "Floats are not deterministic");
+    let endowed = Transaction::contract_deploy(&chain_id, &counter(), "", Amount::new(1), GAS, &creator.private_key);
+    assert_eq!(blockchain.validate_transaction(&endowed), Err("Insufficient funds".to_string()));
+    let redeploy = blockchain.chain[1].transactions[0].clone();
+    assert_eq!(blockchain.validate_transaction(&redeploy), Err("This contract was already deployed".to_string()));
+
+    let reinit = call(&blockchain, &creator, &address, "init", &hex::encode(10u64.to_le_bytes()), 0);
+    assert!(blockchain.validate_transaction(&reinit).is_err());
+    assert!(blockchain.query_contract(&address, "init", "", &creator.address, GAS.limit).is_err());
+    let unknown_method = call(&blockchain, &creator, &address, "mint", "", 0);
+    assert_eq!(blockchain.validate_transaction(&unknown_method), Err("Contract has no method mint".to_string()));
+    let not_a_contract = call(&blockchain, &creator, &Wallet::new().address, "buy", "", 0);
+    assert!(blockchain.validate_transaction(&not_a_contract).unwrap_err().starts_with("No contract"));
+}
+
+#[test]
+fn test_rejected_block_leaves_contracts_untouched() {
+    let mut blockchain = Blockchain::new();
+    let creator = Wallet::new();
+    let address = deploy(&mut blockchain, &creator, &counter(), "");
+    let chain_id = blockchain.chain_id().to_string();
+    let transactions = vec![
+        call(&blockchain, &creator, &address, "increment", "", 0),
+        Transaction::new(&chain_id, &Wallet::new().address, Amount::new(1_000), &creator.private_key).with_nonce(2, &creator.private_key),
+    ];
+    let tip = blockchain.chain.last().unwrap().hash.clone();
+    let mut block = Block::new(&chain_id, 2, transactions, tip);
+    block.timestamp = blockchain.chain[1].timestamp;
+    block.hash = block.calculate_hash();
+    assert!(blockchain.add_block(block).is_err());
+    assert!(blockchain.contracts.get(&address).unwrap().storage.is_empty());
+    assert_eq!(query(&blockchain, &address, "get", ""), 0);
+}
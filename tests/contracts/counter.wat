@@ -0,0 +1,30 @@
+;; A counter stored under the key "count" as an 8 byte little-endian integer.
+(module
+  (import "env" "storage_get" (func $storage_get (param i32 i32 i32 i32) (result i32)))
+  (import "env" "storage_set" (func $storage_set (param i32 i32 i32 i32)))
+  (import "env" "output" (func $output (param i32 i32)))
+  (import "env" "block_height" (func $block_height (result i64)))
+  (memory (export "memory") 1)
+  ;; The key at 0, the value at 16
+  (data (i32.const 0) "count")
+
+  (func $load (result i64)
+    (if (i32.lt_s (call $storage_get (i32.const 0) (i32.const 5) (i32.const 16) (i32.const 8)) (i32.const 0))
+      (then (i64.store (i32.const 16) (i64.const 0))))
+    (i64.load (i32.const 16)))
+
+  (func (export "increment")
+    (i64.store (i32.const 16) (i64.add (call $load) (i64.const 1)))
+    (call $storage_set (i32.const 0) (i32.const 5) (i32.const 16) (i32.const 8)))
+
+  (func (export "get")
+    (i64.store (i32.const 16) (call $load))
+    (call $output (i32.const 16) (i32.const 8)))
+
+  (func (export "height")
+    (i64.store (i32.const 16) (call $block_height))
+    (call $output (i32.const 16) (i32.const 8)))
+
+  ;; Never returns, to run out of gas
+  (func (export "spin")
+    (loop $forever (br $forever))))
@@ -0,0 +1,65 @@
+;; A fungible token: balances are stored under the holder's address as 8 byte
+;; little-endian integers. Native funds sent to `buy` mint tokens one for one, and
+;; `redeem` burns tokens to pay the native funds back.
+(module
+  (import "env" "caller" (func $caller (param i32) (result i32)))
+  (import "env" "value" (func $value (result i64)))
+  (import "env" "input_len" (func $input_len (result i32)))
+  (import "env" "input" (func $input (param i32)))
+  (import "env" "output" (func $output (param i32 i32)))
+  (import "env" "storage_get" (func $storage_get (param i32 i32 i32 i32) (result i32)))
+  (import "env" "storage_set" (func $storage_set (param i32 i32 i32 i32)))
+  (import "env" "transfer" (func $transfer (param i32 i32 i64)))
+  (memory (export "memory") 1)
+  ;; The caller's address at 0, the input at 64 and balances at 128
+
+  (func $balance (param $holder i32) (param $len i32) (result i64)
+    (if (i32.lt_s (call $storage_get (local.get $holder) (local.get $len) (i32.const 128) (i32.const 8)) (i32.const 0))
+      (then (return (i64.const 0))))
+    (i64.load (i32.const 128)))
+
+  (func $set_balance (param $holder i32) (param $len i32) (param $amount i64)
+    (i64.store (i32.const 128) (local.get $amount))
+    (call $storage_set (local.get $holder) (local.get $len) (i32.const 128) (i32.const 8)))
+
+  ;; Takes `amount` from the caller, trapping if the caller holds less
+  (func $debit_caller (param $amount i64) (result i32)
+    (local $len i32)
+    (local $balance i64)
+    (local.set $len (call $caller (i32.const 0)))
+    (local.set $balance (call $balance (i32.const 0) (local.get $len)))
+    (if (i64.gt_u (local.get $amount) (local.get $balance))
+      (then unreachable))
+    (call $set_balance (i32.const 0) (local.get $len) (i64.sub (local.get $balance) (local.get $amount)))
+    (local.get $len))
+
+  (func $credit (param $holder i32) (param $len i32) (param $amount i64)
+    (call $set_balance (local.get $holder) (local.get $len)
+      (i64.add (call $balance (local.get $holder) (local.get $len)) (local.get $amount))))
+
+  ;; Input: the initial supply, all of it held by the deployer
+  (func (export "init")
+    (call $input (i32.const 64))
+    (call $credit (i32.const 0) (call $caller (i32.const 0)) (i64.load (i32.const 64))))
+
+  ;; Input: the amount followed by the receiver's address
+  (func (export "transfer")
+    (call $input (i32.const 64))
+    (drop (call $debit_caller (i64.load (i32.const 64))))
+    (call $credit (i32.const 72) (i32.sub (call $input_len) (i32.const 8)) (i64.load (i32.const 64))))
+
+  ;; Input: the holder's address
+  (func (export "balance_of")
+    (call $input (i32.const 64))
+    (i64.store (i32.const 136) (call $balance (i32.const 64) (call $input_len)))
+    (call $output (i32.const 136) (i32.const 8)))
+
+  (func (export "buy")
+    (call $credit (i32.const 0) (call $caller (i32.const 0)) (call $value)))
+
+  ;; Input: the amount to burn and be paid back
+  (func (export "redeem")
+    (local $len i32)
+    (call $input (i32.const 64))
+    (local.set $len (call $debit_caller (i64.load (i32.const 64))))
+    (call $transfer (i32.const 0) (local.get $len) (i64.load (i32.const 64)))))
@@ -0,0 +1,194 @@
+mod blockchain {
+    include!("../src/blockchain.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod transaction {
+    include!("../src/transaction.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+mod sigcache {
+    include!("../src/sigcache.rs");
+}
+mod staking {
+    include!("../src/staking.rs");
+}
+mod genesis {
+    include!("../src/genesis.rs");
+}
+mod htlc {
+    include!("../src/htlc.rs");
+}
+mod escrow {
+    include!("../src/escrow.rs");
+}
+mod token {
+    include!("../src/token.rs");
+}
+mod nft {
+    include!("../src/nft.rs");
+}
+mod contract {
+    include!("../src/contract.rs");
+}
+mod receipt {
+    include!("../src/receipt.rs");
+}
+
+use blockchain::Blockchain;
+use block::Block;
+use escrow::{EscrowDecision, EscrowStatus};
+use transaction::Transaction;
+use types::{Amount, Hash};
+use wallet::Wallet;
+
+struct Parties {
+    payer: Wallet,
+    payee: Wallet,
+    arbiter: Wallet,
+}
+
+/// Escrows 40 of the payer's 100 for the payee, mined in block 1.
+fn escrowed(blockchain: &mut Blockchain) -> (Parties, Hash) {
+    let parties = Parties { payer: Wallet::new(), payee: Wallet::new(), arbiter: Wallet::new() };
+    blockchain.add_funds(&parties.payer.address, Amount::new(100)).unwrap();
+    let create = Transaction::escrow_create(blockchain.chain_id(), &parties.payee.address, &parties.arbiter.address, Amount::new(40), &parties.payer.private_key);
+    let id = create.id();
+    blockchain.mine_block(vec![create], None).unwrap();
+    (parties, id)
+}
+
+/// An approval to pay the payee, numbered as the signer's next transaction.
+fn release(blockchain: &Blockchain, id: &Hash, parties: &Parties, signer: &Wallet) -> Transaction {
+    Transaction::escrow_release(blockchain.chain_id(), id.clone(), &parties.payee.address, Amount::new(40), &signer.private_key)
+        .with_nonce(blockchain.next_nonce(&signer.address, &[]), &signer.private_key)
+}
+
+/// An approval to refund the payer, numbered as the signer's next transaction.
+fn refund(blockchain: &Blockchain, id: &Hash, parties: &Parties, signer: &Wallet) -> Transaction {
+    Transaction::escrow_refund(blockchain.chain_id(), id.clone(), &parties.payer.address, Amount::new(40), &signer.private_key)
+        .with_nonce(blockchain.next_nonce(&signer.address, &[]), &signer.private_key)
+}
+
+#[test]
+fn test_payer_and_payee_release() {
+    let mut blockchain = Blockchain::new();
+    let (parties, id) = escrowed(&mut blockchain);
+    assert_eq!(blockchain.get_balance(&parties.payer.address), Amount::new(60));
+    let escrow = blockchain.escrows.get(&id).unwrap();
+    assert_eq!((&escrow.payee, &escrow.arbiter), (&parties.payee.address, &parties.arbiter.address));
+    assert_eq!(escrow.status, EscrowStatus::Open);
+
+    blockchain.mine_block(vec![release(&blockchain, &id, &parties, &parties.payer)], None).unwrap();
+    assert_eq!(blockchain.get_balance(&parties.payee.address), Amount::ZERO, "One approval is not enough");
+    assert_eq!(blockchain.escrows.get(&id).unwrap().approvals.get(&parties.payer.address), Some(&EscrowDecision::Release));
+    assert!(blockchain.validate_transaction(&release(&blockchain, &id, &parties, &parties.payer)).is_err(), "The payer approved already");
+
+    blockchain.mine_block(vec![release(&blockchain, &id, &parties, &parties.payee)], None).unwrap();
+    assert_eq!(blockchain.get_balance(&parties.payee.address), Amount::new(40));
+    assert_eq!(blockchain.escrows.get(&id).unwrap().status, EscrowStatus::Released { height: 3 });
+    assert!(blockchain.validate_transaction(&refund(&blockchain, &id, &parties, &parties.arbiter)).is_err(), "Settled escrows stay settled");
+}
+
+#[test]
+fn test_arbiter_sides_with_the_payer() {
+    let mut blockchain = Blockchain::new();
+    let (parties, id) = escrowed(&mut blockchain);
+    // The payee wants the funds, but the payer and arbiter agree to refund
+    let votes = vec![
+        release(&blockchain, &id, &parties, &parties.payee),
+        refund(&blockchain, &id, &parties, &parties.payer),
+        refund(&blockchain, &id, &parties, &parties.arbiter),
+        release(&blockchain, &id, &parties, &parties.arbiter).with_nonce(1, &parties.arbiter.private_key),
+    ];
+    blockchain.mine_block(votes, None).unwrap();
+    assert_eq!(blockchain.chain[2].transactions.len(), 3, "The escrow settled before the last vote");
+    assert_eq!(blockchain.get_balance(&parties.payer.address), Amount::new(100));
+    assert_eq!(blockchain.get_balance(&parties.payee.address), Amount::ZERO);
+    assert_eq!(blockchain.escrows.get(&id).unwrap().status, EscrowStatus::Refunded { height: 2 });
+}
+
+#[test]
+fn test_parties_can_change_their_approval() {
+    let mut blockchain = Blockchain::new();
+    let (parties, id) = escrowed(&mut blockchain);
+    blockchain.mine_block(vec![refund(&blockchain, &id, &parties, &parties.arbiter)], None).unwrap();
+    blockchain.mine_block(vec![release(&blockchain, &id, &parties, &parties.arbiter)], None).unwrap();
+    // The arbiter's refund was replaced, so the payer alone cannot refund
+    blockchain.mine_block(vec![refund(&blockchain, &id, &parties, &parties.payer)], None).unwrap();
+    assert!(blockchain.escrows.get(&id).unwrap().is_open());
+    blockchain.mine_block(vec![release(&blockchain, &id, &parties, &parties.payee)], None).unwrap();
+    assert_eq!(blockchain.get_balance(&parties.payee.address), Amount::new(40));
+    assert_eq!(blockchain.get_balance(&parties.payer.address), Amount::new(60));
+}
+
+#[test]
+fn test_escrow_rules() {
+    let mut blockchain = Blockchain::new();
+    let (parties, id) = escrowed(&mut blockchain);
+    let chain_id = blockchain.chain_id().to_string();
+    let outsider = Wallet::new();
+
+    let meddling = release(&blockchain, &id, &parties, &outsider);
+    assert_eq!(blockchain.validate_transaction(&meddling), Err("Only the payer, payee or arbiter can settle an escrow".to_string()));
+    let redirected = Transaction::escrow_release(&chain_id, id.clone(), &parties.arbiter.address, Amount::new(40), &parties.arbiter.private_key);
+    assert!(blockchain.validate_transaction(&redirected).is_err(), "A release pays the payee");
+    let inflated = Transaction::escrow_refund(&chain_id, id.clone(), &parties.payer.address, Amount::new(41), &parties.payer.private_key);
+    assert!(blockchain.validate_transaction(&inflated).is_err());
+    let unknown = release(&blockchain, &Hash::digest("unknown"), &parties, &parties.payer);
+    assert!(blockchain.validate_transaction(&unknown).is_err());
+
+    let payer = &parties.payer;
+    let zero = Transaction::escrow_create(&chain_id, &parties.payee.address, &parties.arbiter.address, Amount::ZERO, &payer.private_key);
+    assert!(blockchain.validate_transaction(&zero).is_err());
+    let self_arbitrated = Transaction::escrow_create(&chain_id, &parties.payee.address, &payer.address, Amount::new(10), &payer.private_key);
+    assert_eq!(blockchain.validate_transaction(&self_arbitrated), Err("Payer, payee and arbiter of an escrow must be different".to_string()));
+    let overdrawn = Transaction::escrow_create(&chain_id, &parties.payee.address, &parties.arbiter.address, Amount::new(61), &payer.private_key);
+    assert_eq!(blockchain.validate_transaction(&overdrawn), Err("Insufficient funds".to_string()));
+
+    let create = blockchain.chain[1].transactions[0].clone();
+    assert_eq!(blockchain.validate_transaction(&create), Err("This escrow was already created".to_string()));
+}
+
+#[test]
+fn test_rejected_block_leaves_escrows_untouched() {
+    let mut blockchain = Blockchain::new();
+    let (parties, id) = escrowed(&mut blockchain);
+    let chain_id = blockchain.chain_id().to_string();
+    let votes = vec![
+        release(&blockchain, &id, &parties, &parties.payer),
+        release(&blockchain, &id, &parties, &parties.arbiter),
+        Transaction::new(&chain_id, &parties.payee.address, Amount::new(1_000), &parties.payer.private_key).with_nonce(2, &parties.payer.private_key),
+    ];
+    let tip = blockchain.chain.last().unwrap().hash.clone();
+    let mut block = Block::new(&chain_id, 2, votes, tip);
+    block.timestamp = blockchain.chain[1].timestamp;
+    block.hash = block.calculate_hash();
+    assert!(blockchain.add_block(block).is_err());
+    assert!(blockchain.escrows.get(&id).unwrap().approvals.is_empty());
+    assert_eq!(blockchain.get_balance(&parties.payee.address), Amount::ZERO);
+
+    let reloaded: Blockchain = serde_json::from_str(&serde_json::to_string(&blockchain).unwrap()).unwrap();
+    assert_eq!(reloaded.escrows, blockchain.escrows);
+}
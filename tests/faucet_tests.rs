@@ -0,0 +1,178 @@
+mod blockchain {
+    include!("../src/blockchain.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod transaction {
+    include!("../src/transaction.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+mod sigcache {
+    include!("../src/sigcache.rs");
+}
+mod staking {
+    include!("../src/staking.rs");
+}
+mod genesis {
+    include!("../src/genesis.rs");
+}
+mod htlc {
+    include!("../src/htlc.rs");
+}
+mod escrow {
+    include!("../src/escrow.rs");
+}
+mod token {
+    include!("../src/token.rs");
+}
+mod nft {
+    include!("../src/nft.rs");
+}
+mod contract {
+    include!("../src/contract.rs");
+}
+mod receipt {
+    include!("../src/receipt.rs");
+}
+mod faucet {
+    include!("../src/faucet.rs");
+}
+
+use blockchain::Blockchain;
+use faucet::{FaucetLimiter, SECONDS_PER_DAY};
+use genesis::{FaucetConfig, GenesisConfig};
+use transaction::Transaction;
+use types::Amount;
+use address::Address;
+use wallet::Wallet;
+
+fn faucet_config(address: &Address) -> FaucetConfig {
+    FaucetConfig {
+        address: address.clone(),
+        amount: Amount::new(100),
+        cooldown_secs: 60,
+        daily_cap: Amount::new(250),
+    }
+}
+
+fn dev_chain(faucet: &Wallet) -> Blockchain {
+    Blockchain::from_genesis(GenesisConfig {
+        faucet: Some(faucet_config(&faucet.address)),
+        ..GenesisConfig::default()
+    })
+}
+
+/// A grant of `amount` to `receiver` that expires at height 10.
+fn grant(blockchain: &Blockchain, faucet: &Wallet, receiver: &Address, amount: u64) -> Transaction {
+    Transaction::mint(blockchain.chain_id(), receiver, Amount::new(amount), &faucet.private_key).expiring_at(10, &faucet.private_key)
+}
+
+#[test]
+fn test_mint_is_recorded_on_chain() {
+    let faucet = Wallet::new();
+    let mut blockchain = dev_chain(&faucet);
+    let alice = Wallet::new().address;
+    let mint = grant(&blockchain, &faucet, &alice, 100);
+
+    blockchain.mine_block(vec![mint.clone()], None).unwrap();
+    assert_eq!(blockchain.get_balance(&alice), Amount::new(100));
+    assert_eq!(blockchain.get_balance(&faucet.address), Amount::ZERO);
+    assert_eq!(blockchain.chain[1].transactions, vec![mint.clone()]);
+
+    // A past grant cannot be submitted again to get around the limiter
+    assert_eq!(blockchain.validate_transaction(&mint), Err("Expected nonce 1, got 0".to_string()));
+    blockchain.mine_block(vec![mint], None).unwrap();
+    assert_eq!(blockchain.get_balance(&alice), Amount::new(100));
+}
+
+#[test]
+fn test_mint_rules() {
+    let faucet = Wallet::new();
+    let blockchain = dev_chain(&faucet);
+    let impostor = Wallet::new();
+    let alice = Wallet::new().address;
+
+    let forged = grant(&blockchain, &impostor, &alice, 100);
+    assert_eq!(blockchain.validate_transaction(&forged), Err("Only the faucet account can mint".to_string()));
+
+    let oversized = grant(&blockchain, &faucet, &alice, 101);
+    assert!(blockchain.validate_transaction(&oversized).is_err());
+    let unbounded = Transaction::mint(blockchain.chain_id(), &alice, Amount::new(100), &faucet.private_key);
+    assert_eq!(blockchain.validate_transaction(&unbounded), Err("Faucet grants must set an expiry height".to_string()));
+}
+
+#[test]
+fn test_faucet_only_on_dev_networks() {
+    let faucet = Wallet::new();
+    let mainnet = GenesisConfig {
+        chain_id: "chainrust-main".to_string(),
+        faucet: Some(faucet_config(&faucet.address)),
+        ..GenesisConfig::default()
+    };
+    assert!(!mainnet.is_dev());
+    assert!(mainnet.validate().is_err());
+    // A node that skipped validation still refuses the grants
+    let blockchain = Blockchain::from_genesis(mainnet.clone());
+    let mint = grant(&blockchain, &faucet, &Wallet::new().address, 100);
+    assert_eq!(blockchain.validate_transaction(&mint), Err("Faucet is disabled outside dev networks".to_string()));
+
+    let testnet = GenesisConfig { dev: true, ..mainnet };
+    assert!(testnet.validate().is_ok());
+    assert!(Blockchain::from_genesis(testnet).validate_transaction(&mint).is_ok());
+    assert!(GenesisConfig::default().validate().is_ok());
+}
+
+#[test]
+fn test_mint_disabled_without_faucet() {
+    let faucet = Wallet::new();
+    let blockchain = Blockchain::new();
+    let alice = Wallet::new().address;
+    let mint = grant(&blockchain, &faucet, &alice, 100);
+    assert_eq!(blockchain.validate_transaction(&mint), Err("Faucet is disabled on this network".to_string()));
+}
+
+#[test]
+fn test_limiter_cooldown_and_daily_cap() {
+    let config = faucet_config(&Wallet::new().address);
+    let mut limiter = FaucetLimiter::default();
+    let now = 10 * SECONDS_PER_DAY;
+    let (alice, bob) = (Wallet::new().address, Wallet::new().address);
+
+    assert!(limiter.check_and_record(&config, &alice, now).is_ok());
+    assert!(limiter.check_and_record(&config, &alice, now + 59).is_err());
+    assert!(limiter.check_and_record(&config, &alice, now + 60).is_ok());
+
+    // 200 of the 250 cap is used, so a third grant of 100 is refused
+    assert_eq!(limiter.check_and_record(&config, &bob, now + 60), Err("Faucet daily cap reached".to_string()));
+    assert!(limiter.check_and_record(&config, &bob, now + SECONDS_PER_DAY).is_ok());
+}
+
+#[test]
+fn test_dev_genesis_faucet_key() {
+    let genesis = GenesisConfig::load_from_file("genesis.json").unwrap();
+    let faucet = genesis.faucet.expect("The dev genesis configures a faucet");
+    let dev_key = "1ecdf7132ea195bd85c80454654f224845047638257fa6d28d111ad84b411c35";
+    let public_key = crypto::public_key_from_private(dev_key).unwrap();
+    assert_eq!(Address::from_public_key(&public_key), Some(faucet.address));
+}
@@ -0,0 +1,254 @@
+mod blockchain {
+    include!("../src/blockchain.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod transaction {
+    include!("../src/transaction.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+mod sigcache {
+    include!("../src/sigcache.rs");
+}
+mod staking {
+    include!("../src/staking.rs");
+}
+mod genesis {
+    include!("../src/genesis.rs");
+}
+mod htlc {
+    include!("../src/htlc.rs");
+}
+mod escrow {
+    include!("../src/escrow.rs");
+}
+mod token {
+    include!("../src/token.rs");
+}
+mod nft {
+    include!("../src/nft.rs");
+}
+mod contract {
+    include!("../src/contract.rs");
+}
+mod receipt {
+    include!("../src/receipt.rs");
+}
+
+use address::Address;
+use blockchain::Blockchain;
+use block::Block;
+use crypto::SignatureScheme;
+use genesis::{ConsensusParams, GenesisConfig};
+use receipt::ReceiptStatus;
+use transaction::{Gas, Transaction, TransactionKind};
+use types::Amount;
+use wallet::Wallet;
+
+const LIMIT: u64 = 1_000_000;
+
+fn chain(min_gas_price: u64, block_gas_limit: u64) -> Blockchain {
+    Blockchain::from_genesis(GenesisConfig {
+        consensus: ConsensusParams { min_gas_price: Amount::new(min_gas_price), block_gas_limit, ..ConsensusParams::default() },
+        ..GenesisConfig::default()
+    })
+}
+
+fn counter() -> String {
+    hex::encode(wat::parse_str(include_str!("contracts/counter.wat")).unwrap())
+}
+
+fn gas(price: u64) -> Gas {
+    Gas { limit: LIMIT, price: Amount::new(price) }
+}
+
+/// Deploys the counter from `creator` at `price` in the next block and returns its address.
+fn deploy(blockchain: &mut Blockchain, creator: &Wallet, price: u64) -> Address {
+    let deploy = Transaction::contract_deploy(blockchain.chain_id(), &counter(), "", Amount::ZERO, gas(price), &creator.private_key);
+    let address = contract::contract_address(&deploy.id());
+    blockchain.mine_block(vec![deploy], None).unwrap();
+    address
+}
+
+/// A call numbered as the caller's next transaction.
+fn call(blockchain: &Blockchain, caller: &Wallet, contract: &Address, method: &str, amount: u64, gas: Gas) -> Transaction {
+    Transaction::contract_call(blockchain.chain_id(), contract, method, "", Amount::new(amount), gas, &caller.private_key)
+        .with_nonce(blockchain.next_nonce(&caller.address, &[]), &caller.private_key)
+}
+
+/// A block of `transactions` on top of the tip, as another node would have produced it.
+fn foreign_block(blockchain: &Blockchain, transactions: Vec<Transaction>) -> Block {
+    let tip = blockchain.chain.last().unwrap();
+    let mut block = Block::new(blockchain.chain_id(), tip.index + 1, transactions, tip.hash.clone());
+    block.timestamp = tip.timestamp;
+    block.hash = block.calculate_hash();
+    block
+}
+
+#[test]
+fn test_only_used_gas_is_charged() {
+    let mut blockchain = chain(2, 30_000_000);
+    let creator = Wallet::new();
+    blockchain.add_funds(&creator.address, Amount::new(3_000_000)).unwrap();
+    let address = deploy(&mut blockchain, &creator, 2);
+    let deployed = blockchain.receipts.get(&blockchain.chain[1].transactions[0].id()).unwrap().clone();
+    assert!(deployed.succeeded());
+    assert_eq!((deployed.gas_limit, deployed.gas_price, deployed.height), (LIMIT, Amount::new(2), 1));
+    assert_eq!(deployed.fee, Amount::new(deployed.gas_used * 2));
+    assert_eq!(deployed.refund, Amount::new((LIMIT - deployed.gas_used) * 2));
+    assert_eq!(blockchain.get_balance(&creator.address), Amount::new(3_000_000 - deployed.gas_used * 2));
+
+    let before = blockchain.get_balance(&creator.address);
+    let expected = blockchain.query_contract(&address, "increment", "", &creator.address, LIMIT).unwrap().gas_used;
+    let increment = call(&blockchain, &creator, &address, "increment", 0, gas(2));
+    let id = increment.id();
+    blockchain.mine_block(vec![increment], None).unwrap();
+    let receipt = blockchain.receipts.get(&id).unwrap();
+    assert_eq!(receipt.gas_used, expected, "A transaction uses what a query of the same call does");
+    assert_eq!(blockchain.get_balance(&creator.address), before.checked_sub(receipt.fee).unwrap());
+
+    let get = call(&blockchain, &creator, &address, "get", 0, gas(2));
+    let id = get.id();
+    blockchain.mine_block(vec![get], None).unwrap();
+    assert_eq!(blockchain.receipts.get(&id).unwrap().output, hex::encode(1u64.to_le_bytes()));
+
+    let reloaded: Blockchain = serde_json::from_str(&serde_json::to_string(&blockchain).unwrap()).unwrap();
+    assert_eq!(reloaded.receipts, blockchain.receipts);
+}
+
+#[test]
+fn test_failed_call_pays_for_its_gas() {
+    let mut blockchain = chain(1, 30_000_000);
+    let creator = Wallet::new();
+    blockchain.add_funds(&creator.address, Amount::new(2_000_000)).unwrap();
+    let address = deploy(&mut blockchain, &creator, 1);
+    let before = blockchain.get_balance(&creator.address);
+
+    // Validation turns it away, but a block includes it if asked to
+    let spin = call(&blockchain, &creator, &address, "spin", 50, gas(1));
+    assert_eq!(blockchain.validate_transaction(&spin), Err("Out of gas".to_string()));
+    let id = spin.id();
+    blockchain.mine_block(vec![spin], None).unwrap();
+    assert_eq!(blockchain.chain[2].transactions.len(), 1);
+    let receipt = blockchain.receipts.get(&id).unwrap();
+    assert_eq!(receipt.status, ReceiptStatus::Failed { error: "Out of gas".to_string() });
+    assert_eq!((receipt.gas_used, receipt.fee, receipt.refund), (LIMIT, Amount::new(LIMIT), Amount::ZERO));
+    assert_eq!(blockchain.get_balance(&creator.address), before.checked_sub(Amount::new(LIMIT)).unwrap(), "The payment to the contract is not made");
+    assert_eq!(blockchain.get_balance(&address), Amount::ZERO);
+}
+
+#[test]
+fn test_block_gas_limit() {
+    let mut blockchain = chain(0, 2_500_000);
+    let creator = Wallet::new();
+    let address = deploy(&mut blockchain, &creator, 0);
+    let spins: Vec<Transaction> = (0..3)
+        .map(|amount| call(&blockchain, &creator, &address, "spin", 0, Gas { limit: LIMIT + amount, price: Amount::ZERO }).with_nonce(1 + amount, &creator.private_key))
+        .collect();
+
+    let (fit, deferred) = blockchain.fill_block(spins.clone());
+    assert_eq!((fit.len(), deferred.len()), (2, 1));
+    assert_eq!(deferred[0].id(), spins[2].id());
+
+    let block = foreign_block(&blockchain, spins.clone());
+    assert_eq!(blockchain.add_block(block), Err("Block uses more than the block gas limit of 2500000".to_string()));
+    assert!(blockchain.receipts.get(&spins[0].id()).is_none(), "The rejected block left no receipts");
+
+    blockchain.mine_block(spins.clone(), None).unwrap();
+    assert_eq!(blockchain.chain[2].transactions.len(), 2);
+    assert!(blockchain.receipts.get(&spins[2].id()).is_none());
+
+    // A smaller later call would fit, but cannot be mined ahead of the deferred one
+    let caller = Wallet::new();
+    let queued = vec![
+        spins[2].clone(),
+        call(&blockchain, &caller, &address, "spin", 0, Gas { limit: 2 * LIMIT, price: Amount::ZERO }),
+        call(&blockchain, &caller, &address, "spin", 0, Gas { limit: LIMIT, price: Amount::ZERO }).with_nonce(1, &caller.private_key),
+    ];
+    let (fit, deferred) = blockchain.fill_block(queued.clone());
+    assert_eq!(fit.iter().map(Transaction::id).collect::<Vec<_>>(), vec![queued[0].id()]);
+    assert_eq!(deferred.iter().map(Transaction::id).collect::<Vec<_>>(), vec![queued[1].id(), queued[2].id()]);
+
+    let oversized = call(&blockchain, &creator, &address, "get", 0, Gas { limit: 2_500_001, price: Amount::ZERO });
+    assert_eq!(blockchain.validate_transaction(&oversized), Err("Gas limit 2500001 exceeds the block gas limit of 2500000".to_string()));
+}
+
+#[test]
+fn test_gas_rules() {
+    let mut blockchain = chain(2, 30_000_000);
+    let (creator, poor) = (Wallet::new(), Wallet::new());
+    blockchain.add_funds(&creator.address, Amount::new(5_000_000)).unwrap();
+    blockchain.add_funds(&poor.address, Amount::new(100)).unwrap();
+    let address = deploy(&mut blockchain, &creator, 2);
+    let chain_id = blockchain.chain_id().to_string();
+
+    let mut ungassed = call(&blockchain, &creator, &address, "get", 0, gas(2));
+    ungassed.gas = None;
+    ungassed.sign(&creator.private_key);
+    assert_eq!(blockchain.validate_transaction(&ungassed), Err("Contract transactions must set a gas limit and price".to_string()));
+    let cheap = call(&blockchain, &creator, &address, "get", 0, gas(1));
+    assert_eq!(blockchain.validate_transaction(&cheap), Err("Gas price 1 is below the minimum of 2".to_string()));
+    let empty = call(&blockchain, &creator, &address, "get", 0, Gas { limit: 0, price: Amount::new(2) });
+    assert!(blockchain.validate_transaction(&empty).is_err());
+    let unaffordable = call(&blockchain, &poor, &address, "get", 0, gas(2));
+    assert_eq!(blockchain.validate_transaction(&unaffordable), Err("Insufficient funds".to_string()), "The whole limit must be affordable");
+    assert!(blockchain.validate_transaction(&call(&blockchain, &poor, &address, "get", 0, Gas { limit: 50, price: Amount::new(2) })).is_err());
+
+    let transfer = Transaction::new(&chain_id, &poor.address, Amount::new(1), &creator.private_key).with_gas(gas(2), &creator.private_key);
+    assert_eq!(blockchain.validate_transaction(&transfer), Err("Only contract and scripted transactions pay for gas".to_string()));
+    let transfer = Transaction::new(&chain_id, &poor.address, Amount::new(1), &creator.private_key).with_nonce(1, &creator.private_key);
+    assert!(blockchain.validate_transaction(&transfer).is_ok());
+}
+
+#[test]
+fn test_scripts_pay_for_gas() {
+    let mut blockchain = chain(1, 30_000_000);
+    let (alice, bob) = (Wallet::new(), Wallet::new());
+    let locking: script::Script = format!("02 {} {} 02 checkmultisig", alice.public_key, bob.public_key).parse().unwrap();
+    blockchain.add_funds(&locking.address(), Amount::new(10_000)).unwrap();
+    let chain_id = blockchain.chain_id().to_string();
+    let signed = |gas: Option<Gas>| {
+        let mut tx = Transaction::from_script(&chain_id, locking.clone(), TransactionKind::Transfer, &bob.address, Amount::new(40));
+        tx.gas = gas;
+        let message = tx.signing_message();
+        let signatures = [&alice, &bob].map(|wallet| crypto::sign(SignatureScheme::Ecdsa, &message, &wallet.private_key));
+        tx.script.as_mut().unwrap().unlocking = signatures.join(" ").parse().unwrap();
+        tx
+    };
+    // Five operations and two signature checks to lock, two pushes to unlock
+    let script_gas = signed(None).script_gas();
+    assert_eq!(script_gas, 7 * script::GAS_PER_OP + 2 * script::GAS_PER_SIG_OP);
+
+    assert_eq!(blockchain.validate_transaction(&signed(None)), Err("Scripted transactions must pay at least 1 per gas on this chain".to_string()));
+    let short = signed(Some(Gas { limit: script_gas - 1, price: Amount::new(1) }));
+    assert_eq!(blockchain.validate_transaction(&short), Err(format!("Gas limit {} does not cover the {} gas its scripts use", script_gas - 1, script_gas)));
+
+    let tx = signed(Some(Gas { limit: 5_000, price: Amount::new(1) }));
+    let id = tx.id();
+    blockchain.mine_block(vec![tx], None).unwrap();
+    let receipt = blockchain.receipts.get(&id).unwrap();
+    assert_eq!((receipt.gas_used, receipt.refund), (script_gas, Amount::new(5_000 - script_gas)));
+    assert_eq!(blockchain.get_balance(&locking.address()), Amount::new(10_000 - 40 - script_gas));
+    assert_eq!(blockchain.get_balance(&bob.address), Amount::new(40));
+}
@@ -0,0 +1,192 @@
+mod blockchain {
+    include!("../src/blockchain.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod transaction {
+    include!("../src/transaction.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+mod sigcache {
+    include!("../src/sigcache.rs");
+}
+mod staking {
+    include!("../src/staking.rs");
+}
+mod genesis {
+    include!("../src/genesis.rs");
+}
+mod htlc {
+    include!("../src/htlc.rs");
+}
+mod escrow {
+    include!("../src/escrow.rs");
+}
+mod token {
+    include!("../src/token.rs");
+}
+mod nft {
+    include!("../src/nft.rs");
+}
+mod contract {
+    include!("../src/contract.rs");
+}
+mod receipt {
+    include!("../src/receipt.rs");
+}
+
+use blockchain::Blockchain;
+use genesis::GenesisConfig;
+use address::Address;
+use types::Amount;
+use wallet::Wallet;
+
+fn alice() -> Address {
+    Address::from_public_key("02d524421eb3d7d4c8d4e66f536aa00e3760282cd476373e0a7ca7cb73044ce934").unwrap()
+}
+
+fn bob() -> Address {
+    Address::from_public_key("0228ef26a8a89849cd903bac832e35ed9e144714f80a020490bfb6ba3fa5086d46").unwrap()
+}
+
+fn sample_config() -> GenesisConfig {
+    let mut config = GenesisConfig {
+        chain_id: "chainrust-test".to_string(),
+        timestamp: 1_700_000_000,
+        ..GenesisConfig::default()
+    };
+    config.balances.insert(alice(), Amount::new(1_000));
+    config.balances.insert(bob(), Amount::new(500));
+    config
+}
+
+#[test]
+fn test_genesis_hash_is_deterministic() {
+    let first = Blockchain::from_genesis(sample_config());
+    let second = Blockchain::from_genesis(sample_config());
+    assert_eq!(first.genesis_hash(), second.genesis_hash());
+    assert_eq!(first.chain[0].timestamp, 1_700_000_000);
+    assert!(first.is_chain_valid());
+}
+
+#[test]
+fn test_genesis_hash_commits_to_allocations() {
+    let base = sample_config().block().hash;
+
+    let mut more = sample_config();
+    more.balances.insert(Wallet::new().address, Amount::new(1));
+    assert_ne!(more.block().hash, base);
+
+    let mut other_chain = sample_config();
+    other_chain.chain_id = "chainrust-main".to_string();
+    assert_ne!(other_chain.block().hash, base);
+}
+
+#[test]
+fn test_default_memo_fee_keeps_existing_genesis_hash() {
+    let json = serde_json::to_value(sample_config()).unwrap();
+    assert!(json["consensus"].get("memo_fee_per_byte").is_none());
+    assert!(json["consensus"].get("block_gas_limit").is_none());
+    assert!(json["consensus"].get("min_gas_price").is_none());
+
+    let mut pricier = sample_config();
+    pricier.consensus.memo_fee_per_byte = Amount::new(5);
+    assert_ne!(pricier.block().hash, sample_config().block().hash);
+    let reloaded: GenesisConfig = serde_json::from_value(serde_json::to_value(&pricier).unwrap()).unwrap();
+    assert_eq!(reloaded, pricier);
+}
+
+#[test]
+fn test_account_ledger_keeps_existing_genesis_hash() {
+    let json = serde_json::to_value(sample_config()).unwrap();
+    assert!(json.get("ledger").is_none());
+
+    let utxo = GenesisConfig { ledger: genesis::LedgerModel::Utxo, ..sample_config() };
+    assert_eq!(serde_json::to_value(&utxo).unwrap()["ledger"], "utxo");
+    assert_ne!(utxo.block().hash, sample_config().block().hash);
+}
+
+#[test]
+fn test_initial_state_from_genesis() {
+    let validator = Wallet::new();
+    let mut config = sample_config();
+    config.validators.insert(validator.address.clone(), Amount::new(100));
+    config.consensus.unbonding_delay = 3;
+    let mut blockchain = Blockchain::from_genesis(config);
+
+    assert_eq!(blockchain.get_balance(&alice()), Amount::new(1_000));
+    assert_eq!(blockchain.get_balance(&bob()), Amount::new(500));
+    assert_eq!(blockchain.staking.stake_of(&validator.address), Amount::new(100));
+    assert_eq!(blockchain.staking.unbonding_delay, 3);
+
+    // Genesis validators produce blocks from height one
+    assert!(blockchain.mine_block(vec![], None).is_err());
+    assert!(blockchain.mine_block(vec![], Some(&validator.private_key)).is_ok());
+}
+
+#[test]
+fn test_mined_blocks_meet_genesis_difficulty() {
+    let config = GenesisConfig { difficulty: 2, ..sample_config() };
+    let mut blockchain = Blockchain::from_genesis(config);
+    let block = blockchain.mine_block(vec![], None).unwrap();
+    assert!(block.hash.as_str().starts_with("00"));
+    assert!(blockchain.is_chain_valid());
+
+    let mut easy = blockchain.chain[1].clone();
+    easy.nonce = 0;
+    easy.hash = easy.calculate_hash();
+    while easy.meets_difficulty(2) {
+        easy.nonce += 1;
+        easy.hash = easy.calculate_hash();
+    }
+    blockchain.chain[1] = easy;
+    assert!(!blockchain.is_chain_valid());
+}
+
+#[test]
+fn test_load_genesis_from_file() {
+    let filename = "test_genesis.json";
+    std::fs::write(
+        filename,
+        format!(r#"{{"chain_id": "chainrust-file", "timestamp": 42, "balances": {{"{}": 7}}}}"#, alice()),
+    )
+    .unwrap();
+    let config = GenesisConfig::load_from_file(filename).unwrap();
+    std::fs::remove_file(filename).unwrap();
+
+    assert_eq!(config.chain_id, "chainrust-file");
+    assert_eq!(config.difficulty, 0);
+    assert_eq!(config.consensus, genesis::ConsensusParams::default());
+    assert_eq!(Blockchain::from_genesis(config).get_balance(&alice()), Amount::new(7));
+}
+
+#[test]
+fn test_load_rejects_raw_public_keys() {
+    let validator = Wallet::new();
+    let filename = "test_genesis_public_key.json";
+    std::fs::write(filename, format!(r#"{{"validators": {{"{}": 100}}}}"#, validator.public_key)).unwrap();
+    let loaded = GenesisConfig::load_from_file(filename);
+    std::fs::remove_file(filename).unwrap();
+    assert!(loaded.is_err());
+}
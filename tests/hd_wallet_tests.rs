@@ -0,0 +1,65 @@
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+
+use address::Address;
+use wallet::HdWallet;
+use zeroize::Zeroize;
+
+const ABANDON: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+#[test]
+fn test_generate_and_restore() {
+    for words in [12, 24] {
+        let hd = HdWallet::generate(words, "").unwrap();
+        assert_eq!(hd.mnemonic().split_whitespace().count(), words);
+
+        let restored = HdWallet::restore(&hd.mnemonic(), "").unwrap();
+        assert_eq!(restored.addresses(0, 3).unwrap(), hd.addresses(0, 3).unwrap());
+    }
+    assert!(HdWallet::generate(15, "").is_err());
+}
+
+#[test]
+fn test_restore_rejects_bad_mnemonics() {
+    let bad_checksum = ABANDON.replace("about", "abandon");
+    assert!(HdWallet::restore(&bad_checksum, "").is_err());
+    assert!(HdWallet::restore("not a real mnemonic", "").is_err());
+}
+
+#[test]
+fn test_bip44_reference_vector() {
+    let hd = HdWallet::restore(ABANDON, "").unwrap();
+    let key = hd.derive_path("m/44'/0'/0'/0/0").unwrap();
+    assert_eq!(key.public_key, "03aaeb52dd7494c361049de67cc680e83ebcbbbdbeb13637d92cd845f70308af5e");
+    assert_eq!(Address::from_public_key(&key.public_key), Some(key.address.clone()));
+}
+
+#[test]
+fn test_standard_path_and_passphrase() {
+    let hd = HdWallet::restore(ABANDON, "").unwrap();
+    assert_eq!(HdWallet::path(2, 7), format!("m/44'/{}'/2'/0/7", wallet::COIN_TYPE));
+    assert_eq!(hd.derive(2, 7).unwrap().private_key, hd.derive_path(&HdWallet::path(2, 7)).unwrap().private_key);
+
+    let addresses = hd.addresses(0, 5).unwrap();
+    assert_eq!(addresses.len(), 5);
+    assert!(addresses.windows(2).all(|pair| pair[0] != pair[1]));
+    assert_ne!(hd.addresses(1, 1).unwrap()[0], addresses[0], "Accounts derive different keys");
+
+    let protected = HdWallet::restore(ABANDON, "TREZOR").unwrap();
+    assert_ne!(protected.addresses(0, 1).unwrap()[0], addresses[0], "The passphrase changes every key");
+    assert!(hd.derive_path("44'/0'").is_err());
+}
+
+#[test]
+fn test_derived_keys_can_be_wiped() {
+    let hd = HdWallet::restore(ABANDON, "").unwrap();
+    let mut key = hd.derive(0, 0).unwrap();
+    assert_eq!(key.private_key.len(), 64);
+    key.zeroize();
+    assert!(key.private_key.is_empty(), "Dropping the derived key runs the same wipe");
+    assert_eq!(key.address, hd.addresses(0, 1).unwrap()[0], "Only the private key is secret");
+}
@@ -0,0 +1,202 @@
+mod blockchain {
+    include!("../src/blockchain.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod transaction {
+    include!("../src/transaction.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+mod sigcache {
+    include!("../src/sigcache.rs");
+}
+mod staking {
+    include!("../src/staking.rs");
+}
+mod genesis {
+    include!("../src/genesis.rs");
+}
+mod htlc {
+    include!("../src/htlc.rs");
+}
+mod escrow {
+    include!("../src/escrow.rs");
+}
+mod token {
+    include!("../src/token.rs");
+}
+mod nft {
+    include!("../src/nft.rs");
+}
+mod contract {
+    include!("../src/contract.rs");
+}
+mod receipt {
+    include!("../src/receipt.rs");
+}
+
+use blockchain::Blockchain;
+use block::Block;
+use genesis::{GenesisConfig, LedgerModel};
+use htlc::{hashlock_of, HtlcStatus, PREIMAGE_BYTES};
+use transaction::{Transaction, TransactionKind};
+use types::{Amount, Hash};
+use wallet::Wallet;
+
+const PREIMAGE: &str = "3f1e6b0c9a5d2e8f7c4b1a09d8e7f6a5b4c3d2e1f0a9b8c7d6e5f4a3b2c1d0e9";
+
+fn funded(blockchain: &mut Blockchain, amount: u64) -> Wallet {
+    let wallet = Wallet::new();
+    blockchain.add_funds(&wallet.address, Amount::new(amount)).unwrap();
+    wallet
+}
+
+/// Locks 40 from a new account for a new receiver until height 5, mined in block 1.
+fn locked(blockchain: &mut Blockchain) -> (Wallet, Wallet, Hash) {
+    let (alice, bob) = (funded(blockchain, 100), Wallet::new());
+    let lock = Transaction::htlc_lock(blockchain.chain_id(), &bob.address, Amount::new(40), hashlock_of(PREIMAGE).unwrap(), 5, &alice.private_key);
+    let id = lock.id();
+    blockchain.mine_block(vec![lock], None).unwrap();
+    (alice, bob, id)
+}
+
+#[test]
+fn test_hashlock_is_sha256_of_a_32_byte_preimage() {
+    let bytes = hex::decode(PREIMAGE).unwrap();
+    assert_eq!(hashlock_of(PREIMAGE).unwrap(), Hash::digest(&bytes));
+    assert_eq!(hashlock_of(&PREIMAGE.to_uppercase()), hashlock_of(PREIMAGE));
+    assert!(hashlock_of(&PREIMAGE[..62]).is_err());
+    assert!(hashlock_of(&format!("{}00", PREIMAGE)).is_err());
+    assert!(hashlock_of("not hex").is_err());
+    assert_eq!(htlc::generate_preimage().len(), 2 * PREIMAGE_BYTES);
+}
+
+#[test]
+fn test_claim_with_preimage() {
+    let mut blockchain = Blockchain::new();
+    let (alice, bob, id) = locked(&mut blockchain);
+    let chain_id = blockchain.chain_id().to_string();
+    assert_eq!(blockchain.get_balance(&alice.address), Amount::new(60));
+    assert_eq!(blockchain.htlcs.open(Some(&bob.address)).len(), 1);
+    assert_eq!(blockchain.htlcs.open(Some(&alice.address))[0].id, id);
+    assert!(blockchain.htlcs.open(Some(&Wallet::new().address)).is_empty());
+    assert!(blockchain.htlcs.revealed(&hashlock_of(PREIMAGE).unwrap()).is_none());
+
+    let wrong = Transaction::htlc_claim(&chain_id, id.clone(), Amount::new(40), &"00".repeat(PREIMAGE_BYTES), &bob.private_key);
+    assert_eq!(blockchain.validate_transaction(&wrong), Err("Preimage does not match the hashlock".to_string()));
+    let stolen = Transaction::htlc_claim(&chain_id, id.clone(), Amount::new(40), PREIMAGE, &alice.private_key);
+    assert_eq!(blockchain.validate_transaction(&stolen), Err("Only the receiver of an HTLC can claim it".to_string()));
+    let greedy = Transaction::htlc_claim(&chain_id, id.clone(), Amount::new(41), PREIMAGE, &bob.private_key);
+    assert!(blockchain.validate_transaction(&greedy).is_err());
+
+    let claim = Transaction::htlc_claim(&chain_id, id.clone(), Amount::new(40), PREIMAGE, &bob.private_key);
+    let mut tampered = claim.clone();
+    tampered.kind = TransactionKind::HtlcClaim { htlc: id.clone(), preimage: "00".repeat(PREIMAGE_BYTES) };
+    assert!(!tampered.verify(&chain_id), "The preimage is signed");
+
+    blockchain.mine_block(vec![claim.clone()], None).unwrap();
+    assert_eq!(blockchain.get_balance(&bob.address), Amount::new(40));
+    assert!(blockchain.htlcs.open(None).is_empty());
+    let revealed = blockchain.htlcs.revealed(&hashlock_of(PREIMAGE).unwrap()).unwrap();
+    assert_eq!(revealed.id, id);
+    assert_eq!(revealed.status, HtlcStatus::Claimed { preimage: PREIMAGE.to_string(), height: 2 });
+
+    assert!(blockchain.validate_transaction(&claim).is_err(), "Claimed only once");
+    let refund = Transaction::htlc_refund(&chain_id, id, Amount::new(40), &alice.private_key).with_nonce(1, &alice.private_key);
+    assert!(blockchain.validate_transaction(&refund).is_err());
+}
+
+#[test]
+fn test_refund_after_timeout() {
+    let mut blockchain = Blockchain::new();
+    let (alice, bob, id) = locked(&mut blockchain);
+    let chain_id = blockchain.chain_id().to_string();
+    let refund = Transaction::htlc_refund(&chain_id, id.clone(), Amount::new(40), &alice.private_key).with_nonce(1, &alice.private_key);
+    assert_eq!(blockchain.validate_transaction(&refund), Err("HTLC cannot be refunded before height 5".to_string()));
+
+    for _ in 2..5 {
+        blockchain.mine_block(vec![], None).unwrap();
+    }
+    // Block 5 is the first that may refund and no longer claim
+    let claim = Transaction::htlc_claim(&chain_id, id.clone(), Amount::new(40), PREIMAGE, &bob.private_key);
+    assert_eq!(blockchain.validate_transaction(&claim), Err("HTLC timed out at height 5".to_string()));
+    let hijacked = Transaction::htlc_refund(&chain_id, id.clone(), Amount::new(40), &bob.private_key);
+    assert_eq!(blockchain.validate_transaction(&hijacked), Err("Only the sender of an HTLC can refund it".to_string()));
+
+    blockchain.mine_block(vec![refund.clone(), claim], None).unwrap();
+    assert_eq!(blockchain.chain[5].transactions, vec![refund]);
+    assert_eq!(blockchain.get_balance(&alice.address), Amount::new(100));
+    assert_eq!(blockchain.get_balance(&bob.address), Amount::ZERO);
+    assert_eq!(blockchain.htlcs.get(&id).unwrap().status, HtlcStatus::Refunded { height: 5 });
+    assert!(blockchain.htlcs.revealed(&hashlock_of(PREIMAGE).unwrap()).is_none());
+}
+
+#[test]
+fn test_lock_rules() {
+    let mut blockchain = Blockchain::new();
+    let alice = funded(&mut blockchain, 100);
+    let bob = Wallet::new();
+    let chain_id = blockchain.chain_id().to_string();
+    let hashlock = hashlock_of(PREIMAGE).unwrap();
+
+    let empty = Transaction::htlc_lock(&chain_id, &bob.address, Amount::ZERO, hashlock.clone(), 5, &alice.private_key);
+    assert!(blockchain.validate_transaction(&empty).is_err());
+    let expired = Transaction::htlc_lock(&chain_id, &bob.address, Amount::new(10), hashlock.clone(), 1, &alice.private_key);
+    assert_eq!(blockchain.validate_transaction(&expired), Err("HTLC timeout height 1 has already been reached".to_string()));
+    let oversized = Transaction::htlc_lock(&chain_id, &bob.address, Amount::new(101), hashlock.clone(), 5, &alice.private_key);
+    assert_eq!(blockchain.validate_transaction(&oversized), Err("Insufficient funds".to_string()));
+    let memo = Transaction::htlc_lock(&chain_id, &bob.address, Amount::new(10), hashlock.clone(), 5, &alice.private_key).with_memo("swap", &alice.private_key);
+    assert!(blockchain.validate_transaction(&memo).is_err());
+
+    // The lock transaction names the HTLC, so replaying it after settlement is refused
+    let lock = Transaction::htlc_lock(&chain_id, &bob.address, Amount::new(10), hashlock.clone(), 5, &alice.private_key);
+    blockchain.mine_block(vec![lock.clone()], None).unwrap();
+    let claim = Transaction::htlc_claim(&chain_id, lock.id(), Amount::new(10), PREIMAGE, &bob.private_key);
+    blockchain.mine_block(vec![claim], None).unwrap();
+    assert_eq!(blockchain.validate_transaction(&lock), Err("This HTLC was already created".to_string()));
+
+    let utxo = Blockchain::from_genesis(GenesisConfig { ledger: LedgerModel::Utxo, ..GenesisConfig::default() });
+    let lock = Transaction::htlc_lock(utxo.chain_id(), &bob.address, Amount::new(10), hashlock, 5, &alice.private_key);
+    assert_eq!(utxo.validate_transaction(&lock), Err("htlc_lock transactions are not valid on a UTXO ledger".to_string()));
+}
+
+#[test]
+fn test_rejected_block_leaves_htlcs_untouched() {
+    let mut blockchain = Blockchain::new();
+    let (alice, bob, id) = locked(&mut blockchain);
+    let chain_id = blockchain.chain_id().to_string();
+    let claim = Transaction::htlc_claim(&chain_id, id.clone(), Amount::new(40), PREIMAGE, &bob.private_key);
+    let overdraft = Transaction::new(&chain_id, &bob.address, Amount::new(1_000), &alice.private_key).with_nonce(1, &alice.private_key);
+
+    let tip = blockchain.chain.last().unwrap().hash.clone();
+    let mut block = Block::new(&chain_id, 2, vec![claim, overdraft], tip);
+    block.timestamp = blockchain.chain[1].timestamp;
+    block.hash = block.calculate_hash();
+    assert!(blockchain.add_block(block).is_err());
+    assert!(blockchain.htlcs.get(&id).unwrap().is_open());
+    assert_eq!(blockchain.get_balance(&bob.address), Amount::ZERO);
+
+    let reloaded: Blockchain = serde_json::from_str(&serde_json::to_string(&blockchain).unwrap()).unwrap();
+    assert_eq!(reloaded.htlcs, blockchain.htlcs);
+}
@@ -0,0 +1,108 @@
+mod keystore {
+    include!("../src/keystore.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+
+use keystore::{KdfParams, Keystore, KeystoreEntry, MAX_KDF_MEMORY_KIB};
+use wallet::Wallet;
+
+/// Argon2 at its minimum cost keeps the tests fast; real keystores use the defaults.
+const TEST_PARAMS: KdfParams = KdfParams { memory_kib: 64, iterations: 1, parallelism: 1 };
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("chainrust-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn test_encrypt_decrypt_roundtrip() {
+    let wallet = Wallet::new();
+    let entry = KeystoreEntry::encrypt(&wallet, "hunter2", TEST_PARAMS).unwrap();
+    assert!(!entry.crypto.ciphertext.contains(&wallet.private_key));
+    assert_eq!(entry.decrypt("hunter2").unwrap().private_key, wallet.private_key);
+    assert!(entry.decrypt("wrong").is_err());
+}
+
+#[test]
+fn test_tampered_entry_is_rejected() {
+    let wallet = Wallet::new();
+    let entry = KeystoreEntry::encrypt(&wallet, "pw", TEST_PARAMS).unwrap();
+
+    let mut relabelled = entry.clone();
+    relabelled.address = Wallet::new().address;
+    assert!(relabelled.decrypt("pw").is_err(), "The address is authenticated");
+
+    let mut flipped = entry;
+    let last = flipped.crypto.ciphertext.pop().unwrap();
+    flipped.crypto.ciphertext.push(if last == '0' { '1' } else { '0' });
+    assert!(flipped.decrypt("pw").is_err());
+}
+
+#[test]
+fn test_import_list_export() {
+    let (dir, other_dir) = (temp_dir("import"), temp_dir("export"));
+    let keystore = Keystore::with_params(&dir, TEST_PARAMS).unwrap();
+    let first = Wallet::new();
+    let second = Wallet::new();
+    assert_eq!(keystore.import(&first.private_key, "pw").unwrap(), first.address);
+    keystore.insert(&second, "other").unwrap();
+    assert!(keystore.import(&first.private_key, "pw").is_err(), "Keys are never overwritten");
+
+    let mut expected = vec![first.address.clone(), second.address.clone()];
+    expected.sort();
+    assert_eq!(keystore.list().unwrap(), expected);
+
+    assert!(keystore.export(&first.address, "other").is_err());
+    let exported = keystore.export(&first.address, "pw").unwrap();
+    let json = serde_json::to_string(&exported).unwrap();
+    assert!(!json.contains(&first.private_key), "Exports stay encrypted");
+
+    let other = Keystore::with_params(&other_dir, TEST_PARAMS).unwrap();
+    other.import_entry(&serde_json::from_str(&json).unwrap()).unwrap();
+    assert_eq!(other.unlock(&first.address, "pw").unwrap().private_key, first.private_key);
+
+    std::fs::remove_dir_all(dir).unwrap();
+    std::fs::remove_dir_all(other_dir).unwrap();
+}
+
+#[test]
+fn test_default_kdf_is_memory_hard() {
+    assert!(KdfParams::default().memory_kib >= 19 * 1024);
+}
+
+#[test]
+fn test_excessive_kdf_params_are_refused() {
+    let dir = temp_dir("kdf-cap");
+    let keystore = Keystore::with_params(&dir, TEST_PARAMS).unwrap();
+    let wallet = Wallet::new();
+    let mut entry = KeystoreEntry::encrypt(&wallet, "pw", TEST_PARAMS).unwrap();
+    entry.crypto.kdf_params.memory_kib = MAX_KDF_MEMORY_KIB + 1;
+    let refused = entry.decrypt("pw").err().unwrap();
+    assert!(refused.starts_with("KDF parameters exceed"), "Refused before deriving a key");
+    assert!(keystore.import_entry(&entry).is_err());
+    entry.crypto.kdf_params = KdfParams { iterations: u32::MAX, ..TEST_PARAMS };
+    assert!(keystore.import_entry(&entry).is_err());
+    assert!(keystore.list().unwrap().is_empty());
+    assert!(KdfParams::default().check().is_ok());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_keystore_files_are_private() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = temp_dir("mode");
+    let keystore = Keystore::with_params(&dir, TEST_PARAMS).unwrap();
+    let wallet = Wallet::new();
+    keystore.insert(&wallet, "pw").unwrap();
+    let mode = |path: &std::path::Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(&dir.join(format!("{}.json", wallet.address))), 0o600);
+    assert_eq!(mode(&dir), 0o700);
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,183 @@
+mod transaction {
+    include!("../src/transaction.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+
+use address::Address;
+use crypto::SignatureScheme;
+use multisig::{MultisigPolicy, MultisigSignature, PartiallySignedTransaction};
+use transaction::{Transaction, TransactionKind};
+use types::Amount;
+use wallet::Wallet;
+
+const CHAIN_ID: &str = "chainrust-test";
+
+fn cosigners() -> Vec<Wallet> {
+    (0..3).map(|_| Wallet::new()).collect()
+}
+
+fn public_keys(wallets: &[Wallet]) -> Vec<String> {
+    wallets.iter().map(|wallet| wallet.public_key.clone()).collect()
+}
+
+fn unsigned(wallets: &[Wallet]) -> PartiallySignedTransaction {
+    let policy = MultisigPolicy::new(2, &public_keys(wallets)).unwrap();
+    PartiallySignedTransaction::new(CHAIN_ID, policy, TransactionKind::Transfer, &Wallet::new().address, Amount::new(10)).unwrap()
+}
+
+#[test]
+fn test_policy_address_is_canonical() {
+    let wallets = cosigners();
+    let mut keys = public_keys(&wallets);
+    let address = MultisigPolicy::new(2, &keys).unwrap().address();
+    keys.reverse();
+    assert_eq!(MultisigPolicy::new(2, &keys).unwrap().address(), address, "Key order must not matter");
+    assert_ne!(MultisigPolicy::new(1, &keys).unwrap().address(), address, "The threshold is part of the address");
+    assert!(wallets.iter().all(|wallet| wallet.address != address));
+    assert!(Address::parse(address.as_str()).is_ok());
+}
+
+#[test]
+fn test_policy_validation() {
+    let keys = public_keys(&cosigners());
+    assert!(MultisigPolicy::new(0, &keys).is_err());
+    assert!(MultisigPolicy::new(4, &keys).is_err());
+    assert!(MultisigPolicy::new(1, &[]).is_err());
+    assert!(MultisigPolicy::new(1, &[keys[0].clone(), keys[0].clone()]).is_err(), "Duplicate keys are rejected");
+    assert!(MultisigPolicy::new(1, &["02abcd".to_string()]).is_err());
+    let too_many: Vec<String> = (0..=multisig::MAX_MULTISIG_KEYS).map(|_| Wallet::new().public_key).collect();
+    assert!(MultisigPolicy::new(1, &too_many).is_err());
+}
+
+#[test]
+fn test_threshold_signatures_verify() {
+    let wallets = cosigners();
+    let mut psbt = unsigned(&wallets);
+    assert_eq!(psbt.signatures_missing(), 2);
+    assert!(!psbt.transaction.verify(CHAIN_ID));
+
+    psbt.sign(&wallets[0].private_key).unwrap();
+    assert_eq!(psbt.signatures_missing(), 1);
+    assert!(psbt.sign(&wallets[0].private_key).is_err(), "A key signs only once");
+    assert!(psbt.clone().finalize().is_err());
+
+    psbt.sign(&wallets[2].private_key).unwrap();
+    let transaction = psbt.finalize().unwrap();
+    assert!(transaction.verify(CHAIN_ID));
+    assert!(!transaction.verify("chainrust-other"));
+}
+
+#[test]
+fn test_signatures_must_be_distinct_and_from_policy_keys() {
+    let wallets = cosigners();
+    let mut psbt = unsigned(&wallets);
+    assert!(psbt.sign(&Wallet::new().private_key).is_err(), "Outsiders cannot sign");
+    psbt.sign(&wallets[1].private_key).unwrap();
+
+    // Repeating one valid signature does not reach the threshold
+    let mut duplicated = psbt.transaction.clone();
+    let auth = duplicated.multisig.as_mut().unwrap();
+    auth.signatures.push(auth.signatures[0].clone());
+    assert!(!duplicated.verify(CHAIN_ID));
+
+    // Nor does a valid signature from a key outside the policy
+    let mut outsider = psbt.transaction.clone();
+    let stranger = Wallet::new();
+    let signature = crypto::sign_message(&outsider.signing_message(), &stranger.private_key);
+    outsider.multisig.as_mut().unwrap().signatures.push(MultisigSignature { public_key: stranger.public_key, signature });
+    assert!(!outsider.verify(CHAIN_ID));
+}
+
+#[test]
+fn test_tampering_breaks_signatures() {
+    let wallets = cosigners();
+    let mut psbt = unsigned(&wallets);
+    psbt.sign(&wallets[0].private_key).unwrap();
+    psbt.sign(&wallets[1].private_key).unwrap();
+    let transaction = psbt.finalize().unwrap();
+
+    let mut inflated = transaction.clone();
+    inflated.amount = Amount::new(1000);
+    assert!(!inflated.verify(CHAIN_ID));
+
+    // Swapping in a 1-of-3 policy changes the address, so the sender no longer matches
+    let mut weakened = transaction.clone();
+    weakened.multisig.as_mut().unwrap().policy.threshold = 1;
+    assert!(!weakened.verify(CHAIN_ID));
+
+    let mut single = transaction;
+    single.sender_public_key = wallets[0].public_key.clone();
+    assert!(!single.verify(CHAIN_ID), "Multisig transactions carry no single-key fields");
+}
+
+#[test]
+fn test_combine_and_json_round_trip() {
+    let wallets = cosigners();
+    let base = unsigned(&wallets);
+    let mut first = serde_json::from_str::<PartiallySignedTransaction>(&serde_json::to_string(&base).unwrap()).unwrap();
+    let mut second = base.clone();
+    first.sign(&wallets[0].private_key).unwrap();
+    second.sign(&wallets[1].private_key).unwrap();
+
+    first.combine(&second).unwrap();
+    assert_eq!(first.signatures_missing(), 0);
+    let id = first.transaction.id();
+    let transaction = first.finalize().unwrap();
+    assert_eq!(transaction.id(), id);
+
+    let other = PartiallySignedTransaction::new(
+        CHAIN_ID,
+        base.transaction.multisig.clone().unwrap().policy,
+        TransactionKind::Transfer,
+        &Wallet::new().address,
+        Amount::new(10),
+    )
+    .unwrap();
+    assert!(second.combine(&other).is_err(), "Cannot merge signatures for a different transaction");
+}
+
+#[test]
+fn test_single_key_transactions_omit_multisig_field() {
+    let wallet = Wallet::new();
+    let transaction = Transaction::new(CHAIN_ID, &Wallet::new().address, Amount::new(1), &wallet.private_key);
+    let json = serde_json::to_value(&transaction).unwrap();
+    assert!(json.get("multisig").is_none(), "Existing transactions must serialize as before");
+}
+
+#[test]
+fn test_schnorr_cosigners() {
+    let wallets = cosigners();
+    let mut psbt = unsigned(&wallets);
+    psbt.transaction.scheme = SignatureScheme::Schnorr;
+    psbt.sign(&wallets[0].private_key).unwrap();
+    psbt.sign(&wallets[1].private_key).unwrap();
+    let transaction = psbt.finalize().unwrap();
+    assert!(transaction.verify(CHAIN_ID));
+
+    let mut relabelled = transaction;
+    relabelled.scheme = SignatureScheme::Ecdsa;
+    assert!(!relabelled.verify(CHAIN_ID));
+}
@@ -0,0 +1,218 @@
+mod blockchain {
+    include!("../src/blockchain.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod transaction {
+    include!("../src/transaction.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+mod sigcache {
+    include!("../src/sigcache.rs");
+}
+mod staking {
+    include!("../src/staking.rs");
+}
+mod genesis {
+    include!("../src/genesis.rs");
+}
+mod htlc {
+    include!("../src/htlc.rs");
+}
+mod escrow {
+    include!("../src/escrow.rs");
+}
+mod token {
+    include!("../src/token.rs");
+}
+mod nft {
+    include!("../src/nft.rs");
+}
+mod contract {
+    include!("../src/contract.rs");
+}
+mod receipt {
+    include!("../src/receipt.rs");
+}
+
+use blockchain::Blockchain;
+use block::Block;
+use nft::MAX_METADATA_URI_BYTES;
+use transaction::{Transaction, TransactionKind};
+use types::{Amount, Hash};
+use wallet::Wallet;
+
+/// Creates a collection and mints one NFT of it to its owner, in blocks 1 and 2.
+fn minted(blockchain: &mut Blockchain) -> (Wallet, Hash, Hash) {
+    let owner = Wallet::new();
+    let create = Transaction::nft_collection_create(blockchain.chain_id(), "Tiles", &owner.private_key);
+    let collection = create.id();
+    blockchain.mine_block(vec![create], None).unwrap();
+    let mint = Transaction::nft_mint(blockchain.chain_id(), collection.clone(), &owner.address, Hash::digest("tile #1"), "ipfs://tiles/1.json", &owner.private_key).with_nonce(1, &owner.private_key);
+    let nft = mint.id();
+    blockchain.mine_block(vec![mint], None).unwrap();
+    (owner, collection, nft)
+}
+
+#[test]
+fn test_mint_into_a_collection() {
+    let mut blockchain = Blockchain::new();
+    let (owner, collection, id) = minted(&mut blockchain);
+    let created = blockchain.nfts.collection(&collection).unwrap();
+    assert_eq!((created.name.as_str(), &created.owner), ("Tiles", &owner.address));
+
+    let nft = blockchain.nfts.get(&id).unwrap();
+    assert_eq!(nft.collection, collection);
+    assert_eq!(nft.content_hash, Hash::digest("tile #1"));
+    assert_eq!(nft.metadata_uri, "ipfs://tiles/1.json");
+    assert_eq!(nft.owner, owner.address);
+
+    // The collection owner can mint straight to someone else
+    let fan = Wallet::new();
+    let gift = Transaction::nft_mint(blockchain.chain_id(), collection, &fan.address, Hash::digest("tile #2"), "ipfs://tiles/2.json", &owner.private_key).with_nonce(2, &owner.private_key);
+    let gift_id = gift.id();
+    blockchain.mine_block(vec![gift], None).unwrap();
+    assert_eq!(blockchain.nfts.get(&gift_id).unwrap().owner, fan.address);
+    assert_eq!(blockchain.nfts.owned_by(&owner.address).len(), 1);
+    assert_eq!(blockchain.nfts.owned_by(&fan.address)[0].id, gift_id);
+    assert_eq!(blockchain.get_balance(&fan.address), Amount::ZERO);
+}
+
+#[test]
+fn test_only_the_current_owner_transfers() {
+    let mut blockchain = Blockchain::new();
+    let (owner, _, id) = minted(&mut blockchain);
+    let chain_id = blockchain.chain_id().to_string();
+    let (alice, bob) = (Wallet::new(), Wallet::new());
+
+    let send = Transaction::nft_transfer(&chain_id, id.clone(), &alice.address, &owner.private_key).with_nonce(2, &owner.private_key);
+    blockchain.mine_block(vec![send], None).unwrap();
+    assert_eq!(blockchain.nfts.get(&id).unwrap().owner, alice.address);
+    let stale = Transaction::nft_transfer(&chain_id, id.clone(), &bob.address, &owner.private_key);
+    assert_eq!(blockchain.validate_transaction(&stale), Err("Only the owner of an NFT can transfer it".to_string()));
+
+    // The new owner can pass it on within the same block it is sent on in
+    let onward = vec![
+        Transaction::nft_transfer(&chain_id, id.clone(), &bob.address, &alice.private_key),
+        Transaction::nft_transfer(&chain_id, id.clone(), &owner.address, &bob.private_key),
+    ];
+    blockchain.mine_block(onward, None).unwrap();
+    assert_eq!(blockchain.nfts.get(&id).unwrap().owner, owner.address);
+    assert!(blockchain.nfts.owned_by(&alice.address).is_empty());
+}
+
+#[test]
+fn test_history_is_rebuilt_from_blocks() {
+    let mut blockchain = Blockchain::new();
+    let (owner, _, id) = minted(&mut blockchain);
+    let chain_id = blockchain.chain_id().to_string();
+    let alice = Wallet::new();
+    let send = Transaction::nft_transfer(&chain_id, id.clone(), &alice.address, &owner.private_key).with_nonce(2, &owner.private_key);
+    let send_id = send.id();
+    blockchain.mine_block(vec![send], None).unwrap();
+
+    let history = blockchain.nft_history(&id);
+    assert_eq!(history.len(), 2);
+    assert_eq!((history[0].tx.clone(), history[0].height, history[0].from.clone()), (id.clone(), 2, None));
+    assert_eq!(history[0].to, owner.address);
+    assert_eq!((history[1].tx.clone(), history[1].height), (send_id, 3));
+    assert_eq!((history[1].from.clone(), history[1].to.clone()), (Some(owner.address.clone()), alice.address.clone()));
+    assert_eq!(history[1].timestamp, blockchain.chain[3].timestamp);
+    assert!(blockchain.nft_history(&Hash::digest("unknown")).is_empty());
+}
+
+#[test]
+fn test_nft_rules() {
+    let mut blockchain = Blockchain::new();
+    let (owner, collection, id) = minted(&mut blockchain);
+    let chain_id = blockchain.chain_id().to_string();
+    let other = Wallet::new();
+    let mint = |content: &str, uri: &str, signer: &Wallet| {
+        Transaction::nft_mint(&chain_id, collection.clone(), &other.address, Hash::digest(content), uri, &signer.private_key)
+            .with_nonce(blockchain.next_nonce(&signer.address, &[]), &signer.private_key)
+    };
+
+    assert!(blockchain.validate_transaction(&mint("tile #2", "https://example.com/2", &owner)).is_ok());
+    assert_eq!(blockchain.validate_transaction(&mint("tile #2", "ipfs://2", &other)), Err("Only the owner of Tiles can mint into it".to_string()));
+    assert_eq!(blockchain.validate_transaction(&mint("tile #1", "ipfs://1b", &owner)), Err("Tiles already has an NFT for this content".to_string()));
+    for uri in ["", "ipfs://tiles/ 2", &"u".repeat(MAX_METADATA_URI_BYTES + 1)] {
+        assert!(blockchain.validate_transaction(&mint("tile #2", uri, &owner)).is_err(), "{:?} is not a valid URI", uri);
+    }
+    let unknown = Transaction::nft_mint(&chain_id, Hash::digest("unknown"), &other.address, Hash::digest("x"), "ipfs://x", &other.private_key);
+    assert!(blockchain.validate_transaction(&unknown).is_err());
+
+    assert!(blockchain.validate_transaction(&Transaction::nft_collection_create(&chain_id, "", &other.private_key)).is_err());
+    assert!(blockchain.validate_transaction(&Transaction::nft_collection_create(&chain_id, "Tiles", &other.private_key)).is_ok(), "Names need not be unique");
+    assert_eq!(blockchain.validate_transaction(&blockchain.chain[1].transactions[0]), Err("This collection was already created".to_string()));
+    assert_eq!(blockchain.validate_transaction(&blockchain.chain[2].transactions[0]), Err("This NFT was already minted".to_string()));
+
+    let priced = Transaction::with_kind(&chain_id, TransactionKind::NftTransfer { nft: id.clone() }, &other.address, Amount::new(1), &owner.private_key);
+    assert_eq!(blockchain.validate_transaction(&priced), Err("nft_transfer transactions must have a zero amount".to_string()));
+    let missing = Transaction::nft_transfer(&chain_id, Hash::digest("unknown"), &other.address, &owner.private_key);
+    assert!(blockchain.validate_transaction(&missing).is_err());
+}
+
+#[test]
+fn test_rejected_block_leaves_nfts_untouched() {
+    let mut blockchain = Blockchain::new();
+    let (owner, _, id) = minted(&mut blockchain);
+    let chain_id = blockchain.chain_id().to_string();
+    let alice = Wallet::new();
+    let transactions = vec![
+        Transaction::nft_transfer(&chain_id, id.clone(), &alice.address, &owner.private_key).with_nonce(2, &owner.private_key),
+        Transaction::new(&chain_id, &alice.address, Amount::new(1_000), &owner.private_key).with_nonce(3, &owner.private_key),
+    ];
+    let tip = blockchain.chain.last().unwrap().hash.clone();
+    let mut block = Block::new(&chain_id, 3, transactions, tip);
+    block.timestamp = blockchain.chain[2].timestamp;
+    block.hash = block.calculate_hash();
+    assert!(blockchain.add_block(block).is_err());
+    assert_eq!(blockchain.nfts.get(&id).unwrap().owner, owner.address);
+
+    let reloaded: Blockchain = serde_json::from_str(&serde_json::to_string(&blockchain).unwrap()).unwrap();
+    assert_eq!(reloaded.nfts, blockchain.nfts);
+}
+
+#[test]
+fn test_signed_fields_cannot_be_moved_into_names() {
+    let blockchain = Blockchain::new();
+    let owner = Wallet::new();
+    let create = Transaction::nft_collection_create(blockchain.chain_id(), "n", &owner.private_key).expiring_at(10, &owner.private_key);
+    assert!(create.verify(blockchain.chain_id()));
+    let mut rewrapped = create.clone();
+    rewrapped.kind = TransactionKind::NftCollectionCreate { name: "n:expires_at_height=10".to_string() };
+    rewrapped.expires_at_height = None;
+    assert!(!rewrapped.verify(blockchain.chain_id()), "The expiry cannot be moved into the name");
+
+    let mint = Transaction::nft_mint(blockchain.chain_id(), create.id(), &owner.address, Hash::digest("art"), "ipfs://art", &owner.private_key)
+        .with_memo("note", &owner.private_key);
+    let mut rewrapped = mint.clone();
+    rewrapped.kind = TransactionKind::NftMint {
+        collection: create.id(),
+        content_hash: Hash::digest("art"),
+        metadata_uri: format!("ipfs://art:memo={}", hex::encode("note")),
+    };
+    rewrapped.memo = None;
+    assert!(!rewrapped.verify(blockchain.chain_id()), "The memo cannot be moved into the metadata URI");
+}
@@ -0,0 +1,44 @@
+mod peers {
+    include!("../src/peers.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+
+use peers::{Handshake, PeerSet, MAX_PEERS};
+use types::Hash;
+
+const CHAIN_ID: &str = "chainrust-test";
+
+fn handshake(url: &str, chain_id: &str, genesis_hash: &Hash) -> Handshake {
+    Handshake { url: url.to_string(), chain_id: chain_id.to_string(), genesis_hash: genesis_hash.clone() }
+}
+
+#[test]
+fn test_peers_on_another_network_are_refused() {
+    let genesis = Hash::digest("genesis");
+    let mut peers = PeerSet::default();
+
+    let other_chain = peers.accept(handshake("http://10.0.0.2:8080", "chainrust-other", &genesis), CHAIN_ID, &genesis);
+    assert_eq!(other_chain, Err("Peer is on chain chainrust-other, not chainrust-test".to_string()));
+    let other_genesis = peers.accept(handshake("http://10.0.0.2:8080", CHAIN_ID, &Hash::digest("fork")), CHAIN_ID, &genesis);
+    assert!(other_genesis.unwrap_err().starts_with("Peer starts from genesis"));
+    assert!(peers.is_empty());
+
+    assert_eq!(peers.accept(handshake("http://10.0.0.2:8080", CHAIN_ID, &genesis), CHAIN_ID, &genesis), Ok(()));
+    assert_eq!(peers.accept(handshake("http://10.0.0.2:8080", CHAIN_ID, &genesis), CHAIN_ID, &genesis), Ok(()));
+    assert_eq!(peers.list(), vec![handshake("http://10.0.0.2:8080", CHAIN_ID, &genesis)], "Handshaking again replaces the entry");
+    assert!(peers.accept(handshake("", CHAIN_ID, &genesis), CHAIN_ID, &genesis).is_err());
+}
+
+#[test]
+fn test_peer_limit() {
+    let genesis = Hash::digest("genesis");
+    let mut peers = PeerSet::default();
+    for index in 0..MAX_PEERS {
+        assert!(peers.accept(handshake(&format!("http://peer{}", index), CHAIN_ID, &genesis), CHAIN_ID, &genesis).is_ok());
+    }
+    assert!(peers.accept(handshake("http://one-too-many", CHAIN_ID, &genesis), CHAIN_ID, &genesis).is_err());
+    assert!(peers.accept(handshake("http://peer0", CHAIN_ID, &genesis), CHAIN_ID, &genesis).is_ok(), "Known peers may handshake again");
+    assert_eq!(peers.len(), MAX_PEERS);
+}
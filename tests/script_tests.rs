@@ -0,0 +1,163 @@
+mod transaction {
+    include!("../src/transaction.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+
+use crypto::SignatureScheme;
+use script::{evaluate, Op, Script, ScriptContext, MAX_ELEMENT_BYTES, MAX_SCRIPT_OPS, MAX_STACK_DEPTH};
+use sha2::{Digest, Sha256};
+use transaction::TimeLock;
+use wallet::Wallet;
+
+const MESSAGE: &str = "chainrust-test:transfer:message";
+
+fn context(valid_after: Option<TimeLock>) -> ScriptContext<'static> {
+    ScriptContext { message: MESSAGE, scheme: SignatureScheme::Ecdsa, valid_after }
+}
+
+fn run(locking: &str, unlocking: &str, valid_after: Option<TimeLock>) -> Result<(), String> {
+    evaluate(&locking.parse()?, &unlocking.parse()?, &context(valid_after))
+}
+
+fn sign(wallet: &Wallet) -> String {
+    crypto::sign(SignatureScheme::Ecdsa, MESSAGE, &wallet.private_key)
+}
+
+fn key_hash(wallet: &Wallet) -> String {
+    hex::encode(Sha256::digest(hex::decode(&wallet.public_key).unwrap()))
+}
+
+fn number(value: u64) -> String {
+    Script::new(vec![Script::push_number(value)]).to_string()
+}
+
+#[test]
+fn test_script_text_round_trips() {
+    let text = "dup hash 00ff equal verify checksig checkmultisig checklocktime empty";
+    let script: Script = text.parse().unwrap();
+    assert_eq!(script.ops[2], Op::Push(vec![0x00, 0xff]));
+    assert_eq!(script.ops[8], Op::Push(Vec::new()));
+    assert_eq!(script.to_string(), text);
+    assert_eq!("DUP  HASH".parse::<Script>().unwrap().to_string(), "dup hash");
+    assert_eq!(serde_json::to_value(&script).unwrap(), text);
+    assert_eq!(number(0), "00");
+    assert_eq!(number(1000), "03e8");
+
+    assert!("nop".parse::<Script>().is_err());
+    assert!("0f0".parse::<Script>().is_err(), "Pushes are whole bytes");
+    assert!(serde_json::from_str::<Script>("\"dup drop\"").is_err());
+}
+
+#[test]
+fn test_script_addresses_commit_to_the_script() {
+    let wallet = Wallet::new();
+    let script: Script = format!("{} checksig", wallet.public_key).parse().unwrap();
+    assert_eq!(script.address(), script.to_string().parse::<Script>().unwrap().address());
+    assert_ne!(script.address(), wallet.address);
+    assert_ne!(script.address(), format!("{} checksig verify", wallet.public_key).parse::<Script>().unwrap().address());
+}
+
+#[test]
+fn test_known_good_programs() {
+    let (alice, bob, carol) = (Wallet::new(), Wallet::new(), Wallet::new());
+
+    let pay_to_key_hash = format!("dup hash {} equal verify checksig", key_hash(&alice));
+    assert_eq!(run(&pay_to_key_hash, &format!("{} {}", sign(&alice), alice.public_key), None), Ok(()));
+
+    let secret = hex::encode(b"correct horse battery staple");
+    let hashlock = format!("hash {} equal", hex::encode(Sha256::digest(b"correct horse battery staple")));
+    assert_eq!(run(&hashlock, &secret, None), Ok(()));
+
+    let two_of_three = format!("02 {} {} {} 03 checkmultisig", alice.public_key, bob.public_key, carol.public_key);
+    assert_eq!(run(&two_of_three, &format!("{} {}", sign(&alice), sign(&carol)), None), Ok(()));
+    assert_eq!(run(&two_of_three, &format!("{} {}", sign(&bob), sign(&carol)), None), Ok(()));
+
+    let after_height = format!("{} checklocktime {} checksig", number(1000), alice.public_key);
+    assert_eq!(run(&after_height, &sign(&alice), Some(TimeLock::Height(1000))), Ok(()));
+    assert_eq!(run(&after_height, &sign(&alice), Some(TimeLock::Height(5000))), Ok(()));
+
+    let after_time = format!("{} checklocktime {} checksig", number(1_767_225_600), alice.public_key);
+    assert_eq!(run(&after_time, &sign(&alice), Some(TimeLock::Timestamp(1_767_225_600))), Ok(()));
+
+    let schnorr = ScriptContext { message: MESSAGE, scheme: SignatureScheme::Schnorr, valid_after: None };
+    let signature = crypto::sign(SignatureScheme::Schnorr, MESSAGE, &alice.private_key);
+    let locking: Script = format!("{} checksig", alice.public_key).parse().unwrap();
+    assert_eq!(evaluate(&locking, &signature.parse().unwrap(), &schnorr), Ok(()));
+}
+
+#[test]
+fn test_known_bad_programs() {
+    let (alice, bob, carol) = (Wallet::new(), Wallet::new(), Wallet::new());
+
+    let pay_to_key_hash = format!("dup hash {} equal verify checksig", key_hash(&alice));
+    assert!(run(&pay_to_key_hash, &format!("{} {}", sign(&bob), bob.public_key), None).is_err(), "Wrong key");
+    assert!(run(&pay_to_key_hash, &format!("{} {}", sign(&bob), alice.public_key), None).is_err(), "Wrong signer");
+    assert!(run(&pay_to_key_hash, &alice.public_key, None).is_err(), "Missing signature");
+
+    let hashlock = format!("hash {} equal", hex::encode(Sha256::digest(b"secret")));
+    assert!(run(&hashlock, &hex::encode(b"guess"), None).is_err());
+
+    let two_of_three = format!("02 {} {} {} 03 checkmultisig", alice.public_key, bob.public_key, carol.public_key);
+    assert!(run(&two_of_three, &format!("{} {}", sign(&carol), sign(&alice)), None).is_err(), "Signatures out of key order");
+    assert!(run(&two_of_three, &format!("{} {}", sign(&alice), sign(&alice)), None).is_err(), "One key signing twice");
+    assert!(run(&two_of_three, &sign(&alice), None).is_err(), "Too few signatures");
+
+    let after_height = format!("{} checklocktime {} checksig", number(1000), alice.public_key);
+    assert!(run(&after_height, &sign(&alice), None).is_err(), "No time lock");
+    assert!(run(&after_height, &sign(&alice), Some(TimeLock::Height(999))).is_err(), "Lock too early");
+    assert!(run(&after_height, &sign(&alice), Some(TimeLock::Timestamp(1_767_225_600))).is_err(), "Timestamp for a height");
+    assert!(run("000000000000000001 checklocktime", "", Some(TimeLock::Height(u64::MAX))).is_err(), "Number over 8 bytes");
+
+    assert!(run("01", "01 dup", None).is_err(), "Unlocking scripts may only push");
+    assert!(run("empty", "", None).is_err(), "False on top");
+    assert!(run("0000", "", None).is_err(), "Zero bytes are false");
+    assert!(run("", "", None).is_err(), "Empty stack");
+    assert!(run("dup", "", None).is_err(), "Stack underflow");
+    assert!(run("verify 01", "empty", None).is_err());
+}
+
+#[test]
+fn test_resource_limits() {
+    assert!(vec!["01"; MAX_SCRIPT_OPS].join(" ").parse::<Script>().is_ok());
+    assert!(vec!["01"; MAX_SCRIPT_OPS + 1].join(" ").parse::<Script>().is_err());
+    assert!(hex::encode(vec![1; MAX_ELEMENT_BYTES]).parse::<Script>().is_ok());
+    assert!(hex::encode(vec![1; MAX_ELEMENT_BYTES + 1]).parse::<Script>().is_err());
+
+    // Limits also hold for scripts built in code rather than parsed
+    let oversized = Script::new(vec![Op::Push(vec![1; MAX_ELEMENT_BYTES + 1])]);
+    assert!(evaluate(&oversized, &Script::default(), &context(None)).is_err());
+
+    let deep = format!("01{}", " dup".repeat(MAX_STACK_DEPTH - 1));
+    assert_eq!(run(&deep, "", None), Ok(()));
+    assert!(run(&format!("{} dup", deep), "", None).is_err());
+
+    // Each checkmultisig counts every key it names, whether or not signatures are checked
+    let keys = vec![Wallet::new().public_key; 16].join(" ");
+    let one = format!("00 {} 10 checkmultisig", keys);
+    assert_eq!(run(&one, "", None), Ok(()));
+    assert!(run(&format!("{} verify {}", one, one), "", None).is_err());
+    assert!(run(&format!("00 {} 11 checkmultisig", vec![Wallet::new().public_key; 17].join(" ")), "", None).is_err());
+}
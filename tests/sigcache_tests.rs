@@ -0,0 +1,85 @@
+mod transaction {
+    include!("../src/transaction.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+mod sigcache {
+    include!("../src/sigcache.rs");
+}
+
+use sigcache::SignatureCache;
+use transaction::Transaction;
+use types::Amount;
+use wallet::Wallet;
+
+const CHAIN_ID: &str = "chainrust-test";
+
+fn transfer(amount: u64) -> Transaction {
+    Transaction::new(CHAIN_ID, &Wallet::new().address, Amount::new(amount), &Wallet::new().private_key)
+}
+
+#[test]
+fn test_only_valid_signatures_are_cached() {
+    let cache = SignatureCache::default();
+    let tx = transfer(5);
+    assert!(cache.verify(&tx, CHAIN_ID));
+    assert!(cache.contains(&tx, CHAIN_ID));
+    assert!(!cache.contains(&tx, "chainrust-other"), "Entries are per chain");
+    assert!(!cache.verify(&tx, "chainrust-other"));
+
+    let mut forged = tx.clone();
+    forged.signature = transfer(5).signature;
+    assert!(!cache.verify(&forged, CHAIN_ID));
+    assert!(!cache.contains(&forged, CHAIN_ID));
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn test_verify_block() {
+    let cache = SignatureCache::default();
+    let block: Vec<Transaction> = (1..=20).map(transfer).collect();
+    assert!(cache.verify(&block[0], CHAIN_ID));
+    assert!(cache.verify_block(&block, CHAIN_ID));
+    assert_eq!(cache.len(), block.len());
+    assert!(cache.verify_block(&[], CHAIN_ID));
+
+    let mut forged = block.clone();
+    forged.push(transfer(21));
+    forged[20].amount = Amount::new(22);
+    assert!(!cache.verify_block(&forged, CHAIN_ID));
+    assert_eq!(cache.len(), block.len(), "A failed block caches nothing");
+}
+
+#[test]
+fn test_oldest_entries_are_evicted() {
+    let cache = SignatureCache::new(2);
+    let txs: Vec<Transaction> = (1..=3).map(transfer).collect();
+    for tx in &txs {
+        assert!(cache.verify(tx, CHAIN_ID));
+    }
+    assert_eq!(cache.len(), 2);
+    assert!(!cache.contains(&txs[0], CHAIN_ID));
+    assert!(cache.contains(&txs[2], CHAIN_ID));
+}
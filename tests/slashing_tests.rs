@@ -0,0 +1,197 @@
+mod blockchain {
+    include!("../src/blockchain.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod transaction {
+    include!("../src/transaction.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+mod sigcache {
+    include!("../src/sigcache.rs");
+}
+mod staking {
+    include!("../src/staking.rs");
+}
+mod genesis {
+    include!("../src/genesis.rs");
+}
+mod htlc {
+    include!("../src/htlc.rs");
+}
+mod escrow {
+    include!("../src/escrow.rs");
+}
+mod token {
+    include!("../src/token.rs");
+}
+mod nft {
+    include!("../src/nft.rs");
+}
+mod contract {
+    include!("../src/contract.rs");
+}
+mod receipt {
+    include!("../src/receipt.rs");
+}
+
+use blockchain::Blockchain;
+use block::{Block, SignedHeader};
+use transaction::Transaction;
+use types::Amount;
+use wallet::Wallet;
+
+fn chain_with_validator(stake: u64) -> (Blockchain, Wallet) {
+    let mut blockchain = Blockchain::new();
+    let validator = Wallet::new();
+    blockchain.add_funds(&validator.address, Amount::new(stake)).unwrap();
+    let bond = Transaction::bond(blockchain.chain_id(), Amount::new(stake), &validator.private_key);
+    blockchain.mine_block(vec![bond], None).unwrap();
+    (blockchain, validator)
+}
+
+/// Two blocks the validator signs for the same height with different contents.
+fn double_sign(blockchain: &Blockchain, validator: &Wallet) -> (SignedHeader, SignedHeader) {
+    let tip = blockchain.chain.last().unwrap();
+    let mut first = Block::new(blockchain.chain_id(), tip.index + 1, vec![], tip.hash.clone());
+    let mut second = first.clone();
+    second.nonce = 1;
+    first.sign(&validator.private_key);
+    second.sign(&validator.private_key);
+    (first.header(), second.header())
+}
+
+#[test]
+fn test_detects_equivocation() {
+    let (blockchain, validator) = chain_with_validator(100);
+    let (first, second) = double_sign(&blockchain, &validator);
+    assert!(SignedHeader::is_equivocation(&first, &second));
+    assert!(!SignedHeader::is_equivocation(&first, &first), "Same block twice is not equivocation");
+}
+
+#[test]
+fn test_headers_at_different_heights_are_not_evidence() {
+    let (blockchain, validator) = chain_with_validator(100);
+    let (first, _) = double_sign(&blockchain, &validator);
+    let tip = blockchain.chain.last().unwrap();
+    let mut later = Block::new(blockchain.chain_id(), tip.index + 2, vec![], first.hash.clone());
+    later.sign(&validator.private_key);
+
+    let mut relabelled = later.header();
+    assert!(!SignedHeader::is_equivocation(&first, &relabelled));
+    // Claiming the later block was at the same height breaks its signature
+    relabelled.index = first.index;
+    assert!(!SignedHeader::is_equivocation(&first, &relabelled));
+}
+
+#[test]
+fn test_evidence_slashes_offender() {
+    let (mut blockchain, validator) = chain_with_validator(100);
+    let reporter = Wallet::new();
+    let (first, second) = double_sign(&blockchain, &validator);
+    let height = first.index;
+
+    let evidence = Transaction::evidence(blockchain.chain_id(), first, second, &reporter.private_key).unwrap();
+    assert!(evidence.verify(blockchain.chain_id()));
+    blockchain.mine_block(vec![evidence.clone()], Some(&validator.private_key)).unwrap();
+
+    assert_eq!(blockchain.staking.stake_of(&validator.address), Amount::new(50));
+    assert!(blockchain.staking.is_slashed(&validator.address, height));
+    assert_eq!(
+        blockchain.validate_transaction(&evidence),
+        Err("Equivocation at this height was already punished".to_string())
+    );
+}
+
+#[test]
+fn test_evidence_reaches_unbonding_stake() {
+    let (mut blockchain, validator) = chain_with_validator(100);
+    let reporter = Wallet::new();
+    let (first, second) = double_sign(&blockchain, &validator);
+
+    let unbond = Transaction::unbond(blockchain.chain_id(), Amount::new(100), &validator.private_key).with_nonce(1, &validator.private_key);
+    blockchain.mine_block(vec![unbond], Some(&validator.private_key)).unwrap();
+    assert_eq!(blockchain.staking.stake_of(&validator.address), Amount::ZERO);
+
+    let evidence = Transaction::evidence(blockchain.chain_id(), first, second, &reporter.private_key).unwrap();
+    blockchain.mine_block(vec![evidence], None).unwrap();
+    assert_eq!(blockchain.staking.unbonding[0].amount, Amount::new(50));
+}
+
+#[test]
+fn test_forged_evidence_is_rejected() {
+    let (blockchain, validator) = chain_with_validator(100);
+    let reporter = Wallet::new();
+    let (first, mut second) = double_sign(&blockchain, &validator);
+    second.proposer_signature = first.proposer_signature.clone();
+
+    let evidence = Transaction::evidence(blockchain.chain_id(), first.clone(), second, &reporter.private_key).unwrap();
+    assert!(blockchain.validate_transaction(&evidence).is_err());
+
+    // Evidence must name the proposer it punishes
+    let mut misdirected = Transaction::evidence(blockchain.chain_id(), first.clone(), first, &reporter.private_key).unwrap();
+    misdirected.receiver = reporter.address.clone();
+    misdirected.sign(&reporter.private_key);
+    assert!(blockchain.validate_transaction(&misdirected).is_err());
+}
+
+#[test]
+fn test_dropped_evidence_leaves_reporter_nonce() {
+    let (mut blockchain, validator) = chain_with_validator(1);
+    blockchain.staking.slash_percent = 50;
+    let reporter = Wallet::new();
+    blockchain.add_funds(&reporter.address, Amount::new(10)).unwrap();
+    let (first, second) = double_sign(&blockchain, &validator);
+
+    // Half of one unit rounds down to nothing, so the evidence cannot be applied
+    let evidence = Transaction::evidence(blockchain.chain_id(), first, second, &reporter.private_key).unwrap();
+    assert_eq!(blockchain.validate_transaction(&evidence), Err("Offender has no stake to slash".to_string()));
+    let block = blockchain.mine_block(vec![evidence], Some(&validator.private_key)).unwrap();
+    assert!(block.transactions.is_empty());
+    assert_eq!(blockchain.next_nonce(&reporter.address, &[]), 0, "A dropped transaction does not use up its nonce");
+
+    let transfer = Transaction::new(blockchain.chain_id(), &validator.address, Amount::new(1), &reporter.private_key);
+    let block = blockchain.mine_block(vec![transfer], Some(&validator.private_key)).unwrap().clone();
+    assert_eq!(block.transactions.len(), 1);
+    assert!(blockchain.is_chain_valid());
+}
+
+#[test]
+fn test_evidence_that_burns_nothing_is_rejected() {
+    let (mut blockchain, validator) = chain_with_validator(1);
+    blockchain.staking.slash_percent = 50;
+    let reporter = Wallet::new();
+    let (first, second) = double_sign(&blockchain, &validator);
+    assert_eq!(blockchain.staking.slash_amount(&validator.address), Amount::ZERO);
+
+    let evidence = Transaction::evidence(blockchain.chain_id(), first, second, &reporter.private_key).unwrap();
+    assert_eq!(blockchain.validate_transaction(&evidence), Err("Offender has no stake to slash".to_string()));
+
+    // Unbonding entries round on their own, so two units split across them burn nothing either
+    blockchain.staking.unbonding.push(staking::Unbonding { address: validator.address.clone(), amount: Amount::new(1), release_height: 100 });
+    assert_eq!(blockchain.validate_transaction(&evidence), Err("Offender has no stake to slash".to_string()));
+    blockchain.staking.bond(&validator.address, Amount::new(1)).unwrap();
+    assert_eq!(blockchain.staking.slash_amount(&validator.address), Amount::new(1));
+    assert!(blockchain.validate_transaction(&evidence).is_ok());
+}
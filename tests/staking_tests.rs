@@ -0,0 +1,262 @@
+mod blockchain {
+    include!("../src/blockchain.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod transaction {
+    include!("../src/transaction.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+mod sigcache {
+    include!("../src/sigcache.rs");
+}
+mod staking {
+    include!("../src/staking.rs");
+}
+mod genesis {
+    include!("../src/genesis.rs");
+}
+mod htlc {
+    include!("../src/htlc.rs");
+}
+mod escrow {
+    include!("../src/escrow.rs");
+}
+mod token {
+    include!("../src/token.rs");
+}
+mod nft {
+    include!("../src/nft.rs");
+}
+mod contract {
+    include!("../src/contract.rs");
+}
+mod receipt {
+    include!("../src/receipt.rs");
+}
+
+use blockchain::{Blockchain, PROPOSER_TIMEOUT};
+use block::Block;
+use staking::Staking;
+use transaction::Transaction;
+use types::{Amount, Hash};
+use wallet::Wallet;
+
+fn funded_validator(blockchain: &mut Blockchain, stake: u64) -> Wallet {
+    let wallet = Wallet::new();
+    blockchain.add_funds(&wallet.address, Amount::new(stake)).unwrap();
+    let bond = Transaction::bond(blockchain.chain_id(), Amount::new(stake), &wallet.private_key);
+    let key = blockchain.expected_proposer().map(|_| wallet.private_key.clone());
+    blockchain.mine_block(vec![bond], key.as_deref()).unwrap();
+    wallet
+}
+
+#[test]
+fn test_bond_moves_balance_into_stake() {
+    let mut blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    blockchain.add_funds(&wallet.address, Amount::new(100)).unwrap();
+
+    let bond = Transaction::bond(blockchain.chain_id(), Amount::new(60), &wallet.private_key);
+    assert!(bond.verify(blockchain.chain_id()));
+    blockchain.mine_block(vec![bond], None).unwrap();
+
+    assert_eq!(blockchain.get_balance(&wallet.address), Amount::new(40));
+    assert_eq!(blockchain.staking.stake_of(&wallet.address), Amount::new(60));
+    assert_eq!(blockchain.expected_proposer(), Some(wallet.address.clone()));
+}
+
+#[test]
+fn test_bond_rejected_without_funds() {
+    let blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    let bond = Transaction::bond(blockchain.chain_id(), Amount::new(10), &wallet.private_key);
+    assert_eq!(blockchain.validate_transaction(&bond), Err("Insufficient funds".to_string()));
+}
+
+#[test]
+fn test_kind_is_covered_by_signature() {
+    let blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    let mut bond = Transaction::bond(blockchain.chain_id(), Amount::new(10), &wallet.private_key);
+    bond.kind = transaction::TransactionKind::Unbond;
+    assert!(!bond.verify(blockchain.chain_id()), "Changing the kind must invalidate the signature");
+}
+
+#[test]
+fn test_unbond_releases_after_delay() {
+    let mut blockchain = Blockchain::new();
+    blockchain.staking.unbonding_delay = 2;
+    let validator = funded_validator(&mut blockchain, 50);
+
+    let unbond = Transaction::unbond(blockchain.chain_id(), Amount::new(50), &validator.private_key).with_nonce(1, &validator.private_key);
+    blockchain.mine_block(vec![unbond], Some(&validator.private_key)).unwrap();
+    let unbond_height = blockchain.chain.last().unwrap().index;
+    assert_eq!(blockchain.staking.stake_of(&validator.address), Amount::ZERO);
+    assert_eq!(blockchain.get_balance(&validator.address), Amount::ZERO);
+    assert_eq!(blockchain.staking.unbonding[0].release_height, unbond_height + 2);
+
+    // No validators remain, so blocks are unsigned again
+    blockchain.mine_block(vec![], None).unwrap();
+    assert_eq!(blockchain.get_balance(&validator.address), Amount::ZERO);
+    blockchain.mine_block(vec![], None).unwrap();
+    assert_eq!(blockchain.get_balance(&validator.address), Amount::new(50));
+    assert!(blockchain.staking.unbonding.is_empty());
+}
+
+#[test]
+fn test_unbond_more_than_stake_fails() {
+    let mut blockchain = Blockchain::new();
+    let validator = funded_validator(&mut blockchain, 30);
+    let unbond = Transaction::unbond(blockchain.chain_id(), Amount::new(31), &validator.private_key);
+    assert_eq!(blockchain.validate_transaction(&unbond), Err("Insufficient stake".to_string()));
+}
+
+#[test]
+fn test_select_proposer_is_deterministic_and_weighted() {
+    let mut staking = Staking::default();
+    let (heavy_validator, light_validator) = (Wallet::new().address, Wallet::new().address);
+    staking.bond(&heavy_validator, Amount::new(900)).unwrap();
+    staking.bond(&light_validator, Amount::new(100)).unwrap();
+
+    let seed = Hash::digest("seed");
+    let first = staking.select_proposer(&seed, 0);
+    assert_eq!(first, staking.select_proposer(&seed, 0));
+
+    let mut heavy = 0;
+    for i in 0..1000 {
+        if staking.select_proposer(&Hash::digest(format!("hash-{}", i)), 0) == Some(heavy_validator.clone()) {
+            heavy += 1;
+        }
+    }
+    assert!(heavy > 800 && heavy < 980, "heavy validator selected {} times out of 1000", heavy);
+}
+
+#[test]
+fn test_select_proposer_ignores_stake_below_minimum() {
+    let mut staking = Staking { min_stake: Amount::new(10), ..Default::default() };
+    let (small, big) = (Wallet::new().address, Wallet::new().address);
+    let seed = Hash::digest("seed");
+    staking.bond(&small, Amount::new(5)).unwrap();
+    assert_eq!(staking.select_proposer(&seed, 0), None);
+    staking.bond(&big, Amount::new(10)).unwrap();
+    assert_eq!(staking.select_proposer(&seed, 0), Some(big));
+}
+
+#[test]
+fn test_proposer_rotates_after_timeout() {
+    let mut blockchain = Blockchain::new();
+    let genesis = blockchain.chain.last().unwrap().clone();
+    // A parent twenty rounds old, so the next block may come from any of those rounds
+    let mut parent = Block::new(blockchain.chain_id(), 1, vec![], genesis.hash.clone());
+    parent.timestamp = chrono::Utc::now().timestamp() - 20 * PROPOSER_TIMEOUT;
+    parent.mine(0);
+    blockchain.add_block(parent.clone()).unwrap();
+
+    let validators = [Wallet::new(), Wallet::new()];
+    for validator in &validators {
+        blockchain.staking.bond(&validator.address, Amount::new(10)).unwrap();
+    }
+    let block_in_round = |round: i64, signer: &Wallet| {
+        let mut block = Block::new(blockchain.chain_id(), 2, vec![], parent.hash.clone());
+        block.timestamp = parent.timestamp + round * PROPOSER_TIMEOUT;
+        block.sign(&signer.private_key);
+        block
+    };
+    let proposer_of = |round: i64| {
+        let address = blockchain.proposer_at(parent.timestamp + round * PROPOSER_TIMEOUT).unwrap();
+        validators.iter().find(|validator| validator.address == address).unwrap()
+    };
+    let first = proposer_of(0);
+    let round = (1..20).find(|round| proposer_of(*round).address != first.address).expect("Another validator gets a round");
+    let other = proposer_of(round);
+    assert_eq!(blockchain.staking.select_proposer(&parent.hash, round as u64).as_ref(), Some(&other.address));
+
+    assert!(blockchain.clone().add_block(block_in_round(0, first)).is_ok());
+    assert!(blockchain.clone().add_block(block_in_round(0, other)).is_err());
+    assert!(blockchain.clone().add_block(block_in_round(round, first)).is_err(), "The round belongs to the next proposer");
+    assert!(blockchain.clone().add_block(block_in_round(round, other)).is_ok(), "A silent proposer is skipped after the timeout");
+
+    let ahead = (21..200).find(|round| proposer_of(*round).address == first.address).unwrap();
+    assert_eq!(
+        blockchain.clone().add_block(block_in_round(ahead, first)),
+        Err("Block claims a proposer round that has not started yet".to_string())
+    );
+}
+
+#[test]
+fn test_mine_block_requires_selected_proposer() {
+    let mut blockchain = Blockchain::new();
+    let validator = funded_validator(&mut blockchain, 10);
+    let outsider = Wallet::new();
+
+    assert!(blockchain.mine_block(vec![], None).is_err());
+    assert!(blockchain.mine_block(vec![], Some(&outsider.private_key)).is_err());
+
+    let block = blockchain.mine_block(vec![], Some(&validator.private_key)).unwrap();
+    assert_eq!(block.proposer, validator.public_key);
+    assert!(block.verify_proposer_signature());
+    assert!(blockchain.is_chain_valid());
+}
+
+#[test]
+fn test_add_block_validates_proposer_signature() {
+    let mut blockchain = Blockchain::new();
+    let validator = funded_validator(&mut blockchain, 10);
+    let tip = blockchain.chain.last().unwrap().clone();
+
+    let mut unsigned = Block::new(blockchain.chain_id(), tip.index + 1, vec![], tip.hash.clone());
+    assert!(blockchain.clone().add_block(unsigned.clone()).is_err());
+
+    let outsider = Wallet::new();
+    let mut forged = unsigned.clone();
+    forged.sign(&outsider.private_key);
+    assert!(blockchain.clone().add_block(forged).is_err());
+
+    let mut tampered = unsigned.clone();
+    tampered.sign(&validator.private_key);
+    tampered.proposer_signature = crypto::sign_message(tampered.hash.as_str(), &outsider.private_key);
+    assert!(blockchain.clone().add_block(tampered).is_err());
+
+    unsigned.sign(&validator.private_key);
+    blockchain.add_block(unsigned).unwrap();
+    assert!(blockchain.is_chain_valid());
+}
+
+#[test]
+fn test_add_block_is_atomic() {
+    let mut blockchain = Blockchain::new();
+    let wallet = Wallet::new();
+    blockchain.add_funds(&wallet.address, Amount::new(10)).unwrap();
+    let tip = blockchain.chain.last().unwrap().clone();
+
+    let receiver = Wallet::new().address;
+    let ok = Transaction::new(blockchain.chain_id(), &receiver, Amount::new(10), &wallet.private_key);
+    let overdraft = Transaction::new(blockchain.chain_id(), &receiver, Amount::new(10), &wallet.private_key).with_nonce(1, &wallet.private_key);
+    let block = Block::new(blockchain.chain_id(), tip.index + 1, vec![ok, overdraft], tip.hash.clone());
+    assert!(blockchain.add_block(block).is_err());
+    assert_eq!(blockchain.get_balance(&wallet.address), Amount::new(10));
+    assert_eq!(blockchain.get_balance(&receiver), Amount::ZERO);
+    assert_eq!(blockchain.chain.len(), 1);
+}
@@ -0,0 +1,187 @@
+mod blockchain {
+    include!("../src/blockchain.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod transaction {
+    include!("../src/transaction.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+mod sigcache {
+    include!("../src/sigcache.rs");
+}
+mod staking {
+    include!("../src/staking.rs");
+}
+mod genesis {
+    include!("../src/genesis.rs");
+}
+mod htlc {
+    include!("../src/htlc.rs");
+}
+mod escrow {
+    include!("../src/escrow.rs");
+}
+mod token {
+    include!("../src/token.rs");
+}
+mod nft {
+    include!("../src/nft.rs");
+}
+mod contract {
+    include!("../src/contract.rs");
+}
+mod receipt {
+    include!("../src/receipt.rs");
+}
+
+use blockchain::Blockchain;
+use block::Block;
+use token::MAX_SYMBOL_LEN;
+use transaction::{Transaction, TransactionKind};
+use types::{Amount, Hash};
+use wallet::Wallet;
+
+/// Issues 1000 units of GOLD to a new issuer, mined in block 1.
+fn issued(blockchain: &mut Blockchain) -> (Wallet, Hash) {
+    let issuer = Wallet::new();
+    let create = Transaction::token_create(blockchain.chain_id(), "GOLD", 2, Amount::new(1_000), &issuer.private_key);
+    let id = create.id();
+    blockchain.mine_block(vec![create], None).unwrap();
+    (issuer, id)
+}
+
+#[test]
+fn test_create_and_transfer_a_token() {
+    let mut blockchain = Blockchain::new();
+    let (issuer, id) = issued(&mut blockchain);
+    let token = blockchain.tokens.get(&id).unwrap();
+    assert_eq!((token.symbol.as_str(), token.decimals, token.supply), ("GOLD", 2, Amount::new(1_000)));
+    assert_eq!(token.issuer, issuer.address);
+    assert_eq!(blockchain.tokens.balance(&id, &issuer.address), Amount::new(1_000));
+    // Issuing needs no native funds and leaves native balances alone
+    assert_eq!(blockchain.get_balance(&issuer.address), Amount::ZERO);
+
+    let holder = Wallet::new();
+    let send = Transaction::token_transfer(blockchain.chain_id(), id.clone(), &holder.address, Amount::new(250), &issuer.private_key).with_nonce(1, &issuer.private_key);
+    blockchain.mine_block(vec![send], None).unwrap();
+    assert_eq!(blockchain.tokens.balance(&id, &issuer.address), Amount::new(750));
+    assert_eq!(blockchain.tokens.balance(&id, &holder.address), Amount::new(250));
+    assert_eq!(blockchain.get_balance(&holder.address), Amount::ZERO);
+    assert_eq!(blockchain.tokens.get(&id).unwrap().supply, Amount::new(1_000));
+}
+
+#[test]
+fn test_mint_and_burn_change_the_supply() {
+    let mut blockchain = Blockchain::new();
+    let (issuer, id) = issued(&mut blockchain);
+    let chain_id = blockchain.chain_id().to_string();
+    let holder = Wallet::new();
+
+    let mint = Transaction::token_mint(&chain_id, id.clone(), &holder.address, Amount::new(500), &issuer.private_key).with_nonce(1, &issuer.private_key);
+    blockchain.mine_block(vec![mint], None).unwrap();
+    assert_eq!(blockchain.tokens.balance(&id, &holder.address), Amount::new(500));
+    assert_eq!(blockchain.tokens.get(&id).unwrap().supply, Amount::new(1_500));
+
+    let forged = Transaction::token_mint(&chain_id, id.clone(), &holder.address, Amount::new(500), &holder.private_key);
+    assert_eq!(blockchain.validate_transaction(&forged), Err("Only the issuer of GOLD can mint it".to_string()));
+    let overflow = Transaction::token_mint(&chain_id, id.clone(), &holder.address, Amount::new(u64::MAX), &issuer.private_key);
+    assert_eq!(blockchain.validate_transaction(&overflow), Err("Token supply would overflow".to_string()));
+
+    let burn = Transaction::token_burn(&chain_id, id.clone(), Amount::new(200), &holder.private_key);
+    blockchain.mine_block(vec![burn], None).unwrap();
+    assert_eq!(blockchain.tokens.balance(&id, &holder.address), Amount::new(300));
+    assert_eq!(blockchain.tokens.get(&id).unwrap().supply, Amount::new(1_300));
+    let overburn = Transaction::token_burn(&chain_id, id.clone(), Amount::new(301), &holder.private_key);
+    assert_eq!(blockchain.validate_transaction(&overburn), Err("Insufficient token balance".to_string()));
+}
+
+#[test]
+fn test_token_rules() {
+    let mut blockchain = Blockchain::new();
+    let (issuer, id) = issued(&mut blockchain);
+    let chain_id = blockchain.chain_id().to_string();
+    let other = Wallet::new();
+    let create = |symbol: &str, decimals: u8| Transaction::token_create(&chain_id, symbol, decimals, Amount::new(10), &other.private_key);
+
+    assert!(blockchain.validate_transaction(&create("SILVER", 18)).is_ok());
+    assert!(blockchain.validate_transaction(&create("S1", 0)).is_ok());
+    for symbol in ["", "silver", "1UP", "SIL-VER", &"S".repeat(MAX_SYMBOL_LEN + 1)] {
+        assert!(blockchain.validate_transaction(&create(symbol, 0)).is_err(), "{:?} is not a valid symbol", symbol);
+    }
+    assert!(blockchain.validate_transaction(&create("SILVER", 19)).is_err());
+    assert_eq!(blockchain.validate_transaction(&create("GOLD", 0)), Err("Token symbol GOLD is already taken".to_string()));
+    let replayed = blockchain.chain[1].transactions[0].clone();
+    assert_eq!(blockchain.validate_transaction(&replayed), Err("This token was already created".to_string()));
+
+    let overdrawn = Transaction::token_transfer(&chain_id, id.clone(), &other.address, Amount::new(1_001), &issuer.private_key);
+    assert_eq!(blockchain.validate_transaction(&overdrawn), Err("Insufficient token balance".to_string()));
+    let empty = Transaction::token_transfer(&chain_id, id.clone(), &other.address, Amount::ZERO, &issuer.private_key);
+    assert!(blockchain.validate_transaction(&empty).is_err());
+    let unknown = Transaction::token_transfer(&chain_id, Hash::digest("unknown"), &other.address, Amount::new(1), &issuer.private_key);
+    assert!(blockchain.validate_transaction(&unknown).is_err());
+}
+
+#[test]
+fn test_token_transfers_are_signed() {
+    let mut blockchain = Blockchain::new();
+    let (issuer, id) = issued(&mut blockchain);
+    let (holder, thief) = (Wallet::new(), Wallet::new());
+    let send = Transaction::token_transfer(blockchain.chain_id(), id.clone(), &holder.address, Amount::new(5), &issuer.private_key).with_nonce(1, &issuer.private_key);
+    assert!(blockchain.validate_transaction(&send).is_ok());
+
+    // The token and receiver are part of the signing message
+    let mut other_token = send.clone();
+    other_token.kind = TransactionKind::TokenTransfer { token: Hash::digest("other") };
+    assert!(blockchain.validate_transaction(&other_token).is_err());
+    let mut redirected = send.clone();
+    redirected.receiver = thief.address.clone();
+    assert!(blockchain.validate_transaction(&redirected).is_err());
+    let mut as_mint = send;
+    as_mint.kind = TransactionKind::TokenMint { token: id };
+    assert!(blockchain.validate_transaction(&as_mint).is_err());
+}
+
+#[test]
+fn test_rejected_block_leaves_tokens_untouched() {
+    let mut blockchain = Blockchain::new();
+    let (issuer, id) = issued(&mut blockchain);
+    let chain_id = blockchain.chain_id().to_string();
+    let holder = Wallet::new();
+    let transactions = vec![
+        Transaction::token_transfer(&chain_id, id.clone(), &holder.address, Amount::new(100), &issuer.private_key).with_nonce(1, &issuer.private_key),
+        Transaction::token_burn(&chain_id, id.clone(), Amount::new(100), &issuer.private_key).with_nonce(2, &issuer.private_key),
+        Transaction::new(&chain_id, &holder.address, Amount::new(1_000), &issuer.private_key).with_nonce(3, &issuer.private_key),
+    ];
+    let tip = blockchain.chain.last().unwrap().hash.clone();
+    let mut block = Block::new(&chain_id, 2, transactions, tip);
+    block.timestamp = blockchain.chain[1].timestamp;
+    block.hash = block.calculate_hash();
+    assert!(blockchain.add_block(block).is_err());
+    assert_eq!(blockchain.tokens.balance(&id, &issuer.address), Amount::new(1_000));
+    assert_eq!(blockchain.tokens.get(&id).unwrap().supply, Amount::new(1_000));
+
+    let reloaded: Blockchain = serde_json::from_str(&serde_json::to_string(&blockchain).unwrap()).unwrap();
+    assert_eq!(reloaded.tokens, blockchain.tokens);
+}
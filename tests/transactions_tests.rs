@@ -4,11 +4,40 @@ mod transaction {
 mod wallet {
     include!("../src/wallet.rs");
 }
+mod address {
+    include!("../src/address.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
 
-use transaction::Transaction;
+use address::Address;
+use crypto::SignatureScheme;
+use transaction::{BatchOutput, TimeLock, Transaction, TransactionKind};
+use types::Amount;
 use wallet::Wallet;
 use secp256k1::{Secp256k1, SecretKey, PublicKey};
-use hex;
+
+const CHAIN_ID: &str = "chainrust-test";
+
+fn receiver_address() -> Address {
+    Address::from_public_key("02d524421eb3d7d4c8d4e66f536aa00e3760282cd476373e0a7ca7cb73044ce934").unwrap()
+}
 
 #[test]
 fn test_create_message() {
@@ -22,29 +51,29 @@ fn test_create_message() {
 #[test]
 fn test_transaction_new_and_verify() {
     let wallet = Wallet::new();
-    let sender = wallet.public_key.clone();
-    let receiver = "02d524421eb3d7d4c8d4e66f536aa00e3760282cd476373e0a7ca7cb73044ce934".to_string();
-    let amount = 50;
+    let sender = wallet.address.clone();
+    let receiver = receiver_address();
+    let amount = Amount::new(50);
     let private_key = wallet.private_key.clone();
 
-    let transaction = Transaction::new(&sender, &receiver, amount, &private_key);
+    let transaction = Transaction::new(CHAIN_ID, &receiver, amount, &private_key);
     assert_eq!(transaction.sender, sender);
+    assert_eq!(transaction.sender_public_key, wallet.public_key);
     assert_eq!(transaction.receiver, receiver);
     assert_eq!(transaction.amount, amount);
-    assert!(transaction.verify(), "Transaction verification failed");
+    assert!(transaction.verify(CHAIN_ID), "Transaction verification failed");
 }
 
 #[test]
 fn test_invalid_signature() {
     let wallet = Wallet::new();
-    let sender = wallet.public_key.clone();
-    let receiver = "02d524421eb3d7d4c8d4e66f536aa00e3760282cd476373e0a7ca7cb73044ce934".to_string();
-    let amount = 50;
+    let receiver = receiver_address();
+    let amount = Amount::new(50);
     let private_key = wallet.private_key.clone();
 
-    let mut transaction = Transaction::new(&sender, &receiver, amount, &private_key);
+    let mut transaction = Transaction::new(CHAIN_ID, &receiver, amount, &private_key);
     transaction.signature = "invalid_signature".to_string();
-    assert!(!transaction.verify(), "Verification should fail with invalid signature");
+    assert!(!transaction.verify(CHAIN_ID), "Verification should fail with invalid signature");
 }
 
 #[test]
@@ -55,10 +84,233 @@ fn test_specific_key_pair() {
     let secret_key = SecretKey::from_slice(&secret_key_bytes).expect("Invalid private key");
     let public_key = PublicKey::from_secret_key(&secp, &secret_key);
     let public_key_hex = hex::encode(public_key.serialize());
-    let receiver = "02d524421eb3d7d4c8d4e66f536aa00e3760282cd476373e0a7ca7cb73044ce934".to_string();
-    let amount = 50;
+    let receiver = receiver_address();
+    let amount = Amount::new(50);
 
-    let transaction = Transaction::new(&public_key_hex, &receiver, amount, private_key);
-    assert!(transaction.verify(), "Transaction verification failed for specific key pair");
+    let transaction = Transaction::new(CHAIN_ID, &receiver, amount, private_key);
+    assert_eq!(transaction.sender_public_key, public_key_hex);
+    assert!(transaction.verify(CHAIN_ID), "Transaction verification failed for specific key pair");
     println!("Derived Public Key: {}", public_key_hex);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_signature_bound_to_chain_id() {
+    let wallet = Wallet::new();
+    let mut transaction = Transaction::new(CHAIN_ID, &receiver_address(), Amount::new(50), &wallet.private_key);
+    assert!(!transaction.verify("chainrust-other"), "Transaction must not verify on another chain");
+
+    // Relabelling the chain id breaks the signature
+    transaction.chain_id = "chainrust-other".to_string();
+    assert!(!transaction.verify("chainrust-other"));
+}
+
+#[test]
+fn test_sender_must_match_public_key() {
+    let wallet = Wallet::new();
+    let other = Wallet::new();
+    let mut transaction = Transaction::new(CHAIN_ID, &receiver_address(), Amount::new(50), &wallet.private_key);
+    transaction.sender = other.address.clone();
+    transaction.sign(&wallet.private_key);
+    assert!(!transaction.verify(CHAIN_ID), "Sender address must be derived from the signing key");
+}
+
+#[test]
+fn test_malformed_fields_are_rejected_on_deserialize() {
+    let wallet = Wallet::new();
+    let transaction = Transaction::new(CHAIN_ID, &receiver_address(), Amount::new(50), &wallet.private_key);
+    let json = serde_json::to_value(&transaction).unwrap();
+    assert_eq!(serde_json::from_value::<Transaction>(json.clone()).unwrap(), transaction);
+
+    let mut bad_receiver = json.clone();
+    bad_receiver["receiver"] = serde_json::json!("receiver");
+    assert!(serde_json::from_value::<Transaction>(bad_receiver).is_err());
+
+    let mut negative = json.clone();
+    negative["amount"] = serde_json::json!(-1);
+    assert!(serde_json::from_value::<Transaction>(negative).is_err());
+
+    let too_large = json.to_string().replace("\"amount\":50", "\"amount\":18446744073709551616");
+    assert!(serde_json::from_str::<Transaction>(&too_large).is_err());
+}
+
+fn schnorr_transfer(wallet: &Wallet, amount: u64) -> Transaction {
+    Transaction::with_scheme(CHAIN_ID, SignatureScheme::Schnorr, TransactionKind::Transfer, &receiver_address(), Amount::new(amount), &wallet.private_key)
+}
+
+#[test]
+fn test_schnorr_sign_and_verify() {
+    let wallet = Wallet::new();
+    let transaction = schnorr_transfer(&wallet, 50);
+    assert_eq!(transaction.sender, wallet.address, "Both schemes share one address");
+    assert_eq!(transaction.signature.len(), 128, "BIP340 signatures are 64 bytes");
+    assert!(transaction.verify(CHAIN_ID));
+    assert!(!transaction.verify("chainrust-other"));
+
+    let mut relabelled = transaction.clone();
+    relabelled.scheme = SignatureScheme::Ecdsa;
+    assert!(!relabelled.verify(CHAIN_ID), "A Schnorr signature is not a valid ECDSA signature");
+
+    let mut tampered = transaction;
+    tampered.amount = Amount::new(51);
+    assert!(!tampered.verify(CHAIN_ID));
+}
+
+#[test]
+fn test_scheme_tag_serialization() {
+    let wallet = Wallet::new();
+    let ecdsa = serde_json::to_value(Transaction::new(CHAIN_ID, &receiver_address(), Amount::new(1), &wallet.private_key)).unwrap();
+    assert!(ecdsa.get("scheme").is_none(), "ECDSA transactions serialize as before");
+
+    let schnorr = schnorr_transfer(&wallet, 1);
+    let json = serde_json::to_value(&schnorr).unwrap();
+    assert_eq!(json["scheme"], "schnorr");
+    assert_eq!(serde_json::from_value::<Transaction>(json).unwrap(), schnorr);
+}
+
+#[test]
+fn test_verify_batch() {
+    let wallet = Wallet::new();
+    let mut block: Vec<Transaction> = (1..=5).map(|amount| schnorr_transfer(&wallet, amount)).collect();
+    block.push(Transaction::new(CHAIN_ID, &receiver_address(), Amount::new(6), &wallet.private_key));
+    assert!(Transaction::verify_batch(&block, CHAIN_ID));
+    assert!(Transaction::verify_batch(&[], CHAIN_ID));
+    assert!(!Transaction::verify_batch(&block, "chainrust-other"));
+
+    let mut forged = block.clone();
+    forged[2].signature = forged[3].signature.clone();
+    assert!(!Transaction::verify_batch(&forged, CHAIN_ID), "One bad signature fails the batch");
+
+    let mut stolen = block;
+    stolen[0].sender = Wallet::new().address;
+    assert!(!Transaction::verify_batch(&stolen, CHAIN_ID), "Batched signatures still bind the sender address");
+}
+
+#[test]
+fn test_verify_schnorr_batch() {
+    let wallets: Vec<Wallet> = (0..3).map(|_| Wallet::new()).collect();
+    let mut block: Vec<Transaction> = (1..=transaction::SCHNORR_BATCH_SIZE as u64 + 10)
+        .map(|amount| schnorr_transfer(&wallets[amount as usize % wallets.len()], amount))
+        .collect();
+    block.insert(7, Transaction::new(CHAIN_ID, &receiver_address(), Amount::new(1), &wallets[0].private_key));
+    assert!(Transaction::verify_batch(&block, CHAIN_ID), "Batches spanning several chunks and keys, mixed with ECDSA");
+
+    let messages: Vec<String> = block.iter().map(|tx| tx.signing_message()).collect();
+    let items: Vec<crypto::SchnorrItem> = block
+        .iter()
+        .zip(&messages)
+        .filter(|(tx, _)| tx.scheme == SignatureScheme::Schnorr)
+        .map(|(tx, message)| (message.as_str(), tx.signature.as_str(), tx.sender_public_key.as_str()))
+        .collect();
+    assert!(crypto::verify_schnorr_batch(&items));
+    assert!(crypto::verify_schnorr_batch(&items[..1]));
+    assert!(crypto::verify_schnorr_batch(&[]));
+
+    let mut swapped = items.clone();
+    swapped[0].0 = items[1].0;
+    assert!(!crypto::verify_schnorr_batch(&swapped), "A signature over another message fails the batch");
+
+    let mut tampered = block.clone();
+    let last = tampered.len() - 1;
+    let mut signature = hex::decode(&tampered[last].signature).unwrap();
+    signature[63] ^= 1;
+    tampered[last].signature = hex::encode(signature);
+    assert!(!Transaction::verify_batch(&tampered, CHAIN_ID), "A bad signature in the last chunk fails the block");
+
+    let mut malformed = block;
+    malformed[0].signature = "zz".into();
+    assert!(!Transaction::verify_batch(&malformed, CHAIN_ID));
+}
+
+#[test]
+fn test_time_lock_is_signed() {
+    let wallet = Wallet::new();
+    let plain = Transaction::new(CHAIN_ID, &receiver_address(), Amount::new(5), &wallet.private_key);
+    let locked = plain.clone().locked_until(TimeLock::Height(10), &wallet.private_key);
+    assert!(locked.verify(CHAIN_ID));
+    assert_ne!(locked.signing_message(), plain.signing_message());
+    assert_ne!(locked.id(), plain.id());
+
+    let mut stripped = locked.clone();
+    stripped.valid_after = None;
+    assert!(!stripped.verify(CHAIN_ID), "Removing the lock breaks the signature");
+    let mut moved = locked.clone();
+    moved.valid_after = Some(TimeLock::Height(1));
+    assert!(!moved.verify(CHAIN_ID));
+
+    let json = serde_json::to_value(&locked).unwrap();
+    assert_eq!(json["valid_after"], serde_json::json!({"height": 10}));
+    assert!(serde_json::to_value(&plain).unwrap().get("valid_after").is_none());
+}
+
+#[test]
+fn test_time_lock_passes_strictly_after() {
+    let wallet = Wallet::new();
+    let plain = Transaction::new(CHAIN_ID, &receiver_address(), Amount::new(5), &wallet.private_key);
+    assert!(plain.is_unlocked(0, 0));
+
+    let by_height = plain.clone().locked_until(TimeLock::Height(10), &wallet.private_key);
+    assert!(!by_height.is_unlocked(10, i64::MAX));
+    assert!(by_height.is_unlocked(11, 0));
+
+    let by_time = plain.locked_until(TimeLock::Timestamp(1_700_000_000), &wallet.private_key);
+    assert!(!by_time.is_unlocked(u64::MAX, 1_700_000_000));
+    assert!(by_time.is_unlocked(1, 1_700_000_001));
+}
+
+#[test]
+fn test_expiry_is_signed() {
+    let wallet = Wallet::new();
+    let expiring = Transaction::new(CHAIN_ID, &receiver_address(), Amount::new(5), &wallet.private_key).expiring_at(20, &wallet.private_key);
+    assert!(expiring.verify(CHAIN_ID));
+    assert!(!expiring.is_expired(19));
+    assert!(expiring.is_expired(20));
+
+    let mut extended = expiring.clone();
+    extended.expires_at_height = Some(1000);
+    assert!(!extended.verify(CHAIN_ID), "The expiry cannot be pushed back without the key");
+    assert_eq!(serde_json::to_value(&expiring).unwrap()["expires_at_height"], 20);
+}
+
+#[test]
+fn test_memo_is_signed_and_priced_per_byte() {
+    let wallet = Wallet::new();
+    let plain = Transaction::new(CHAIN_ID, &receiver_address(), Amount::new(5), &wallet.private_key);
+    assert_eq!(plain.memo_fee(Amount::new(3)), Some(Amount::ZERO));
+
+    let invoiced = plain.with_memo("INV-2024:0042", &wallet.private_key);
+    assert!(invoiced.verify(CHAIN_ID));
+    assert_eq!(invoiced.memo_fee(Amount::new(3)), Some(Amount::new(39)));
+    assert_eq!(invoiced.memo_fee(Amount::MAX), None);
+    assert!(invoiced.signing_message().ends_with(&hex::encode("INV-2024:0042")));
+
+    let mut edited = invoiced.clone();
+    edited.memo = Some("INV-2024:0043".to_string());
+    assert!(!edited.verify(CHAIN_ID));
+    assert_eq!(serde_json::to_value(&invoiced).unwrap()["memo"], "INV-2024:0042");
+}
+
+#[test]
+fn test_batch_outputs_are_signed() {
+    let wallet = Wallet::new();
+    let outputs = vec![
+        BatchOutput { receiver: receiver_address(), amount: Amount::new(7) },
+        BatchOutput { receiver: Wallet::new().address, amount: Amount::new(3) },
+    ];
+    let batch = Transaction::batch(CHAIN_ID, outputs, &wallet.private_key).unwrap();
+    assert!(batch.verify(CHAIN_ID));
+    assert_eq!(batch.receiver, wallet.address);
+    assert_eq!(batch.amount, Amount::new(10));
+    assert_eq!(serde_json::to_value(&batch).unwrap()["kind"]["batch"]["outputs"][0]["amount"], 7);
+
+    let mut redirected = batch.clone();
+    if let TransactionKind::Batch { outputs } = &mut redirected.kind {
+        outputs[1].receiver = Wallet::new().address;
+    }
+    assert!(!redirected.verify(CHAIN_ID), "Every output is covered by the signature");
+
+    let mut trimmed = batch;
+    if let TransactionKind::Batch { outputs } = &mut trimmed.kind {
+        outputs.pop();
+    }
+    assert!(!trimmed.verify(CHAIN_ID));
+}
@@ -0,0 +1,291 @@
+mod blockchain {
+    include!("../src/blockchain.rs");
+}
+mod block {
+    include!("../src/block.rs");
+}
+mod transaction {
+    include!("../src/transaction.rs");
+}
+mod wallet {
+    include!("../src/wallet.rs");
+}
+mod address {
+    include!("../src/address.rs");
+}
+mod crypto {
+    include!("../src/crypto.rs");
+}
+mod types {
+    include!("../src/types.rs");
+}
+mod multisig {
+    include!("../src/multisig.rs");
+}
+mod utxo {
+    include!("../src/utxo.rs");
+}
+mod script {
+    include!("../src/script.rs");
+}
+mod sigcache {
+    include!("../src/sigcache.rs");
+}
+mod staking {
+    include!("../src/staking.rs");
+}
+mod genesis {
+    include!("../src/genesis.rs");
+}
+mod htlc {
+    include!("../src/htlc.rs");
+}
+mod escrow {
+    include!("../src/escrow.rs");
+}
+mod token {
+    include!("../src/token.rs");
+}
+mod nft {
+    include!("../src/nft.rs");
+}
+mod contract {
+    include!("../src/contract.rs");
+}
+mod receipt {
+    include!("../src/receipt.rs");
+}
+
+use blockchain::Blockchain;
+use block::Block;
+use crypto::SignatureScheme;
+use genesis::{FaucetConfig, GenesisConfig, LedgerModel};
+use script::{Script, ScriptAuth};
+use transaction::{BatchOutput, TimeLock, Transaction, TransactionKind};
+use types::Amount;
+use utxo::{select_coins, OutPoint, UnspentOutput, Utxo};
+use wallet::Wallet;
+
+fn utxo_chain(funded: &[(&Wallet, u64)]) -> Blockchain {
+    let mut config = GenesisConfig { ledger: LedgerModel::Utxo, ..GenesisConfig::default() };
+    for (wallet, amount) in funded {
+        config.balances.insert(wallet.address.clone(), Amount::new(*amount));
+    }
+    Blockchain::from_genesis(config)
+}
+
+fn pay(wallet: &Wallet, amount: u64) -> BatchOutput {
+    BatchOutput { receiver: wallet.address.clone(), amount: Amount::new(amount) }
+}
+
+fn coins(blockchain: &Blockchain, wallet: &Wallet) -> Vec<OutPoint> {
+    blockchain.utxos.owned_by(&wallet.address).into_iter().map(|coin| coin.outpoint).collect()
+}
+
+fn coin(index: u32, amount: u64) -> UnspentOutput {
+    let outpoint = OutPoint { tx: types::Hash::digest(index.to_string()), index };
+    UnspentOutput { outpoint, output: Utxo { owner: Wallet::new().address, amount: Amount::new(amount) } }
+}
+
+#[test]
+fn test_outpoints_encode_as_strings() {
+    let outpoint = OutPoint { tx: types::Hash::digest("tx"), index: 3 };
+    let json = serde_json::to_value(&outpoint).unwrap();
+    assert_eq!(json, format!("{}:3", outpoint.tx));
+    assert_eq!(serde_json::from_value::<OutPoint>(json).unwrap(), outpoint);
+    assert!("not-a-hash:0".parse::<OutPoint>().is_err());
+    assert!(format!("{}", outpoint.tx).parse::<OutPoint>().is_err());
+
+    let alice = Wallet::new();
+    let blockchain = utxo_chain(&[(&alice, 10)]);
+    let reloaded: Blockchain = serde_json::from_str(&serde_json::to_string(&blockchain).unwrap()).unwrap();
+    assert_eq!(reloaded.utxos, blockchain.utxos);
+}
+
+#[test]
+fn test_coin_selection_prefers_large_coins() {
+    let coins = vec![coin(0, 5), coin(1, 50), coin(2, 20)];
+    let (selected, change) = select_coins(&coins, Amount::new(60)).unwrap();
+    assert_eq!(selected, vec![coins[1].outpoint.clone(), coins[2].outpoint.clone()]);
+    assert_eq!(change, Amount::new(10));
+
+    let (selected, change) = select_coins(&coins, Amount::new(50)).unwrap();
+    assert_eq!(selected, vec![coins[1].outpoint.clone()]);
+    assert_eq!(change, Amount::ZERO);
+
+    assert!(select_coins(&coins, Amount::new(76)).is_err());
+    assert!(select_coins(&[], Amount::ZERO).is_err());
+}
+
+#[test]
+fn test_genesis_allocations_become_outputs() {
+    let (alice, bob) = (Wallet::new(), Wallet::new());
+    let blockchain = utxo_chain(&[(&alice, 100), (&bob, 40)]);
+    assert_eq!(blockchain.ledger(), LedgerModel::Utxo);
+    assert!(blockchain.balances.is_empty());
+    assert_eq!(blockchain.utxos.len(), 2);
+    assert_eq!(blockchain.get_balance(&alice.address), Amount::new(100));
+    assert_eq!(blockchain.get_balance(&bob.address), Amount::new(40));
+    assert!(coins(&blockchain, &alice).iter().all(|outpoint| outpoint.tx == *blockchain.genesis_hash()));
+}
+
+#[test]
+fn test_spend_consumes_inputs_and_creates_outputs() {
+    let (alice, bob) = (Wallet::new(), Wallet::new());
+    let mut blockchain = utxo_chain(&[(&alice, 100)]);
+    let spend = Transaction::spend(blockchain.chain_id(), SignatureScheme::Ecdsa, coins(&blockchain, &alice), vec![pay(&bob, 30), pay(&alice, 65)], &alice.private_key)
+        .unwrap()
+        .with_memo("rent", &alice.private_key);
+    assert!(spend.verify(blockchain.chain_id()));
+    assert!(spend.signature.is_empty());
+    let id = spend.id();
+    blockchain.mine_block(vec![spend], None).unwrap();
+
+    assert_eq!(blockchain.chain[1].transactions.len(), 1);
+    // 100 in, 95 out, 4 bytes of memo fee and one left over are burned
+    assert_eq!(blockchain.get_balance(&alice.address), Amount::new(65));
+    assert_eq!(blockchain.get_balance(&bob.address), Amount::new(30));
+    assert_eq!(coins(&blockchain, &bob), vec![OutPoint { tx: id.clone(), index: 0 }]);
+    assert_eq!(coins(&blockchain, &alice), vec![OutPoint { tx: id, index: 1 }]);
+}
+
+#[test]
+fn test_outputs_cannot_be_spent_twice() {
+    let (alice, bob) = (Wallet::new(), Wallet::new());
+    let mut blockchain = utxo_chain(&[(&alice, 100)]);
+    let chain_id = blockchain.chain_id().to_string();
+    let inputs = coins(&blockchain, &alice);
+    let to_bob = Transaction::spend(&chain_id, SignatureScheme::Ecdsa, inputs.clone(), vec![pay(&bob, 100)], &alice.private_key).unwrap();
+    let to_self = Transaction::spend(&chain_id, SignatureScheme::Schnorr, inputs.clone(), vec![pay(&alice, 100)], &alice.private_key).unwrap();
+    let repeated = Transaction::spend(&chain_id, SignatureScheme::Ecdsa, vec![inputs[0].clone(), inputs[0].clone()], vec![pay(&bob, 150)], &alice.private_key).unwrap();
+    assert!(blockchain.validate_transaction(&repeated).is_err());
+    let overspent = Transaction::spend(&chain_id, SignatureScheme::Ecdsa, inputs, vec![pay(&bob, 101)], &alice.private_key).unwrap();
+    assert!(blockchain.validate_transaction(&overspent).is_err());
+
+    blockchain.mine_block(vec![to_bob, to_self.clone()], None).unwrap();
+    assert_eq!(blockchain.chain[1].transactions.len(), 1);
+    assert_eq!(blockchain.get_balance(&bob.address), Amount::new(100));
+
+    // A block from elsewhere spending the same output is rejected as a whole
+    let tip = blockchain.chain.last().unwrap().hash.clone();
+    let mut block = Block::new(&chain_id, 2, vec![to_self], tip);
+    block.timestamp = blockchain.chain[1].timestamp;
+    block.hash = block.calculate_hash();
+    assert!(blockchain.add_block(block).is_err());
+    assert_eq!(blockchain.get_balance(&bob.address), Amount::new(100));
+}
+
+#[test]
+fn test_every_input_needs_its_owners_signature() {
+    let (alice, bob, mallory) = (Wallet::new(), Wallet::new(), Wallet::new());
+    let blockchain = utxo_chain(&[(&alice, 100), (&bob, 50)]);
+    let chain_id = blockchain.chain_id();
+
+    let stolen = Transaction::spend(chain_id, SignatureScheme::Ecdsa, coins(&blockchain, &alice), vec![pay(&mallory, 100)], &mallory.private_key).unwrap();
+    assert!(stolen.verify(chain_id), "Signed, but by a key that does not own the output");
+    assert!(blockchain.validate_transaction(&stolen).is_err());
+
+    // Alice and Bob pool their coins; each signs only the input they own
+    let mut inputs = coins(&blockchain, &alice);
+    inputs.extend(coins(&blockchain, &bob));
+    let mut joint = Transaction::spend(chain_id, SignatureScheme::Ecdsa, inputs, vec![pay(&mallory, 150)], &alice.private_key).unwrap();
+    if let TransactionKind::Spend { inputs, .. } = &mut joint.kind {
+        inputs[1].public_key = bob.public_key.clone();
+    }
+    joint.sign(&alice.private_key);
+    assert!(!joint.verify(chain_id), "Bob has not signed yet");
+    joint.sign(&bob.private_key);
+    assert!(joint.verify(chain_id));
+    assert!(blockchain.validate_transaction(&joint).is_ok());
+
+    let mut redirected = joint.clone();
+    if let TransactionKind::Spend { outputs, .. } = &mut redirected.kind {
+        outputs[0].receiver = alice.address.clone();
+    }
+    assert!(!redirected.verify(chain_id));
+
+    let mut resigned = joint.clone();
+    if let TransactionKind::Spend { inputs, .. } = &mut resigned.kind {
+        inputs[0].signature = crypto::sign(SignatureScheme::Ecdsa, "other message", &alice.private_key);
+    }
+    assert!(!resigned.verify(chain_id));
+    assert_eq!(resigned.id(), joint.id(), "Input signatures are not part of the id");
+}
+
+#[test]
+fn test_each_ledger_rejects_the_others_transactions() {
+    let alice = Wallet::new();
+    let utxo = utxo_chain(&[(&alice, 100)]);
+    let transfer = Transaction::new(utxo.chain_id(), &Wallet::new().address, Amount::new(10), &alice.private_key);
+    assert!(utxo.validate_transaction(&transfer).is_err());
+    assert!(utxo.validate_transaction(&Transaction::bond(utxo.chain_id(), Amount::new(10), &alice.private_key)).is_err());
+
+    let mut account = Blockchain::new();
+    account.add_funds(&alice.address, Amount::new(100)).unwrap();
+    let genesis_coin = OutPoint { tx: account.genesis_hash().clone(), index: 0 };
+    let spend = Transaction::spend(account.chain_id(), SignatureScheme::Ecdsa, vec![genesis_coin], vec![pay(&alice, 10)], &alice.private_key).unwrap();
+    assert!(account.validate_transaction(&spend).is_err());
+    assert!(account.utxos.is_empty());
+}
+
+#[test]
+fn test_faucet_grants_create_outputs() {
+    let faucet = Wallet::new();
+    let alice = Wallet::new();
+    let config = GenesisConfig {
+        ledger: LedgerModel::Utxo,
+        faucet: Some(FaucetConfig { address: faucet.address.clone(), amount: Amount::new(100), cooldown_secs: 60, daily_cap: Amount::new(250) }),
+        ..GenesisConfig::default()
+    };
+    let mut blockchain = Blockchain::from_genesis(config);
+    let grant = Transaction::mint(blockchain.chain_id(), &alice.address, Amount::new(100), &faucet.private_key).expiring_at(100, &faucet.private_key);
+    blockchain.mine_block(vec![grant.clone()], None).unwrap();
+    assert_eq!(coins(&blockchain, &alice), vec![OutPoint { tx: grant.id(), index: 0 }]);
+    assert!(blockchain.validate_transaction(&grant).is_err(), "The same grant would recreate the outpoint");
+
+    let again = grant.with_nonce(1, &faucet.private_key);
+    blockchain.mine_block(vec![again], None).unwrap();
+    assert_eq!(blockchain.get_balance(&alice.address), Amount::new(200));
+}
+
+#[test]
+fn test_outputs_locked_by_scripts() {
+    let (alice, bob) = (Wallet::new(), Wallet::new());
+    // Bob can take the output after height 5
+    let locking: Script = format!("05 checklocktime {} checksig", bob.public_key).parse().unwrap();
+    let vault = locking.address();
+    let mut blockchain = utxo_chain(&[(&alice, 100)]);
+    let chain_id = blockchain.chain_id().to_string();
+    let fund = Transaction::spend(&chain_id, SignatureScheme::Ecdsa, coins(&blockchain, &alice), vec![BatchOutput { receiver: vault.clone(), amount: Amount::new(100) }], &alice.private_key).unwrap();
+    blockchain.mine_block(vec![fund], None).unwrap();
+    let locked = blockchain.utxos.owned_by(&vault)[0].outpoint.clone();
+
+    let mut claim = Transaction::spend(&chain_id, SignatureScheme::Ecdsa, vec![locked], vec![pay(&bob, 100)], &bob.private_key).unwrap();
+    if let TransactionKind::Spend { inputs, .. } = &mut claim.kind {
+        inputs[0].public_key = String::new();
+        inputs[0].signature = String::new();
+    }
+    claim.sender = vault.clone();
+    claim.receiver = vault.clone();
+    let unlock = |tx: &mut Transaction| {
+        let signature = crypto::sign(SignatureScheme::Ecdsa, &tx.signing_message(), &bob.private_key);
+        if let TransactionKind::Spend { inputs, .. } = &mut tx.kind {
+            inputs[0].script = Some(ScriptAuth { locking: locking.clone(), unlocking: signature.parse().unwrap() });
+        }
+    };
+    let mut early = claim.clone();
+    unlock(&mut early);
+    assert!(!early.verify(&chain_id), "The script requires a height lock");
+
+    claim.valid_after = Some(TimeLock::Height(5));
+    unlock(&mut claim);
+    assert!(claim.verify(&chain_id));
+    blockchain.mine_block(vec![claim.clone()], None).unwrap();
+    assert!(blockchain.chain[2].transactions.is_empty(), "Block 2 is before the lock");
+    for _ in 0..3 {
+        blockchain.mine_block(vec![], None).unwrap();
+    }
+    blockchain.mine_block(vec![claim], None).unwrap();
+    assert_eq!(blockchain.get_balance(&vault), Amount::ZERO);
+    assert_eq!(blockchain.get_balance(&bob.address), Amount::new(100));
+}
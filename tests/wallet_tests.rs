@@ -1,10 +1,13 @@
 mod wallet {
     include!("../src/wallet.rs");
 }
+mod address {
+    include!("../src/address.rs");
+}
 
+use address::Address;
 use wallet::Wallet;
 use secp256k1::{Secp256k1, PublicKey, SecretKey};
-use hex;
 
 #[test]
 fn test_wallet_new() {
@@ -20,4 +23,5 @@ fn test_wallet_new() {
     assert_eq!(public_key, derived_public_key, "Public key should match derived key");
     assert_eq!(wallet.private_key.len(), 64, "Private key should be 32 bytes (64 hex chars)");
     assert_eq!(wallet.public_key.len(), 66, "Public key should be 33 bytes (66 hex chars)");
+    assert_eq!(Address::from_public_key(&wallet.public_key), Some(wallet.address));
 }
\ No newline at end of file